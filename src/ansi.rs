@@ -21,12 +21,14 @@
 
 //! ANSI Terminal Stream Parsing.
 
+use alloc::borrow::ToOwned;
 use alloc::string::String;
+use alloc::vec::Vec;
 use core::convert::TryFrom;
 
-use vte::{Params, ParamsIter, Perform};
+use vte::{Params, Perform};
 
-use crate::cell::Cell;
+use crate::cell_buffer::LineAttr;
 use crate::color::{Color, NamedColor, Rgb888};
 
 /// Terminal modes.
@@ -67,6 +69,8 @@ pub enum Mode {
     LineFeedNewLine = 20,
     /// ?25
     ShowCursor = 25,
+    /// ?45
+    ReverseWrap = 45,
     /// ?1000
     ReportMouseClicks = 1000,
     /// ?1002
@@ -106,6 +110,7 @@ impl Mode {
                 7 => Mode::LineWrap,
                 12 => Mode::BlinkingCursor,
                 25 => Mode::ShowCursor,
+                45 => Mode::ReverseWrap,
                 1000 => Mode::ReportMouseClicks,
                 1002 => Mode::ReportCellMouseMotion,
                 1003 => Mode::ReportAllMouseMotion,
@@ -204,6 +209,12 @@ pub enum Attr {
     Foreground(Color),
     /// Set indexed background color.
     Background(Color),
+    /// Reset the foreground color to the default.
+    DefaultForeground,
+    /// Reset the background color to the default.
+    DefaultBackground,
+    /// Set underline color, or use the foreground color if `None`.
+    UnderlineColor(Option<Color>),
 }
 
 /// Type that handles actions from the parser.
@@ -244,6 +255,12 @@ pub trait Handler {
     /// Put `count` tabs.
     fn put_tab(&mut self, _count: u16) {}
 
+    /// Move forward `count` tabs, without writing to the cells passed over.
+    fn move_forward_tabs(&mut self, _count: u16) {}
+
+    /// Move backward `count` tabs.
+    fn move_backward_tabs(&mut self, _count: u16) {}
+
     /// Backspace `count` characters.
     fn backspace(&mut self) {}
 
@@ -253,12 +270,24 @@ pub trait Handler {
     /// Linefeed.
     fn linefeed(&mut self) {}
 
+    /// RI - Move the cursor up one row, scrolling down if it is at the top of the scrolling region.
+    fn reverse_index(&mut self) {}
+
+    /// Ring the bell.
+    fn bell(&mut self) {}
+
     /// Scroll up `rows` rows.
     fn scroll_up(&mut self, _rows: usize) {}
 
     /// Scroll down `rows` rows.
     fn scroll_down(&mut self, _rows: usize) {}
 
+    /// SL - Shift the content of every row of the scrolling region left by `cols` columns, within the left and right margins.
+    fn scroll_left(&mut self, _cols: usize) {}
+
+    /// SR - Shift the content of every row of the scrolling region right by `cols` columns, within the left and right margins.
+    fn scroll_right(&mut self, _cols: usize) {}
+
     /// Erase `count` chars in current line following cursor.
     ///
     /// Erase means resetting to the default state (default colors, no content,
@@ -271,12 +300,21 @@ pub trait Handler {
     /// to the right of the deleted things is shifted left.
     fn delete_chars(&mut self, _count: usize) {}
 
+    /// ICH - Insert `count` blank chars at the cursor, shifting the rest of the line right.
+    fn insert_blank(&mut self, _count: usize) {}
+
+    /// REP - Repeat the last character written `count` times.
+    fn repeat_char(&mut self, _count: usize) {}
+
     /// Save current cursor position.
     fn save_cursor_position(&mut self) {}
 
     /// Restore cursor position.
     fn restore_cursor_position(&mut self) {}
 
+    /// Set the line attribute of the cursor's row (DECDWL, DECDHL and DECSWL).
+    fn set_line_attr(&mut self, _attr: LineAttr) {}
+
     /// Clear current line.
     fn clear_line(&mut self, _mode: LineClearMode) {}
 
@@ -295,8 +333,230 @@ pub trait Handler {
     /// DECSTBM - Set the terminal scrolling region.
     fn set_scrolling_region(&mut self, _top: usize, _bottom: Option<usize>) {}
 
+    /// DECSCUSR - Set the shape of the cursor, and whether it blinks.
+    fn set_cursor_style(&mut self, _style: u16) {}
+
+    /// DECSLRM - Set the left and right margins.
+    fn set_margins(&mut self, _left: usize, _right: Option<usize>) {}
+
     /// Report device status.
     fn device_status(&mut self, _arg: usize) {}
+
+    /// Start of a DCS with its header.
+    fn dcs_hook(&mut self, _params: Vec<u16>, _intermediates: &[u8], _action: char) {}
+
+    /// A byte of DCS data.
+    fn dcs_put(&mut self, _byte: u8) {}
+
+    /// End of a DCS.
+    fn dcs_unhook(&mut self) {}
+
+    /// OSC to set window title.
+    fn set_title(&mut self, _title: Option<String>) {}
+
+    /// Push a title onto the stack.
+    fn push_title(&mut self) {}
+
+    /// Pop the last title from the stack.
+    fn pop_title(&mut self) {}
+
+    /// XTWINOPS 8, asking for the window to be resized to `rows` by `cols`.
+    fn request_resize(&mut self, _rows: usize, _cols: usize) {}
+
+    /// Start of bracketed pasted text.
+    fn paste_start(&mut self) {}
+
+    /// End of bracketed pasted text.
+    fn paste_end(&mut self) {}
+
+    /// An inline image was received.
+    fn inline_image(&mut self, _image: InlineImage) {}
+}
+
+/// The kind of a control string, whose payload is terminated by `ST` (`ESC \`).
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ControlString {
+    /// Operating System Command (`ESC ]`), which may also be terminated by `BEL`.
+    Osc,
+    /// Device Control String (`ESC P`).
+    Dcs,
+}
+
+/// A complete Device Control String (`ESC P ... ESC \`).
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct DcsString {
+    /// Numeric parameters.
+    pub params: Vec<u16>,
+    /// Intermediate bytes.
+    pub intermediates: Vec<u8>,
+    /// The final byte of the header, which selects the function, e.g. `q` for sixel graphics.
+    pub action: char,
+    /// The data following the header.
+    pub payload: Vec<u8>,
+}
+
+/// The protocol that an [`InlineImage`] was sent with.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ImageProtocol {
+    /// The kitty graphics protocol (`ESC _ G ... ESC \`).
+    Kitty,
+    /// iTerm2's inline images (`ESC ] 1337 ; File= ... BEL`).
+    Iterm,
+}
+
+/// An inline image control sequence, with its payload still encoded.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct InlineImage {
+    /// The protocol of the sequence.
+    pub protocol: ImageProtocol,
+    /// The `key=value` parameters, e.g. `a=T` for kitty or `width=10` for iTerm.
+    pub params: Vec<(String, String)>,
+    /// The base64 encoded payload. For kitty this may be one chunk of an image.
+    pub payload: Vec<u8>,
+}
+
+impl InlineImage {
+    /// Parse the content of an APC string, if it is a kitty graphics command: `G<key>=<value>,...;<payload>`
+    pub(crate) fn parse_kitty(apc: &[u8]) -> Option<Self> {
+        let command = apc.strip_prefix(b"G")?;
+        let (control, payload) = match command.iter().position(|&b| b == b';') {
+            Some(i) => (&command[..i], &command[i + 1..]),
+            None => (command, &[][..]),
+        };
+        Some(Self {
+            protocol: ImageProtocol::Kitty,
+            params: parse_key_values(control, b','),
+            payload: payload.to_vec(),
+        })
+    }
+
+    /// Parse the parameters of an OSC 1337, if it is an iTerm image: `File=<key>=<value>;...:<payload>`
+    pub(crate) fn parse_iterm(params: &[&[u8]]) -> Option<Self> {
+        let args = params[1..].join(&b';');
+        let args = args.strip_prefix(b"File=")?;
+        let (args, payload) = match args.iter().position(|&b| b == b':') {
+            Some(i) => (&args[..i], &args[i + 1..]),
+            None => (args, &[][..]),
+        };
+        Some(Self {
+            protocol: ImageProtocol::Iterm,
+            params: parse_key_values(args, b';'),
+            payload: payload.to_vec(),
+        })
+    }
+}
+
+fn parse_key_values(data: &[u8], separator: u8) -> Vec<(String, String)> {
+    data.split(|&b| b == separator)
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let pair = String::from_utf8_lossy(pair);
+            match pair.split_once('=') {
+                Some((key, value)) => (key.to_owned(), value.to_owned()),
+                None => (pair.into_owned(), String::new()),
+            }
+        })
+        .collect()
+}
+
+/// Collects Application Program Command strings (`ESC _ ... ESC \`), which the parser discards.
+#[derive(Debug, Default)]
+pub struct ApcCollector {
+    state: ApcState,
+    buf: Vec<u8>,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+enum ApcState {
+    #[default]
+    Ground,
+    Escape,
+    String,
+    StringEscape,
+}
+
+impl ApcCollector {
+    /// Advance with the next `byte` of input. Returns the payload of an APC string once it is terminated.
+    ///
+    /// Strings longer than `max_len` are dropped.
+    pub fn advance(&mut self, byte: u8, max_len: Option<usize>) -> Option<Vec<u8>> {
+        use ApcState::*;
+
+        self.state = match (self.state, byte) {
+            (_, C0::CAN | C0::SUB) => Ground,
+            (StringEscape, b'\\') => {
+                self.state = Ground;
+                return Some(core::mem::take(&mut self.buf));
+            }
+            (Escape | StringEscape, b'_') => {
+                self.buf.clear();
+                String
+            }
+            (String, C0::ESC) => StringEscape,
+            (_, C0::ESC) => Escape,
+            (String, _) if max_len.is_some_and(|max_len| self.buf.len() >= max_len) => {
+                self.buf.clear();
+                Ground
+            }
+            (String, _) => {
+                self.buf.push(byte);
+                String
+            }
+            _ => Ground,
+        };
+        None
+    }
+}
+
+/// Follows the input stream to find control strings whose payload is longer than a limit.
+///
+/// The parser keeps consuming a control string until it is terminated, so a runaway string would otherwise swallow all following output.
+#[derive(Debug, Default)]
+pub struct StringLimit {
+    state: StringLimitState,
+}
+
+#[derive(Debug, Default)]
+enum StringLimitState {
+    #[default]
+    Ground,
+    Escape,
+    String {
+        kind: ControlString,
+        len: usize,
+    },
+}
+
+impl StringLimit {
+    /// Advance with the next `byte` of input. Returns the kind of control string that was abandoned if its payload is now longer than `max_len`.
+    pub fn advance(&mut self, byte: u8, max_len: usize) -> Option<ControlString> {
+        use StringLimitState::*;
+
+        self.state = match (&self.state, byte) {
+            (_, C0::CAN | C0::SUB) => Ground,
+            (String { kind, .. }, C0::BEL) if *kind == ControlString::Osc => Ground,
+            (_, C0::ESC) => Escape,
+            (Escape, b']') => String {
+                kind: ControlString::Osc,
+                len: 0,
+            },
+            (Escape, b'P') => String {
+                kind: ControlString::Dcs,
+                len: 0,
+            },
+            (String { kind, len }, _) if *len >= max_len => {
+                let kind = *kind;
+                self.state = Ground;
+                return Some(kind);
+            }
+            (String { kind, len }, _) => String {
+                kind: *kind,
+                len: len + 1,
+            },
+            _ => Ground,
+        };
+        None
+    }
 }
 
 pub struct Performer<'a, H: Handler> {
@@ -323,26 +583,32 @@ impl<H: Handler> Perform for Performer<'_, H> {
             C0::BS => self.handler.backspace(),
             C0::CR => self.handler.carriage_return(),
             C0::LF | C0::VT | C0::FF => self.handler.linefeed(),
+            C0::BEL => self.handler.bell(),
             _ => debug!("[unhandled] execute byte={:02x}", byte),
         }
     }
 
     #[inline]
     fn hook(&mut self, params: &Params, intermediates: &[u8], ignore: bool, action: char) {
-        debug!(
-            "[unhandled hook] params={:?}, ints: {:?}, ignore: {:?}, action: {:?}",
-            params, intermediates, ignore, action
-        );
+        if ignore {
+            debug!(
+                "[unhandled hook] params={:?}, ints: {:?}, ignore: {:?}, action: {:?}",
+                params, intermediates, ignore, action
+            );
+            return;
+        }
+        let params = params.iter().map(|param| param[0]).collect();
+        self.handler.dcs_hook(params, intermediates, action);
     }
 
     #[inline]
     fn put(&mut self, byte: u8) {
-        debug!("[unhandled put] byte={:?}", byte);
+        self.handler.dcs_put(byte);
     }
 
     #[inline]
     fn unhook(&mut self) {
-        debug!("[unhandled unhook]");
+        self.handler.dcs_unhook();
     }
 
     #[inline]
@@ -358,7 +624,35 @@ impl<H: Handler> Perform for Performer<'_, H> {
             }
             debug!("[unhandled osc_dispatch]: [{}] at line {}", &buf, line!());
         }
-        unhandled(params);
+
+        if params.is_empty() || params[0].is_empty() {
+            return;
+        }
+
+        match params[0] {
+            // iTerm inline image.
+            b"1337" => match InlineImage::parse_iterm(params) {
+                Some(image) => self.handler.inline_image(image),
+                None => unhandled(params),
+            },
+
+            // Set window title.
+            b"0" | b"2" => {
+                if params.len() >= 2 {
+                    let title = params[1..]
+                        .iter()
+                        .map(|x| String::from_utf8_lossy(x))
+                        .collect::<Vec<_>>()
+                        .join(";")
+                        .trim()
+                        .to_owned();
+                    self.handler.set_title(Some(title));
+                    return;
+                }
+                unhandled(params);
+            }
+            _ => unhandled(params),
+        }
     }
 
     #[inline]
@@ -384,6 +678,8 @@ impl<H: Handler> Perform for Performer<'_, H> {
         }
 
         let handler = &mut self.handler;
+        // The parser always gives at least one parameter, which is 0 when there are none
+        let no_params = params.len() == 1 && params.iter().next() == Some(&[0][..]);
         let mut params_iter = params.iter();
         let mut next_param_or = |default: u16| {
             params_iter
@@ -400,6 +696,8 @@ impl<H: Handler> Perform for Performer<'_, H> {
             ('E', []) => handler.move_down_and_cr(next_param_or(1) as usize),
             ('F', []) => handler.move_up_and_cr(next_param_or(1) as usize),
             ('G', []) | ('`', []) => handler.goto_col(next_param_or(1) as usize - 1),
+            ('I', []) => handler.move_forward_tabs(next_param_or(1)),
+            ('Z', []) => handler.move_backward_tabs(next_param_or(1)),
             ('H', []) | ('f', []) => {
                 let y = next_param_or(1) as usize;
                 let x = next_param_or(1) as usize;
@@ -432,10 +730,15 @@ impl<H: Handler> Perform for Performer<'_, H> {
 
                 handler.clear_line(mode);
             }
+            ('@', []) => handler.insert_blank(next_param_or(1) as usize),
             ('P', []) => handler.delete_chars(next_param_or(1) as usize),
             ('S', []) => handler.scroll_up(next_param_or(1) as usize),
             ('T', []) => handler.scroll_down(next_param_or(1) as usize),
             ('X', []) => handler.erase_chars(next_param_or(1) as usize),
+            ('@', [b' ']) => handler.scroll_left(next_param_or(1) as usize),
+            ('A', [b' ']) => handler.scroll_right(next_param_or(1) as usize),
+            ('b', []) => handler.repeat_char(next_param_or(1) as usize),
+            ('q', [b' ']) => handler.set_cursor_style(next_param_or(0)),
             ('d', []) => handler.goto_line(next_param_or(1) as usize - 1),
             ('h', intermediates) => {
                 for param in params_iter.map(|param| param[0]) {
@@ -473,6 +776,33 @@ impl<H: Handler> Perform for Performer<'_, H> {
 
                 handler.set_scrolling_region(top, bottom);
             }
+            // SCOSC, from ANSI.SYS. With parameters, this is DECSLRM, which sets the left and right margins
+            ('s', []) if no_params => handler.save_cursor_position(),
+            ('s', []) => {
+                let left = next_param_or(1) as usize;
+                let right = params_iter
+                    .next()
+                    .map(|param| param[0] as usize)
+                    .filter(|&param| param != 0);
+
+                handler.set_margins(left, right);
+            }
+            ('u', []) if no_params => handler.restore_cursor_position(),
+            ('t', []) => match next_param_or(1) {
+                8 => {
+                    let rows = next_param_or(0) as usize;
+                    let cols = next_param_or(0) as usize;
+                    handler.request_resize(rows, cols);
+                }
+                22 => handler.push_title(),
+                23 => handler.pop_title(),
+                _ => unhandled!(),
+            },
+            ('~', []) => match next_param_or(0) {
+                200 => handler.paste_start(),
+                201 => handler.paste_end(),
+                _ => unhandled!(),
+            },
             _ => unhandled!(),
         }
     }
@@ -491,14 +821,33 @@ impl<H: Handler> Perform for Performer<'_, H> {
         match (byte, intermediates) {
             (b'7', []) => self.handler.save_cursor_position(),
             (b'8', []) => self.handler.restore_cursor_position(),
+            (b'M', []) => self.handler.reverse_index(),
+            (b'3', [b'#']) => self.handler.set_line_attr(LineAttr::DoubleHeightTop),
+            (b'4', [b'#']) => self.handler.set_line_attr(LineAttr::DoubleHeightBottom),
+            (b'5', [b'#']) => self.handler.set_line_attr(LineAttr::Normal),
+            (b'6', [b'#']) => self.handler.set_line_attr(LineAttr::DoubleWidth),
             _ => unhandled!(),
         }
     }
 }
 
+/// Apply the SGR `params` to `handler`, as if they were received in a `CSI … m` sequence without subparameters
+pub(crate) fn apply_sgr_parameters<H: Handler>(handler: &mut H, params: &[u16]) {
+    if params.is_empty() {
+        handler.terminal_attribute(Attr::Reset);
+        return;
+    }
+    let mut params_iter = params.iter().map(core::slice::from_ref);
+    attrs_from_sgr_parameters(&mut params_iter, |attr| match attr {
+        Some(attr) => handler.terminal_attribute(attr),
+        None => debug!("[unhandled] SGR parameters {:?}", params),
+    });
+}
+
 #[inline]
-fn attrs_from_sgr_parameters<F>(params: &mut ParamsIter<'_>, mut handler: F)
+fn attrs_from_sgr_parameters<'p, I, F>(params: &mut I, mut handler: F)
 where
+    I: Iterator<Item = &'p [u16]>,
     F: FnMut(Option<Attr>),
 {
     while let Some(param) = params.next() {
@@ -542,7 +891,7 @@ where
 
                 parse_sgr_color(&mut iter).map(Attr::Foreground)
             }
-            [39] => Some(Attr::Foreground(Cell::default().fg)),
+            [39] => Some(Attr::DefaultForeground),
             [40] => Some(Attr::Background(Color::Named(NamedColor::Black))),
             [41] => Some(Attr::Background(Color::Named(NamedColor::Red))),
             [42] => Some(Attr::Background(Color::Named(NamedColor::Green))),
@@ -562,7 +911,19 @@ where
 
                 parse_sgr_color(&mut iter).map(Attr::Background)
             }
-            [49] => Some(Attr::Background(Cell::default().bg)),
+            [49] => Some(Attr::DefaultBackground),
+            [58] => {
+                let mut iter = params.map(|param| param[0]);
+                parse_sgr_color(&mut iter).map(|color| Attr::UnderlineColor(Some(color)))
+            }
+            [58, params @ ..] => {
+                let rgb_start = if params.len() > 4 { 2 } else { 1 };
+                let rgb_iter = params[rgb_start..].iter().copied();
+                let mut iter = core::iter::once(params[0]).chain(rgb_iter);
+
+                parse_sgr_color(&mut iter).map(|color| Attr::UnderlineColor(Some(color)))
+            }
+            [59] => Some(Attr::UnderlineColor(None)),
             [90] => Some(Attr::Foreground(Color::Named(NamedColor::BrightBlack))),
             [91] => Some(Attr::Foreground(Color::Named(NamedColor::BrightRed))),
             [92] => Some(Attr::Foreground(Color::Named(NamedColor::BrightGreen))),
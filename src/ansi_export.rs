@@ -0,0 +1,158 @@
+use alloc::string::String;
+use core::fmt::Write;
+
+use embedded_graphics::pixelcolor::RgbColor;
+
+use crate::Console;
+use crate::cell::{Cell, Flags};
+use crate::cell_buffer::LineAttr;
+use crate::color::Color;
+use crate::style::{DrawCell, Style};
+
+/// The flags that are written by [`Console::to_ansi`], and their SGR parameters
+const SGR_FLAGS: [(Flags, u8); 7] = [
+    (Flags::BOLD, 1),
+    (Flags::DIM, 2),
+    (Flags::ITALIC, 3),
+    (Flags::UNDERLINE, 4),
+    (Flags::INVERSE, 7),
+    (Flags::HIDDEN, 8),
+    (Flags::STRIKEOUT, 9),
+];
+
+/// The flags of `cell` that are written by [`Console::to_ansi`]
+fn sgr_flags(cell: &Cell) -> Flags {
+    SGR_FLAGS
+        .iter()
+        .filter(|(flag, _)| cell.flags.contains(*flag))
+        .fold(Flags::empty(), |flags, (flag, _)| flags | *flag)
+}
+
+/// Write the SGR parameters that select `color`, where `base` is the parameter for the named colors 0 to 7 and `extended` the one for indexed and RGB colors
+fn write_color(out: &mut String, color: Color, base: u8, extended: u8) {
+    let _ = match color {
+        Color::Named(named) if (named as u8) < 8 => write!(out, ";{}", base + named as u8),
+        // The bright colors are 60 above the others
+        Color::Named(named) => write!(out, ";{}", base + 60 + named as u8 - 8),
+        Color::Indexed(index) => write!(out, ";{};5;{}", extended, index),
+        Color::RGB(rgb) => write!(out, ";{};2;{};{};{}", extended, rgb.r(), rgb.g(), rgb.b()),
+    };
+}
+
+/// Write the SGR control sequence that selects the attributes of `cell`, starting from a reset to `default`
+fn write_sgr(out: &mut String, cell: &Cell, default: &Cell) {
+    out.push_str("\x1b[0");
+    for (flag, parameter) in SGR_FLAGS {
+        if cell.flags.contains(flag) {
+            let _ = write!(out, ";{}", parameter);
+        }
+    }
+    if cell.fg != default.fg {
+        write_color(out, cell.fg, 30, 38);
+    }
+    if cell.bg != default.bg {
+        write_color(out, cell.bg, 40, 48);
+    }
+    if let Some(color) = cell.underline_color {
+        // There are no short parameters for underline colors
+        let color = match color {
+            Color::Named(named) => Color::Indexed(named as u8),
+            color => color,
+        };
+        write_color(out, color, 58, 58);
+    }
+    out.push('m');
+}
+
+impl<'a, C, F> Console<'a, C, F>
+where
+    Style<'a, C, F>: DrawCell<C>,
+{
+    /// The control sequences and text that reproduce the screen on another terminal, e.g. to send a frame elsewhere or to look at it while debugging, or on another [`Console`] of the same size.
+    ///
+    /// Each row is written from its start, after a CUP control sequence (`CSI row ; 1 H`) and the ESC `#` sequence for its [`LineAttr`]. A row that was wrapped onto from the one above is instead reached by letting the text wrap, so that it is wrapped on the other terminal too, and its ESC `#` sequence follows its first character. The last row can't be wrapped this way, so its wrap is lost. An SGR control sequence that resets the attributes and selects the new ones is written wherever they change, and colors that are the same as those of the [default cell](crate::CellBuffer::default_cell) are left to the terminal's defaults. Underline colors are written as indexed or RGB colors. The cursor is moved back to its position at the end, after resetting the attributes. Control characters in cells are written as spaces.
+    pub fn to_ansi(&self) -> String {
+        let mut out = String::new();
+        let default = self.inner.buf.default_cell();
+        let mut current: Option<Cell> = None;
+        let mut wrapped = false;
+        for (row, cells) in self.rows_iter().enumerate() {
+            let line_attr = match self.line_attr(row) {
+                LineAttr::Normal => "\x1b#5",
+                LineAttr::DoubleWidth => "\x1b#6",
+                LineAttr::DoubleHeightTop => "\x1b#3",
+                LineAttr::DoubleHeightBottom => "\x1b#4",
+            };
+            if !wrapped {
+                let _ = write!(out, "\x1b[{};1H{}", row + 1, line_attr);
+            }
+            // The second half of a wide character is written by the terminal
+            for (i, cell) in cells
+                .iter()
+                .filter(|cell| !cell.flags.contains(Flags::WIDE_CHAR_SPACER))
+                .enumerate()
+            {
+                let same_style = current.is_some_and(|current| {
+                    current.fg == cell.fg
+                        && current.bg == cell.bg
+                        && current.underline_color == cell.underline_color
+                        && sgr_flags(&current) == sgr_flags(cell)
+                });
+                if !same_style {
+                    write_sgr(&mut out, cell, &default);
+                    current = Some(*cell);
+                }
+                out.push(if cell.c.is_control() { ' ' } else { cell.c });
+                // A wrapped row is only reached once its first character has been written
+                if wrapped && i == 0 {
+                    out.push_str(line_attr);
+                }
+            }
+            wrapped = cells
+                .last()
+                .is_some_and(|cell| cell.flags.contains(Flags::WRAPLINE));
+        }
+        let (row, col) = self.get_cursor_position();
+        let _ = write!(out, "\x1b[0m\x1b[{};{}H", row + 1, col + 1);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cell::Flags;
+    use crate::{Console, LineAttr, Style};
+    use core::fmt::Write;
+
+    #[test]
+    fn test_to_ansi() {
+        let mut console = Console::new(12, 3, Style::default());
+        console
+            .write_str("\x1b[1;31mred\x1b[0m \x1b[44;38;2;1;2;3mrgb\x1b[7;38;5;100mix")
+            .unwrap();
+        console
+            .write_str("\r\n\x1b[0;4;58;5;3;97mbright\x1b[m\r\n\x1b#6wide\x1b[2;5H")
+            .unwrap();
+
+        let ansi = console.to_ansi();
+        assert!(ansi.starts_with("\x1b[1;1H\x1b#5\x1b[0;1;31mred\x1b[0m \x1b[0;38;2;1;2;3;44mrgb"));
+
+        let mut copy = Console::new(12, 3, Style::default());
+        copy.write_str("garbage\x1b[45m").unwrap();
+        copy.write_str(&ansi).unwrap();
+        assert_eq!(copy.snapshot(), console.snapshot());
+        assert_eq!(copy.line_attr(2), console.line_attr(2));
+        assert_eq!(copy.get_cursor_position(), (1, 4));
+
+        // Wrapped rows stay wrapped
+        let mut console = Console::new(4, 3, Style::default());
+        console.write_str("abcdef\r\n\x1b#6xy").unwrap();
+        console.set_line_attr(1, LineAttr::DoubleWidth);
+        let mut copy = Console::new(4, 3, Style::default());
+        copy.write_str(&console.to_ansi()).unwrap();
+        assert_eq!(copy.snapshot(), console.snapshot());
+        assert!(copy[(0, 3)].flags().contains(Flags::WRAPLINE));
+        assert_eq!(copy.line_attr(1), console.line_attr(1));
+        assert_eq!(copy.line_attr(2), console.line_attr(2));
+    }
+}
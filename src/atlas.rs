@@ -0,0 +1,219 @@
+use embedded_graphics::image::GetPixel;
+use embedded_graphics::mono_font::mapping::StrGlyphMapping;
+use embedded_graphics::pixelcolor::Rgb888;
+use embedded_graphics::prelude::*;
+use tinybmp::{Bmp, ParseError};
+
+use alloc::vec::Vec;
+
+use crate::cell::Cell;
+use crate::style::{ColorInterpolate, DrawCell, Style};
+use crate::text::Mono8BitFont;
+
+/// A [`Mono8BitFont`] loaded from an atlas of prerendered glyphs, rather than rasterized from a font file, so that `fontdue` isn't needed.
+///
+/// The atlas is an image of glyphs on a grid of cells of the same size, from left to right and then top to bottom, in the order of a glyph list like that of [`Mono8BitFont::from_font_bytes`]. Each pixel is the coverage of the glyph, from 0 for the background to 255 for the foreground. The `?` glyph is drawn for characters that aren't in the list, or the first glyph if there is no `?`.
+pub struct BitmapAtlasFont {
+    font: Mono8BitFont,
+}
+
+impl BitmapAtlasFont {
+    /// Load a font from the coverage values of an atlas that is `atlas_width` pixels wide, one byte per pixel in rows from the top.
+    ///
+    /// Each glyph is `character_size` pixels, and the baseline of the text is `baseline` pixels below the top of each glyph. Glyphs that are cut off at the end of the atlas are blank where it ends.
+    pub fn from_coverage(
+        atlas: &[u8],
+        atlas_width: u32,
+        character_size: Size,
+        glyphs: &'static str,
+        baseline: u32,
+    ) -> Self {
+        Self::from_pixels(
+            |point| {
+                let index = point.y as usize * atlas_width as usize + point.x as usize;
+                (point.x < atlas_width as i32)
+                    .then(|| atlas.get(index).copied())
+                    .flatten()
+            },
+            atlas_width,
+            character_size,
+            glyphs,
+            baseline,
+        )
+    }
+
+    /// Load a font from an atlas in a BMP file, as with [`BitmapAtlasFont::from_coverage`], where the brightness of each pixel is its coverage.
+    ///
+    /// White glyphs on a black background are drawn in the foreground color on the background color. Returns an error if `bmp` is not a BMP file that can be read.
+    pub fn from_bmp(
+        bmp: &[u8],
+        character_size: Size,
+        glyphs: &'static str,
+        baseline: u32,
+    ) -> Result<Self, ParseError> {
+        let bmp = Bmp::<Rgb888>::from_slice(bmp)?;
+        Ok(Self::from_pixels(
+            |point| {
+                // Integer approximation of the luma of the color
+                bmp.pixel(point).map(|color| {
+                    let luma =
+                        77 * color.r() as u32 + 150 * color.g() as u32 + 29 * color.b() as u32;
+                    (luma >> 8) as u8
+                })
+            },
+            bmp.size().width,
+            character_size,
+            glyphs,
+            baseline,
+        ))
+    }
+
+    /// Copy each glyph out of an atlas whose coverage at a point is given by `pixel`
+    fn from_pixels(
+        pixel: impl Fn(Point) -> Option<u8>,
+        atlas_width: u32,
+        character_size: Size,
+        glyphs: &'static str,
+        baseline: u32,
+    ) -> Self {
+        let (width, height) = (character_size.width, character_size.height);
+        let columns = (atlas_width / width.max(1)).max(1) as usize;
+        let glyph_mapping = StrGlyphMapping::new(glyphs, 0);
+        let replacement = glyph_mapping.chars().position(|c| c == '?').unwrap_or(0);
+        let glyph_mapping = StrGlyphMapping::new(glyphs, replacement);
+
+        let mut rasterized = Vec::with_capacity((width * height) as usize * glyphs.len());
+        for glyph in 0..glyph_mapping.chars().count() {
+            let origin = Point::new(
+                (glyph % columns) as i32 * width as i32,
+                (glyph / columns) as i32 * height as i32,
+            );
+            for y in 0..height as i32 {
+                for x in 0..width as i32 {
+                    rasterized.push(pixel(origin + Point::new(x, y)).unwrap_or(0));
+                }
+            }
+        }
+        Self {
+            font: Mono8BitFont::from_rasterized(
+                rasterized,
+                character_size,
+                glyphs.into(),
+                replacement,
+                baseline,
+            ),
+        }
+    }
+
+    /// Get the size of the characters in the font.
+    pub fn character_size(&self) -> Size {
+        self.font.character_size()
+    }
+
+    /// The coverage values of the glyph for `c`, as with [`Mono8BitFont::glyph_coverage`]
+    pub fn glyph_coverage(&self, c: char) -> Option<(&[u8], Size)> {
+        self.font.glyph_coverage(c)
+    }
+
+    /// The glyphs as a [`Mono8BitFont`], e.g. to draw text with a [`Mono8BitTextStyle`](crate::Mono8BitTextStyle)
+    pub fn as_mono_8bit_font(&self) -> &Mono8BitFont {
+        &self.font
+    }
+}
+
+impl<'a, C: Copy> DrawCell<C> for Style<'a, C, BitmapAtlasFont> {
+    fn draw_cell<D, P>(
+        &self,
+        cell: &Cell,
+        row: usize,
+        col: usize,
+        display: &mut D,
+    ) -> Result<(), <D as DrawTarget>::Error>
+    where
+        D: DrawTarget<Color = P>,
+        P: PixelColor + From<C> + ColorInterpolate,
+    {
+        // Glyphs are drawn like those of any other `Mono8BitFont`
+        let style = Style {
+            font: &self.font.font,
+            font_bold: &self.font_bold.font,
+            color_to_pixel: self.color_to_pixel,
+            dim_color: self.dim_color,
+            offset: self.offset,
+            rotation: self.rotation,
+            snap_to_named_colors: self.snap_to_named_colors,
+            resolved_colors: self.resolved_colors,
+        };
+        style.draw_cell(cell, row, col, display)
+    }
+
+    fn cell_size(&self) -> Size {
+        self.font.character_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Console;
+    use crate::style::{color_to_rgb, dim_rgb};
+    use crate::test_display::Framebuffer;
+    use core::fmt::Write;
+
+    /// A 24-bit BMP file of `width` by `height` gray pixels with the brightness of `values`
+    fn bmp(width: u32, height: u32, values: &[u8]) -> Vec<u8> {
+        let row_bytes = (width * 3).next_multiple_of(4);
+        let image_bytes = row_bytes * height;
+        let mut file = Vec::new();
+        file.extend_from_slice(b"BM");
+        for value in [54 + image_bytes, 0, 54, 40, width, height] {
+            file.extend_from_slice(&value.to_le_bytes());
+        }
+        file.extend_from_slice(&1u16.to_le_bytes());
+        file.extend_from_slice(&24u16.to_le_bytes());
+        for value in [0, image_bytes, 0, 0, 0, 0] {
+            file.extend_from_slice(&value.to_le_bytes());
+        }
+        // Rows are stored from the bottom
+        for row in values.chunks(width as usize).rev() {
+            for &value in row {
+                file.extend_from_slice(&[value; 3]);
+            }
+            file.resize(file.len() + (row_bytes - width * 3) as usize, 0);
+        }
+        file
+    }
+
+    #[test]
+    fn test_bitmap_atlas_font() {
+        // Two glyphs of 2 by 2 pixels, side by side
+        #[rustfmt::skip]
+        let atlas = [
+            255, 0, 0, 128,
+            0, 255, 128, 0,
+        ];
+        let font = BitmapAtlasFont::from_bmp(&bmp(4, 2, &atlas), Size::new(2, 2), "ab", 1).unwrap();
+        assert_eq!(font.character_size(), Size::new(2, 2));
+        assert_eq!(
+            font.glyph_coverage('b'),
+            Some((&[0, 128, 128, 0][..], Size::new(2, 2)))
+        );
+        assert_eq!(font.glyph_coverage('c'), None);
+        let raw = BitmapAtlasFont::from_coverage(&atlas, 4, Size::new(2, 2), "ab", 1);
+        assert_eq!(raw.glyph_coverage('a'), font.glyph_coverage('a'));
+
+        let style = Style::new(&font, &font, color_to_rgb, dim_rgb);
+        let mut console = Console::new(2, 1, style);
+        let mut display = Framebuffer::new(Size::new(4, 2), Rgb888::new(1, 2, 3));
+        console.write_str("a").unwrap();
+        console.draw(&mut display).unwrap();
+        let (fg, bg) = (
+            color_to_rgb(Cell::default().fg()),
+            color_to_rgb(Cell::default().bg()),
+        );
+        assert_eq!(
+            [0, 1].map(|y| [0, 1].map(|x| display.pixel(x, y))),
+            [[fg, bg], [bg, fg]]
+        );
+    }
+}
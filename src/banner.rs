@@ -0,0 +1,177 @@
+use alloc::string::String;
+use core::fmt::Write;
+
+use crate::Console;
+use crate::ansi::Handler;
+use crate::cell::Flags;
+use crate::cell_buffer::LineAttr;
+use crate::style::{DrawCell, Style};
+
+/// How [`Console::write_ansi_banner`] draws text
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum BannerStyle {
+    /// Characters 3 columns wide and 3 rows tall, drawn with block elements, with a blank column between them. Letters are shown in upper case.
+    #[default]
+    Block3Rows,
+    /// Bold characters on a [double width](LineAttr::DoubleWidth) row
+    Bold1Row,
+}
+
+/// The rows of each character of [`BannerStyle::Block3Rows`]. Other characters are drawn blank.
+const BLOCK_GLYPHS: [(char, [&str; 3]); 62] = [
+    ('A', ["▄▀▄", "█▀█", "▀ ▀"]),
+    ('B', ["█▀▄", "█▀▄", "▀▀ "]),
+    ('C', ["▄▀▀", "█  ", " ▀▀"]),
+    ('D', ["█▀▄", "█ █", "▀▀ "]),
+    ('E', ["█▀▀", "█▀ ", "▀▀▀"]),
+    ('F', ["█▀▀", "█▀ ", "▀  "]),
+    ('G', ["▄▀▀", "█ █", " ▀▀"]),
+    ('H', ["█ █", "█▀█", "▀ ▀"]),
+    ('I', ["▀█▀", " █ ", "▀▀▀"]),
+    ('J', ["  █", "▄ █", " ▀ "]),
+    ('K', ["█ █", "█▀▄", "▀ ▀"]),
+    ('L', ["█  ", "█  ", "▀▀▀"]),
+    ('M', ["█▄█", "█▀█", "▀ ▀"]),
+    ('N', ["█▀▄", "█ █", "▀ ▀"]),
+    ('O', ["▄▀▄", "█ █", " ▀ "]),
+    ('P', ["█▀▄", "█▀ ", "▀  "]),
+    ('Q', ["▄▀▄", "█▄█", " ▀▀"]),
+    ('R', ["█▀▄", "█▀▄", "▀ ▀"]),
+    ('S', ["▄▀▀", " ▀▄", "▀▀ "]),
+    ('T', ["▀█▀", " █ ", " ▀ "]),
+    ('U', ["█ █", "█ █", "▀▀▀"]),
+    ('V', ["█ █", "█ █", " ▀ "]),
+    ('W', ["█ █", "███", "▀ ▀"]),
+    ('X', ["█ █", "▄▀▄", "▀ ▀"]),
+    ('Y', ["█ █", " █ ", " ▀ "]),
+    ('Z', ["▀▀█", "▄▀ ", "▀▀▀"]),
+    ('0', ["█▀█", "█ █", "▀▀▀"]),
+    ('1', ["▄█ ", " █ ", "▀▀▀"]),
+    ('2', ["▀▀█", "█▀▀", "▀▀▀"]),
+    ('3', ["▀▀█", "▀▀█", "▀▀▀"]),
+    ('4', ["█ █", "▀▀█", "  ▀"]),
+    ('5', ["█▀▀", "▀▀█", "▀▀▀"]),
+    ('6', ["█▀▀", "█▀█", "▀▀▀"]),
+    ('7', ["▀▀█", "  █", "  ▀"]),
+    ('8', ["█▀█", "█▀█", "▀▀▀"]),
+    ('9', ["█▀█", "▀▀█", "▀▀▀"]),
+    (' ', ["   ", "   ", "   "]),
+    ('!', [" █ ", " ▀ ", " ▀ "]),
+    ('"', ["█ █", "   ", "   "]),
+    ('#', ["█▄█", "█▄█", "▀ ▀"]),
+    ('%', ["▀ █", "▄▀ ", "▀ ▀"]),
+    ('&', ["▄▀▄", "▄▀▄", " ▀▀"]),
+    ('\'', [" █ ", "   ", "   "]),
+    ('(', [" ▄▀", " █ ", "  ▀"]),
+    (')', ["▀▄ ", " █ ", "▀  "]),
+    ('*', ["▄ ▄", "▄▀▄", "   "]),
+    ('+', [" ▄ ", "▀█▀", "   "]),
+    (',', ["   ", " ▄ ", "▀  "]),
+    ('-', ["   ", "▀▀▀", "   "]),
+    ('.', ["   ", "   ", " ▀ "]),
+    ('/', ["  █", "▄▀ ", "▀  "]),
+    (':', [" ▄ ", " ▄ ", "   "]),
+    (';', [" ▄ ", " ▄ ", "▀  "]),
+    ('<', [" ▄▀", "▀▄ ", "  ▀"]),
+    ('=', ["▄▄▄", "▄▄▄", "   "]),
+    ('>', ["▀▄ ", " ▄▀", "▀  "]),
+    ('?', ["▀▀▄", " ▀ ", " ▀ "]),
+    ('@', ["▄▀▄", "█▀▀", " ▀▀"]),
+    ('[', ["█▀ ", "█  ", "▀▀ "]),
+    (']', [" ▀█", "  █", " ▀▀"]),
+    ('_', ["   ", "   ", "▀▀▀"]),
+    ('|', [" █ ", " █ ", " ▀ "]),
+];
+
+/// The rows of `c` in [`BannerStyle::Block3Rows`]
+fn block_glyph(c: char) -> [&'static str; 3] {
+    let c = c.to_ascii_uppercase();
+    BLOCK_GLYPHS
+        .iter()
+        .find(|(glyph, _)| *glyph == c)
+        .map_or(["   "; 3], |(_, rows)| *rows)
+}
+
+impl<'a, C, F> Console<'a, C, F>
+where
+    Style<'a, C, F>: DrawCell<C>,
+{
+    /// Write `text` in large letters from the cursor, e.g. for a title on a splash screen.
+    ///
+    /// The banner is 3 rows tall with [`BannerStyle::Block3Rows`], and 1 with [`BannerStyle::Bold1Row`]. The current attributes are used, and text that doesn't fit on the console is cut off. The cursor is left in its original column, on the row after the banner, and the console scrolls if the banner doesn't fit below the cursor.
+    pub fn write_ansi_banner(&mut self, text: &str, style: BannerStyle) {
+        let (_, col) = self.get_cursor_position();
+        let available = self.columns() - col;
+        match style {
+            BannerStyle::Block3Rows => {
+                for glyph_row in 0..3 {
+                    let mut line = String::new();
+                    for (i, c) in text.chars().enumerate() {
+                        if i > 0 {
+                            line.push(' ');
+                        }
+                        line.push_str(block_glyph(c)[glyph_row]);
+                    }
+                    self.write_banner_line(&line, available, col);
+                }
+            }
+            BannerStyle::Bold1Row => {
+                let (row, _) = self.get_cursor_position();
+                self.set_line_attr(row, LineAttr::DoubleWidth);
+                let flags = self.inner.temp.flags;
+                self.inner.temp.flags.insert(Flags::BOLD);
+                // Only the left half of a double width row is shown
+                self.write_banner_line(text, (self.columns() / 2).saturating_sub(col), col);
+                self.inner.temp.flags = flags;
+            }
+        }
+    }
+
+    /// Write the first `width` characters of `line`, then move to `col` of the next row
+    fn write_banner_line(&mut self, line: &str, width: usize, col: usize) {
+        for c in line.chars().take(width) {
+            self.inner.input(c);
+        }
+        let _ = self.write_str("\r\n");
+        let (row, _) = self.get_cursor_position();
+        self.inner.goto(row, col);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_display::row_text;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_block_banner() {
+        let mut console = Console::new(10, 4, Style::default());
+        console.write_str("\x1b[2;2H").unwrap();
+        console.write_ansi_banner("Hi!", BannerStyle::Block3Rows);
+
+        let rows = console.rows_iter().map(row_text).collect::<Vec<_>>();
+        // The banner scrolled the console up a row, and the `!` is cut off
+        assert_eq!(
+            rows,
+            [" █ █ ▀█▀  ", " █▀█  █   ", " ▀ ▀ ▀▀▀  ", "          "]
+        );
+        assert_eq!(console.get_cursor_position(), (3, 1));
+        assert_eq!(block_glyph('h'), block_glyph('H'));
+        assert_eq!(block_glyph('~'), ["   "; 3]);
+    }
+
+    #[test]
+    fn test_bold_banner() {
+        let mut console = Console::new(10, 3, Style::default());
+        console.write_str("\r\n").unwrap();
+        console.write_ansi_banner("Title", BannerStyle::Bold1Row);
+
+        let row = console.rows_iter().nth(1).unwrap();
+        assert_eq!(row_text(row), "Title     ");
+        assert_eq!(row[0].flags(), Flags::BOLD);
+        assert_eq!(console.line_attr(1), LineAttr::DoubleWidth);
+        assert_eq!(console.inner.temp.flags, Flags::empty());
+        assert_eq!(console.get_cursor_position(), (2, 0));
+    }
+}
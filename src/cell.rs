@@ -34,24 +34,60 @@ bitflags::bitflags! {
     }
 }
 
-/// A character on the screen
+/// A character on the screen, with its colors and [`Flags`].
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub struct Cell {
     pub(crate) c: char,
     pub(crate) fg: Color,
     pub(crate) bg: Color,
+    pub(crate) underline_color: Option<Color>,
     pub(crate) flags: Flags,
     // Number of times we need to flush this cell
     pub(crate) to_flush: usize,
 }
 
 impl Cell {
-    pub(crate) fn just_bg(&self) -> Self {
+    /// Create a new cell. It will be drawn on the next flush.
+    pub fn new(c: char, fg: Color, bg: Color, flags: Flags) -> Self {
         Self {
-            bg: self.bg,
-            ..Default::default()
+            c,
+            fg,
+            bg,
+            underline_color: None,
+            flags,
+            to_flush: 1,
         }
     }
+
+    /// The character displayed in the cell.
+    pub fn c(&self) -> char {
+        self.c
+    }
+
+    /// The foreground color.
+    pub fn fg(&self) -> Color {
+        self.fg
+    }
+
+    /// The background color.
+    pub fn bg(&self) -> Color {
+        self.bg
+    }
+
+    /// The color of the underline, if it differs from the foreground color.
+    pub fn underline_color(&self) -> Option<Color> {
+        self.underline_color
+    }
+
+    /// The graphical rendition flags.
+    pub fn flags(&self) -> Flags {
+        self.flags
+    }
+
+    /// Whether the cell still needs to be drawn.
+    pub fn is_dirty(&self) -> bool {
+        self.to_flush > 0
+    }
 }
 
 impl Default for Cell {
@@ -61,6 +97,7 @@ impl Default for Cell {
             c: ' ',
             bg: Color::Named(NamedColor::Black),
             fg: Color::Named(NamedColor::BrightWhite),
+            underline_color: None,
             flags: Flags::empty(),
             to_flush: 1,
         }
@@ -1,64 +1,253 @@
 use crate::cell::Cell;
 use alloc::vec::Vec;
+use core::cmp::min;
 
-/// A 2D array of `Cell` to render on screen
+/// How the cells of a row are drawn, as set by the DECDWL (`ESC # 6`), DECDHL (`ESC # 3` and `ESC # 4`) and DECSWL (`ESC # 5`) escape sequences
+///
+/// Cells on double width and double height rows are drawn twice as wide, so only the left half of the row is shown. A double height line is shown by writing the same text on two rows, the top one with [`LineAttr::DoubleHeightTop`] and the bottom one with [`LineAttr::DoubleHeightBottom`].
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum LineAttr {
+    /// Cells are drawn at their normal size
+    #[default]
+    Normal,
+    /// Cells are drawn twice as wide
+    DoubleWidth,
+    /// Cells are drawn twice as wide and twice as tall, showing the top half of their characters
+    DoubleHeightTop,
+    /// Cells are drawn twice as wide and twice as tall, showing the bottom half of their characters
+    DoubleHeightBottom,
+}
+
+/// A 2D array of [`Cell`]s to render on screen
+///
+/// A [`Console`][crate::Console] can be created from a pre-filled buffer with [`Console::with_buffer`][crate::Console::with_buffer].
+///
+/// Rows are stored in a ring buffer so that scrolling doesn't need to move any cells: `row_offset` is the index of the physical row that is displayed as row 0.
 pub struct CellBuffer {
-    pub buf: Vec<Vec<Cell>>,
+    buf: Vec<Vec<Cell>>,
+    // Whether each physical row may contain a cell that needs to be drawn
+    dirty_rows: Vec<bool>,
+    // The line attributes of each physical row
+    line_attrs: Vec<LineAttr>,
     row_offset: usize,
     width: usize,
     height: usize,
+    // What the buffer is filled with when created or resized
+    default_cell: Cell,
+    // Stands in for out of bounds cells that are borrowed mutably, so that writes to them are discarded
+    scratch_cell: Cell,
+    // Number of times each changed cell is drawn
+    flush_count: usize,
 }
 
 impl CellBuffer {
     /// Create a new text buffer
     pub fn new(width: usize, height: usize) -> Self {
+        Self::with_default_cell(width, height, Cell::default())
+    }
+
+    /// Create a new text buffer filled with `default_cell` instead of [`Cell::default`]
+    ///
+    /// A [`Console`][crate::Console] also uses the default cell for new text, and for cells it erases with [`EraseMode::DefaultCell`][crate::EraseMode::DefaultCell].
+    pub fn with_default_cell(width: usize, height: usize, default_cell: Cell) -> Self {
         CellBuffer {
-            buf: vec![vec![Cell::default(); width]; height],
+            buf: vec![vec![default_cell; width]; height],
+            dirty_rows: vec![true; height],
+            line_attrs: vec![LineAttr::Normal; height],
             row_offset: 0,
             width,
             height,
+            default_cell,
+            scratch_cell: default_cell,
+            flush_count: 1,
         }
     }
 
+    /// The cell that the buffer was filled with when it was created
+    pub fn default_cell(&self) -> Cell {
+        self.default_cell
+    }
+
+    /// Number of columns
     pub fn width(&self) -> usize {
         self.width
     }
 
+    /// Number of rows
     pub fn height(&self) -> usize {
         self.height
     }
 
-    /// Read the character at `(row, col)`
+    /// Number of times that each cell is drawn after it changes
+    pub(crate) fn flush_count(&self) -> usize {
+        self.flush_count
+    }
+
+    /// Draw each cell `count` times after it changes, from the next change on. A `count` of 0 is treated as 1.
+    pub(crate) fn set_flush_count(&mut self, count: usize) {
+        self.flush_count = count.max(1);
+    }
+
+    /// Index into `buf` of the displayed `row`
+    fn physical_row(&self, row: usize) -> usize {
+        (row + self.row_offset) % self.height
+    }
+
+    /// Read the character at `(row, col)`, or the default cell if it is out of bounds
     pub fn read(&self, row: usize, col: usize) -> Cell {
         if row >= self.height() || col >= self.width() {
-            return Cell::default();
+            return self.default_cell;
+        }
+        self.buf[self.physical_row(row)][col]
+    }
+
+    /// The cell at `(row, col)`, or `None` if it is out of bounds
+    pub fn get(&self, row: usize, col: usize) -> Option<&Cell> {
+        if row >= self.height() {
+            return None;
+        }
+        self.buf[self.physical_row(row)].get(col)
+    }
+
+    /// The mutable cell at `(row, col)`, or `None` if it is out of bounds. The cell is drawn again on the next flush.
+    pub fn get_mut(&mut self, row: usize, col: usize) -> Option<&mut Cell> {
+        if row >= self.height() || col >= self.width() {
+            return None;
+        }
+        let flush_count = self.flush_count;
+        let cell = &mut self.row_mut(row)[col];
+        cell.to_flush = cell.to_flush.max(flush_count);
+        Some(cell)
+    }
+
+    /// The cell at `(row, col)`, or the default cell if it is out of bounds
+    pub(crate) fn get_or_default(&self, row: usize, col: usize) -> &Cell {
+        self.get(row, col).unwrap_or(&self.default_cell)
+    }
+
+    /// The mutable cell at `(row, col)`, as with [`CellBuffer::get_mut`], or a cell whose changes are discarded if it is out of bounds
+    pub(crate) fn get_mut_or_scratch(&mut self, row: usize, col: usize) -> &mut Cell {
+        if row >= self.height() || col >= self.width() {
+            self.scratch_cell = self.default_cell;
+            return &mut self.scratch_cell;
         }
-        self.buf[row][col]
+        let flush_count = self.flush_count;
+        let cell = &mut self.row_mut(row)[col];
+        cell.to_flush = cell.to_flush.max(flush_count);
+        cell
     }
 
-    /// Write a character `ch` at `(row, col)`
-    pub fn write(&mut self, row: usize, col: usize, cell: Cell) {
+    /// Write a character `ch` at `(row, col)`. It will be drawn on the next flush.
+    pub fn write(&mut self, row: usize, col: usize, mut cell: Cell) {
         if row >= self.height() || col >= self.width() {
             return;
         }
+        let row = self.physical_row(row);
+        cell.to_flush = cell.to_flush.max(self.flush_count);
         self.buf[row][col] = cell;
+        self.dirty_rows[row] = true;
+    }
+
+    /// The [`LineAttr`] of `row`, or [`LineAttr::Normal`] if it is out of bounds
+    pub fn line_attr(&self, row: usize) -> LineAttr {
+        if row >= self.height() {
+            return LineAttr::Normal;
+        }
+        self.line_attrs[self.physical_row(row)]
+    }
+
+    /// Set the [`LineAttr`] of `row`, which is then drawn again on the next flush
+    pub fn set_line_attr(&mut self, row: usize, attr: LineAttr) {
+        if row >= self.height() || self.line_attr(row) == attr {
+            return;
+        }
+        let row = self.physical_row(row);
+        self.line_attrs[row] = attr;
+        for cell in self.buf[row].iter_mut() {
+            cell.to_flush = cell.to_flush.max(self.flush_count);
+        }
+        self.dirty_rows[row] = true;
+    }
+
+    /// The cells of the displayed `row`
+    pub(crate) fn row(&self, row: usize) -> &[Cell] {
+        &self.buf[self.physical_row(row)]
+    }
+
+    /// The mutable cells of the displayed `row`, which is then considered dirty
+    pub(crate) fn row_mut(&mut self, row: usize) -> &mut [Cell] {
+        let row = self.physical_row(row);
+        self.dirty_rows[row] = true;
+        &mut self.buf[row]
+    }
+
+    /// Whether the displayed `row` may contain cells that need to be drawn
+    pub(crate) fn is_row_dirty(&self, row: usize) -> bool {
+        self.dirty_rows[self.physical_row(row)]
+    }
+
+    /// Record whether the displayed `row` contains cells that need to be drawn
+    pub(crate) fn set_row_dirty(&mut self, row: usize, dirty: bool) {
+        let row = self.physical_row(row);
+        self.dirty_rows[row] = dirty;
+    }
+
+    /// Iterate over the rows, in display order
+    pub(crate) fn rows(&self) -> impl Iterator<Item = &[Cell]> {
+        (0..self.height()).map(move |row| self.row(row))
     }
 
     /// Insert one blank line at the bottom, and scroll up one line.
-    pub fn new_line(&mut self, cell: Cell) {
-        self.clear_line(self.row_offset, cell);
+    pub(crate) fn new_line(&mut self, cell: Cell) {
+        let row = self.row_offset;
+        self.clear_line(row, cell);
+        self.line_attrs[row] = LineAttr::Normal;
         self.row_offset = (self.row_offset + 1) % self.height();
+        // Every row has moved on screen
+        self.mark_all_dirty();
     }
 
-    /// Clear line at `row`
-    fn clear_line(&mut self, row: usize, cell: Cell) {
+    /// Clear line at physical `row`
+    fn clear_line(&mut self, row: usize, mut cell: Cell) {
+        cell.to_flush = cell.to_flush.max(self.flush_count);
         for col in 0..self.width() {
             self.buf[row][col] = cell;
         }
+        self.dirty_rows[row] = true;
+    }
+
+    /// Make sure every cell is drawn on the next flush
+    pub(crate) fn mark_all_dirty(&mut self) {
+        for cell in self.buf.iter_mut().flatten() {
+            cell.to_flush = cell.to_flush.max(self.flush_count);
+        }
+        self.dirty_rows.fill(true);
+    }
+
+    /// Change the size of the buffer to `width` by `height`, keeping the cells that are still in bounds.
+    ///
+    /// New cells are blank, and every cell is drawn on the next flush. Sizes of 0 are treated as 1.
+    pub fn resize(&mut self, width: usize, height: usize) {
+        let (width, height) = (width.max(1), height.max(1));
+        let mut buf = vec![vec![self.default_cell; width]; height];
+        for (new_row, old_row) in buf.iter_mut().zip(self.rows()) {
+            let n = min(width, old_row.len());
+            new_row[..n].copy_from_slice(&old_row[..n]);
+        }
+        let line_attrs = (0..height).map(|row| self.line_attr(row)).collect();
+        self.buf = buf;
+        self.dirty_rows = vec![true; height];
+        self.line_attrs = line_attrs;
+        self.row_offset = 0;
+        self.width = width;
+        self.height = height;
+        self.mark_all_dirty();
     }
 
+    /// Fill the whole buffer with `cell`, and make every row [`LineAttr::Normal`]
     pub fn clear(&mut self, cell: Cell) {
         self.row_offset = 0;
+        self.line_attrs.fill(LineAttr::Normal);
         for i in 0..self.height() {
             for j in 0..self.width() {
                 self.write(i, j, cell);
@@ -1,5 +1,7 @@
 pub use embedded_graphics::pixelcolor::Rgb888;
 
+use crate::style::{ColorInterpolate, color_to_rgb};
+
 /// Standard colors, by name.
 //
 // The order here matters since the enum should be castable to a `usize` for
@@ -26,6 +28,28 @@ pub enum NamedColor {
     BrightWhite = 15,
 }
 
+impl NamedColor {
+    /// Every named color, in order
+    pub const ALL: [NamedColor; 16] = [
+        NamedColor::Black,
+        NamedColor::Red,
+        NamedColor::Green,
+        NamedColor::Yellow,
+        NamedColor::Blue,
+        NamedColor::Magenta,
+        NamedColor::Cyan,
+        NamedColor::White,
+        NamedColor::BrightBlack,
+        NamedColor::BrightRed,
+        NamedColor::BrightGreen,
+        NamedColor::BrightYellow,
+        NamedColor::BrightBlue,
+        NamedColor::BrightMagenta,
+        NamedColor::BrightCyan,
+        NamedColor::BrightWhite,
+    ];
+}
+
 /// A color. Can take the form of a named color, a specific RGB color, or an
 /// indexed color. See [ANSI escape code](https://en.wikipedia.org/wiki/ANSI_escape_code#Colors)
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -37,3 +61,29 @@ pub enum Color {
     /// An indexed color.
     Indexed(u8),
 }
+
+impl Color {
+    /// Blend between this color and `other`, where a `value` of 0 gives this color and 255 gives `other`.
+    ///
+    /// Named and indexed colors are resolved with [`color_to_rgb`], so the result is always an RGB color.
+    pub fn blend(self, other: Color, value: u8) -> Color {
+        Color::RGB(Rgb888::interpolate(
+            color_to_rgb(other),
+            color_to_rgb(self),
+            value,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blend() {
+        let red = Color::Named(NamedColor::Red);
+        let blue = Color::RGB(Rgb888::new(0, 0, 255));
+        assert_eq!(red.blend(blue, 0), Color::RGB(Rgb888::new(194, 54, 33)));
+        assert_eq!(red.blend(blue, 255), blue);
+    }
+}
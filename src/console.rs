@@ -1,17 +1,39 @@
 use crate::Style;
-use crate::ansi::{Attr, ClearMode, Handler, LineClearMode, Mode, Performer};
+use crate::ansi::{
+    self, ApcCollector, Attr, ClearMode, ControlString, DcsString, Handler, InlineImage,
+    LineClearMode, Mode, Performer, StringLimit,
+};
 use crate::cell::{Cell, Flags};
-use crate::cell_buffer::CellBuffer;
-use crate::style::{ColorInterpolate, DrawCell};
+use crate::cell_buffer::{CellBuffer, LineAttr};
+use crate::color::Color;
+use crate::effects::DEFAULT_CURSOR_BLINK_INTERVAL_MS;
+use crate::measure::{AnsiStripper, DEFAULT_TAB_WIDTH, char_width, measure_with_tab_width};
+use crate::selection::is_selected;
+#[cfg(feature = "sixel")]
+use crate::sixel::{PlacedImage, SixelImage};
+use crate::style::{ColorInterpolate, DrawCell, LineScaled, Rotated, Rotation};
 
 use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::vec::Vec;
 use core::cmp::min;
-use core::fmt;
+use core::fmt::{self, Write};
+use core::ops::Range;
 
 use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::Rectangle;
 
 use vte::Parser;
 
+/// Maximum number of titles kept by the XTWINOPS title stack.
+const TITLE_STACK_MAX_DEPTH: usize = 16;
+
+/// Width in pixels of the bar drawn by [`Console::draw_scrollbar`]
+const SCROLLBAR_WIDTH: u32 = 2;
+
+/// Maximum number of events kept until they are read with [`Console::poll_event`]. The oldest events are dropped beyond this.
+const EVENT_QUEUE_MAX_LEN: usize = 32;
+
 /// The primary interface to the terminal emulator.
 ///
 /// Write input strings with control sequences, draw to a [`DrawTarget`].
@@ -20,30 +42,235 @@ use vte::Parser;
 pub struct Console<'a, C, F> {
     // ANSI escape sequence parser
     parser: Parser,
+    // Maximum OSC/DCS payload length, and the state needed to enforce it
+    max_string_length: Option<usize>,
+    string_limit: StringLimit,
+    string_overflow_callback: Option<fn(ControlString)>,
+    // APC strings, which the parser discards, for kitty images
+    apc: ApcCollector,
+    // Colors of the bars drawn by chart widgets
+    pub(crate) chart_palette: Vec<Color>,
+    // Number of unfinished calls to `begin_batch`, during which nothing is drawn
+    batch_depth: usize,
+    // The largest number of cells that `try_resize` allows
+    max_cells: Option<usize>,
+    // While the visual bell is shown, the INVERSE flag of each cell before it was, and the milliseconds until it is restored
+    pub(crate) visual_bell: Option<(Vec<bool>, u32)>,
+    // Milliseconds between changes of the cursor blink phase, the time since it last changed, and whether the cursor is in its visible phase
+    pub(crate) cursor_blink_interval_ms: u32,
+    pub(crate) cursor_blink_elapsed_ms: u32,
+    pub(crate) cursor_phase_visible: bool,
+    // The selected cells, from (start_row, start_col) to (end_row, end_col) inclusive, which are drawn inverted
+    pub(crate) selection: Option<(usize, usize, usize, usize)>,
+    // Whether cells on the default background are drawn without their background
+    overlay: bool,
+    // Whether the foreground and background of every cell are swapped when drawn
+    inverted: bool,
+    // Whether cells that fail to draw are skipped instead of stopping the draw
+    resilient_draw: bool,
     // Inner state
-    inner: ConsoleInner,
-    cell_style: Style<'a, C, F>,
+    pub(crate) inner: ConsoleInner,
+    pub(crate) cell_style: Style<'a, C, F>,
+}
+
+/// What a [`Console`] fills cells with when they are erased, e.g. by clearing the screen, deleting characters or scrolling.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum EraseMode {
+    /// Blank cells with the current background color, as set by SGR. This matches xterm.
+    #[default]
+    CurrentBackground,
+    /// The [default cell](CellBuffer::default_cell) of the console, regardless of the current attributes
+    DefaultCell,
+}
+
+/// How a [`Console`] interprets the carriage return (CR, `\r`) and line feed (LF, `\n`) characters it receives, for sources with different line endings
+///
+/// CR followed by LF starts a new line in every mode.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum NewlineMode {
+    /// LF moves to the start of the next line, and CR to the start of the current one, so lines can end with LF or CRLF, and CR overwrites the line
+    #[default]
+    Lf,
+    /// CR moves to the start of the next line, and LF is ignored, for sources that end lines with CR alone. Lines can't be overwritten with CR in this mode.
+    Cr,
+    /// CR moves to the start of the current line, and LF moves down a line without changing the column, as on a VT100. LF also moves to the start of the line while the LNM mode (`CSI 20 h`) is set.
+    CrLf,
+}
+
+/// Errors returned by the fallible methods of [`Console`]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ConsoleError {
+    /// The width or height is zero
+    ZeroDimension,
+    /// The console would have more cells than its limit, as set with [`Console::try_new_with_limit`], or than can be allocated
+    TooLarge {
+        /// The requested number of columns
+        width: usize,
+        /// The requested number of rows
+        height: usize,
+    },
+    /// The position is outside the console
+    OutOfBounds {
+        /// The requested row
+        row: usize,
+        /// The requested column
+        col: usize,
+    },
+    /// The pending reports don't fit in the buffer they are read into
+    ReportOverflow {
+        /// The number of bytes of pending reports
+        len: usize,
+    },
+}
+
+impl fmt::Display for ConsoleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConsoleError::ZeroDimension => write!(f, "the width or height of the console is zero"),
+            ConsoleError::TooLarge { width, height } => {
+                write!(f, "a console of {} by {} cells is too large", width, height)
+            }
+            ConsoleError::OutOfBounds { row, col } => {
+                write!(f, "row {}, column {} is outside the console", row, col)
+            }
+            ConsoleError::ReportOverflow { len } => {
+                write!(
+                    f,
+                    "{} bytes of pending reports don't fit in the buffer",
+                    len
+                )
+            }
+        }
+    }
+}
+
+impl core::error::Error for ConsoleError {}
+
+/// Check that a console of `width` by `height` cells isn't empty, has no more than `max_cells` cells, and that its cells can be allocated
+fn check_dimensions(
+    width: usize,
+    height: usize,
+    max_cells: Option<usize>,
+) -> Result<(), ConsoleError> {
+    if width == 0 || height == 0 {
+        return Err(ConsoleError::ZeroDimension);
+    }
+    let cells = width.checked_mul(height);
+    let bytes = cells.and_then(|cells| cells.checked_mul(core::mem::size_of::<Cell>()));
+    match (cells, bytes) {
+        (Some(cells), Some(bytes))
+            if bytes <= isize::MAX as usize && max_cells.is_none_or(|max| cells <= max) =>
+        {
+            Ok(())
+        }
+        _ => Err(ConsoleError::TooLarge { width, height }),
+    }
+}
+
+/// Something that happened while a [`Console`] was reading input, to be read with [`Console::poll_event`] once [`Console::set_event_queue`] is on
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum TerminalEvent {
+    /// The bell character (BEL, `\x07`) was received
+    Bell,
+    /// The window title was set through OSC 0/2, or popped from the title stack. An empty title means it was cleared.
+    TitleChange(String),
+    /// The host asked for the window to be resized to `(rows, cols)`, with XTWINOPS 8 (`CSI 8 ; rows ; cols t`)
+    ResizeRequest(usize, usize),
+    /// The start of bracketed pasted text (`CSI 200 ~`)
+    PasteStart,
+    /// The end of bracketed pasted text (`CSI 201 ~`)
+    PasteEnd,
 }
 
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
 struct Cursor {
     row: usize,
     col: usize,
 }
 
-struct ConsoleInner {
+/// The cursor position, text attributes, scrolling region and margins of a [`Console`], as saved by [`Console::save_state`] or the DECSC (`ESC 7`) control sequence.
+#[derive(Debug, Clone, Copy)]
+pub struct SavedState {
+    cursor: Cursor,
+    temp: Cell,
+    auto_wrap: bool,
+    scrolling_region: Option<(usize, usize)>,
+    margins: Option<(usize, usize)>,
+}
+
+impl Default for SavedState {
+    fn default() -> Self {
+        Self {
+            cursor: Cursor::default(),
+            temp: Cell::default(),
+            auto_wrap: true,
+            scrolling_region: None,
+            margins: None,
+        }
+    }
+}
+
+pub(crate) struct ConsoleInner {
     /// cursor
     cursor: Cursor,
-    /// Saved cursor
-    saved_cursor: Cursor,
+    /// Where the cursor was after a zero width joiner, so that the next character joins the one before it
+    joiner: Option<Cursor>,
+    /// State saved with DECSC
+    saved_state: SavedState,
     /// current attribute template
-    temp: Cell,
+    pub(crate) temp: Cell,
     /// character buffer
-    buf: CellBuffer,
+    pub(crate) buf: CellBuffer,
+    /// The primary screen's buffer, while the alternate screen is shown
+    primary_buf: Option<CellBuffer>,
+    /// Top and bottom rows of the scrolling region set with DECSTBM, inclusive, if it is smaller than the screen
+    scrolling_region: Option<(usize, usize)>,
+    /// Left and right margins set with DECSLRM, as inclusive columns, if they are narrower than the screen
+    margins: Option<(usize, usize)>,
+    /// The last character written, which REP repeats
+    last_char: Option<char>,
+    /// Whether the cursor blinks, as set with DECSCUSR and `CSI ? 12 h`
+    pub(crate) cursor_blinking: bool,
     /// auto wrap
     auto_wrap: bool,
+    /// Backspace at the first column moves to the end of the row above
+    reverse_wrap: bool,
+    /// What erased cells are filled with
+    erase_mode: EraseMode,
+    /// Distance between tab stops
+    tab_width: usize,
+    /// Reset the attribute template on every linefeed
+    reset_attributes_on_newline: bool,
+    /// How CR and LF are interpreted
+    newline_mode: NewlineMode,
+    /// Whether LF also returns to the first column in `NewlineMode::CrLf`, as set with LNM
+    linefeed_newline: bool,
     /// Reported data for CSI Device Status Report
     report: VecDeque<u8>,
+    /// Whether events are queued for `poll_event`
+    event_queue: bool,
+    /// Events to be read with `poll_event`
+    events: VecDeque<TerminalEvent>,
+    /// Window title set through OSC 0/2
+    title: Option<String>,
+    /// Titles saved with XTWINOPS 22
+    title_stack: Vec<Option<String>>,
+    /// Called when BEL is received
+    bell_callback: Option<fn()>,
+    /// Number of times BEL has been received
+    bell_count: u64,
+    /// Called with every complete DCS
+    dcs_callback: Option<fn(&DcsString)>,
+    /// The DCS being received, if there's a callback for it
+    dcs: Option<DcsString>,
+    /// Called with every inline image sequence
+    inline_image_callback: Option<fn(&InlineImage)>,
+    /// Size of a cell in pixels, for placing images
+    #[cfg(feature = "sixel")]
+    cell_size: Size,
+    /// Sixel images on screen, which are drawn over the cells
+    #[cfg(feature = "sixel")]
+    images: Vec<PlacedImage>,
 }
 
 impl<'a, C, F> Console<'a, C, F>
@@ -52,31 +279,293 @@ where
 {
     /// Create a new console with a given width and height in characters, and a [`Style`]
     pub fn new(width: usize, height: usize, cell_style: Style<'a, C, F>) -> Self {
+        Self::with_buffer(CellBuffer::new(width, height), cell_style)
+    }
+
+    /// Create a new console like [`Console::new`], filled with `default_cell` instead of [`Cell::default`].
+    ///
+    /// New text starts with the attributes of the default cell, which they are also reset to by SGR 0 (and its colors by SGR 39 and 49), and cells erased with [`EraseMode::DefaultCell`] are set to it.
+    pub fn with_default_cell(
+        width: usize,
+        height: usize,
+        default_cell: Cell,
+        cell_style: Style<'a, C, F>,
+    ) -> Self {
+        Self::with_buffer(
+            CellBuffer::with_default_cell(width, height, default_cell),
+            cell_style,
+        )
+    }
+
+    /// Create a new console like [`Console::new`], or return an error instead of allocating if the dimensions are zero or the cells couldn't be allocated.
+    pub fn try_new(
+        width: usize,
+        height: usize,
+        cell_style: Style<'a, C, F>,
+    ) -> Result<Self, ConsoleError> {
+        Self::try_new_with_limit(width, height, None, cell_style)
+    }
+
+    /// Create a new console like [`Console::try_new`], or return an error if it would have more than `max_cells` cells. The limit is kept for [`Console::try_resize`].
+    ///
+    /// This guards against running out of memory when the dimensions are computed at runtime, e.g. from the size of a display.
+    pub fn try_new_with_limit(
+        width: usize,
+        height: usize,
+        max_cells: Option<usize>,
+        cell_style: Style<'a, C, F>,
+    ) -> Result<Self, ConsoleError> {
+        check_dimensions(width, height, max_cells)?;
+        let mut console = Self::new(width, height, cell_style);
+        console.max_cells = max_cells;
+        Ok(console)
+    }
+
+    /// Create a new console that displays the content of an existing [`CellBuffer`], and a [`Style`]
+    ///
+    /// The console takes the dimensions of the buffer, and the cursor starts in the top left corner.
+    pub fn with_buffer(buf: CellBuffer, cell_style: Style<'a, C, F>) -> Self {
         Console {
             parser: Parser::new(),
-            cell_style,
+            max_string_length: None,
+            string_limit: StringLimit::default(),
+            string_overflow_callback: None,
+            apc: ApcCollector::default(),
+            chart_palette: Vec::new(),
+            batch_depth: 0,
+            max_cells: None,
+            visual_bell: None,
+            cursor_blink_interval_ms: DEFAULT_CURSOR_BLINK_INTERVAL_MS,
+            cursor_blink_elapsed_ms: 0,
+            cursor_phase_visible: true,
+            selection: None,
+            overlay: false,
+            inverted: false,
+            resilient_draw: false,
             inner: ConsoleInner {
                 cursor: Cursor::default(),
-                saved_cursor: Cursor::default(),
-                temp: Cell::default(),
-                buf: CellBuffer::new(width, height),
+                joiner: None,
+                saved_state: SavedState {
+                    temp: buf.default_cell(),
+                    ..SavedState::default()
+                },
+                temp: buf.default_cell(),
+                buf,
+                primary_buf: None,
+                scrolling_region: None,
+                margins: None,
+                last_char: None,
+                cursor_blinking: true,
                 auto_wrap: true,
+                reverse_wrap: false,
+                erase_mode: EraseMode::default(),
+                tab_width: DEFAULT_TAB_WIDTH,
+                reset_attributes_on_newline: false,
+                newline_mode: NewlineMode::default(),
+                linefeed_newline: false,
                 report: VecDeque::new(),
+                event_queue: false,
+                events: VecDeque::new(),
+                title: None,
+                title_stack: Vec::new(),
+                bell_callback: None,
+                bell_count: 0,
+                dcs_callback: None,
+                dcs: None,
+                inline_image_callback: None,
+                #[cfg(feature = "sixel")]
+                cell_size: cell_style.cell_size(),
+                #[cfg(feature = "sixel")]
+                images: Vec::new(),
             },
+            cell_style,
         }
     }
 
     /// Write a single `byte` to console
     pub fn write_byte(&mut self, byte: u8) {
+        if self.inner.inline_image_callback.is_some()
+            && let Some(apc) = self.apc.advance(byte, self.max_string_length)
+            && let Some(image) = InlineImage::parse_kitty(&apc)
+        {
+            self.inner.inline_image(image);
+        }
+        if let Some(max_len) = self.max_string_length
+            && let Some(kind) = self.string_limit.advance(byte, max_len)
+        {
+            debug!("Abandoning overlong {:?} string", kind);
+            self.parser = Parser::new();
+            if let Some(callback) = self.string_overflow_callback {
+                callback(kind);
+            }
+        }
         self.parser
             .advance(&mut Performer::new(&mut self.inner), byte);
     }
 
+    /// Limit the length of OSC and DCS control string payloads.
+    ///
+    /// Without a limit (the default), an unterminated control string swallows all of the output that follows it. With a limit, a control string is abandoned once its payload exceeds `max_len` bytes, and the following bytes are parsed normally.
+    pub fn set_max_string_length(&mut self, max_len: Option<usize>) {
+        self.max_string_length = max_len;
+        self.string_limit = StringLimit::default();
+    }
+
+    /// Set a function to call when a control string is abandoned for exceeding the [maximum length](Console::set_max_string_length).
+    pub fn set_string_overflow_callback(&mut self, callback: Option<fn(ControlString)>) {
+        self.string_overflow_callback = callback;
+    }
+
+    /// Set a function to call when the bell character (BEL, `\x07`) is received.
+    pub fn set_bell_callback(&mut self, callback: fn()) {
+        self.inner.bell_callback = Some(callback);
+    }
+
+    /// The number of times the bell character (BEL, `\x07`) has been received.
+    ///
+    /// This can be polled instead of [setting a callback](Console::set_bell_callback).
+    pub fn bell_count(&self) -> u64 {
+        self.inner.bell_count
+    }
+
+    /// Set a function to call with every Device Control String (`ESC P ... ESC \`) that is received.
+    ///
+    /// DCS sequences are otherwise ignored. This lets device-specific protocols be handled outside of the console.
+    pub fn set_dcs_callback(&mut self, callback: Option<fn(&DcsString)>) {
+        self.inner.dcs_callback = callback;
+    }
+
+    /// Set a function to call with every inline image sequence, from the kitty graphics protocol (`ESC _ G ... ESC \`) or iTerm's `OSC 1337`.
+    ///
+    /// Images are not drawn by the console. The callback receives the parameters and the still-encoded payload, so that the image can be decoded and drawn separately.
+    pub fn set_inline_image_callback(&mut self, callback: Option<fn(&InlineImage)>) {
+        self.inner.inline_image_callback = callback;
+        self.apc = ApcCollector::default();
+    }
+
+    /// Write `text` that contains no control characters, without passing it through the control sequence parser.
+    ///
+    /// This is faster than [`write_str`](fmt::Write::write_str) for plain text. Control characters in `text` cause a panic in debug builds, and are written as characters otherwise.
+    pub fn write_plain(&mut self, text: &str) {
+        debug_assert!(
+            !text.chars().any(char::is_control),
+            "write_plain called with control characters: {:?}",
+            text
+        );
+        for c in text.chars() {
+            self.inner.input(c);
+        }
+    }
+
+    /// Write the printable characters of `text`, skipping control sequences and control characters, e.g. to show colored text on a monochrome display.
+    ///
+    /// This uses a minimal state machine rather than the control sequence parser, so the attributes and cursor are never changed by `text`. Note that newlines are skipped too. See [`util::strip_ansi`](crate::util::strip_ansi).
+    pub fn write_ansi_strip(&mut self, text: &str) {
+        let mut stripper = AnsiStripper::default();
+        for c in text.chars().filter(|&c| stripper.advance(c)) {
+            self.inner.input(c);
+        }
+    }
+
+    /// Write binary `data` as ASCII text, without interpreting control sequences.
+    ///
+    /// Printable ASCII bytes (0x20–0x7E) are written as-is, and all other bytes are written as `.`.
+    pub fn write_bytes_as_ascii(&mut self, data: &[u8]) {
+        for &byte in data {
+            if is_printable_ascii(byte) {
+                self.inner.input(byte as char);
+            } else {
+                self.inner.input('.');
+            }
+        }
+    }
+
+    /// Write the printable ASCII bytes (0x20–0x7E) of `data`, skipping all others, without interpreting control sequences.
+    pub fn write_bytes_printable_only(&mut self, data: &[u8]) {
+        for &byte in data.iter().filter(|&&byte| is_printable_ascii(byte)) {
+            self.inner.input(byte as char);
+        }
+    }
+
+    /// Write `c` as-is if it is printable, or as its code point in the form `U+XXXX` if it is a control character, without interpreting control sequences.
+    pub fn write_unicode_codepoint_as_escape(&mut self, c: char) {
+        if c.is_control() {
+            let _ = write!(self.inner, "U+{:04X}", c as u32);
+        } else {
+            self.inner.input(c);
+        }
+    }
+
+    /// Write `bytes` so that control sequences can be seen, e.g. when debugging what a program sends.
+    ///
+    /// Printable ASCII bytes (0x20–0x7E) are written as-is, and all other bytes are written in the form `\xHH`, e.g. `\x1b` for ESC.
+    pub fn write_bytes_as_escape_sequence(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            if is_printable_ascii(byte) {
+                self.inner.input(byte as char);
+            } else {
+                let _ = write!(self.inner, "\\x{:02x}", byte);
+            }
+        }
+    }
+
+    /// Queue [`TerminalEvent`]s to be read with [`Console::poll_event`]. Off by default, so that events take no memory unless they are read.
+    ///
+    /// Turning the queue off drops the events in it. Reports are kept apart from events, to be read with [`Console::pop_report`] either way.
+    pub fn set_event_queue(&mut self, enabled: bool) {
+        self.inner.event_queue = enabled;
+        if !enabled {
+            self.inner.events.clear();
+        }
+    }
+
+    /// Read the next event, in the order they happened, once [`Console::set_event_queue`] is on
+    ///
+    /// Up to 32 events are kept, after which the oldest ones are dropped.
+    pub fn poll_event(&mut self) -> Option<TerminalEvent> {
+        self.inner.events.pop_front()
+    }
+
     /// Read result for some commands
     pub fn pop_report(&mut self) -> Option<u8> {
         self.inner.report.pop_front()
     }
 
+    /// Move all pending reports into `buf`, e.g. to send them back to the host from a fixed-size buffer, and return how many bytes were read.
+    ///
+    /// Returns [`ConsoleError::ReportOverflow`] if they don't all fit, in which case none of them are read.
+    pub fn try_read_reports(&mut self, buf: &mut [u8]) -> Result<usize, ConsoleError> {
+        let len = self.inner.report.len();
+        if len > buf.len() {
+            return Err(ConsoleError::ReportOverflow { len });
+        }
+        for (byte, report) in buf.iter_mut().zip(self.inner.report.drain(..)) {
+            *byte = report;
+        }
+        Ok(len)
+    }
+
+    /// Write all pending reports to `writer`, e.g. to send them back to the host.
+    ///
+    /// If `writer` fails, the reports that were not written are kept.
+    pub fn flush_reports_to<W: fmt::Write>(&mut self, writer: &mut W) -> fmt::Result {
+        while let Some(&byte) = self.inner.report.front() {
+            writer.write_char(byte as char)?;
+            self.inner.report.pop_front();
+        }
+        Ok(())
+    }
+
+    /// The window title, as last set by the OSC 0 or OSC 2 control sequences
+    pub fn title(&self) -> Option<&str> {
+        self.inner.title.as_deref()
+    }
+
+    /// Whether the alternate screen buffer is being shown, as set by the `CSI ? 1049 h` control sequence
+    pub fn is_alternate_screen(&self) -> bool {
+        self.inner.primary_buf.is_some()
+    }
+
     /// Number of rows
     pub fn rows(&self) -> usize {
         self.inner.buf.height()
@@ -87,336 +576,2557 @@ where
         self.inner.buf.width()
     }
 
-    /// Get the current cursor position
-    pub fn get_cursor_position(&self) -> (usize, usize) {
-        (self.inner.cursor.row, self.inner.cursor.col)
+    /// The size of the console when drawn, in pixels, not including the [`Style`] offset
+    ///
+    /// Width and height are swapped when the console is [rotated](Style::rotation) by 90 or 270 degrees.
+    pub fn image_dimensions(&self) -> (u32, u32) {
+        let size = self
+            .cell_style
+            .rotation
+            .rotate_size(self.unrotated_dimensions());
+        (size.width, size.height)
     }
 
-    #[cfg(feature = "ratatui-backend")]
-    pub(crate) fn set_cursor_position(&mut self, row: usize, col: usize) {
-        self.inner.goto(row, col);
-        self.inner.temp = self.inner.buf.read(row, col);
+    /// The size of the console when drawn without rotation
+    fn unrotated_dimensions(&self) -> Size {
+        let cell_size = self.cell_style.cell_size();
+        Size::new(
+            self.columns() as u32 * cell_size.width,
+            self.rows() as u32 * cell_size.height,
+        )
     }
 
-    #[cfg(feature = "ratatui-backend")]
-    pub(crate) fn set_cell(&mut self, row: usize, col: usize, cell: Cell) {
-        self.inner.buf.write(row, col, cell);
+    /// Wrap `display` so that cells drawn to it are rotated by the [`Style`] rotation
+    pub(crate) fn rotated<'d, D: DrawTarget>(&self, display: &'d mut D) -> Rotated<'d, D> {
+        Rotated {
+            target: display,
+            rotation: self.cell_style.rotation,
+            origin: Point::new(
+                self.cell_style.offset.0 as i32,
+                self.cell_style.offset.1 as i32,
+            ),
+            size: self.unrotated_dimensions(),
+        }
     }
 
-    /// Draw the console to an embedded-graphics [`DrawTarget`]
-    pub fn draw<D, P: PixelColor + From<C> + ColorInterpolate>(
-        &mut self,
-        display: &mut D,
-    ) -> Result<(), <D as DrawTarget>::Error>
-    where
-        D: DrawTarget<Color = P>,
-    {
-        for (row, row_cells) in self.inner.buf.buf.iter_mut().enumerate() {
-            for (col, cell) in row_cells.iter_mut().enumerate() {
-                if cell.to_flush > 0 {
-                    self.cell_style.draw_cell(cell, row, col, display)?;
-                    cell.to_flush -= 1;
-                }
-            }
+    /// Reset the text attributes (colors, bold, etc.) to their defaults on every linefeed.
+    ///
+    /// This is off by default. It is useful when displaying log-style output from sources that don't reliably reset their attributes at the end of a line, which would otherwise bleed onto the following lines.
+    pub fn set_reset_attributes_on_newline(&mut self, reset: bool) {
+        self.inner.reset_attributes_on_newline = reset;
+    }
+
+    /// Choose how received CR and LF characters are interpreted. The default is [`NewlineMode::Lf`].
+    pub fn set_newline_mode(&mut self, mode: NewlineMode) {
+        self.inner.newline_mode = mode;
+    }
+
+    /// Change the size of the console to `columns` by `rows`, keeping the content that is still in bounds.
+    ///
+    /// The cursor is moved inside the new bounds, and the whole console is drawn on the next call to [`Console::draw`].
+    pub fn resize(&mut self, columns: usize, rows: usize) {
+        self.inner.buf.resize(columns, rows);
+        self.inner.scrolling_region = None;
+        self.inner.margins = None;
+        if let Some(primary) = &mut self.inner.primary_buf {
+            primary.resize(columns, rows);
         }
+        let Cursor { row, col } = self.inner.cursor;
+        self.inner.goto(row, col);
+    }
 
+    /// Resize the console like [`Console::resize`], or return an error without changing it if the dimensions are zero, or the console would have more cells than the limit it was created with by [`Console::try_new_with_limit`] or than can be allocated.
+    pub fn try_resize(&mut self, columns: usize, rows: usize) -> Result<(), ConsoleError> {
+        check_dimensions(columns, rows, self.max_cells)?;
+        self.resize(columns, rows);
         Ok(())
     }
 
-    /// Clear the screen
-    pub fn clear_screen(&mut self, mode: ClearMode) {
-        self.inner.clear_screen(mode);
+    /// Draw every cell on the next call to [`Console::draw`], e.g. after the display has been cleared by something else.
+    pub fn force_full_redraw(&mut self) {
+        self.inner.buf.mark_all_dirty();
     }
 
-    /// Clear the line
-    pub fn clear_line(&mut self, mode: LineClearMode) {
-        self.inner.clear_line(mode);
+    /// Draw each cell `count` times after it changes before it is considered drawn, instead of once, e.g. so that the ink of a partially refreshed e-paper display settles.
+    ///
+    /// Each call to [`Console::draw`] draws the cell once. This applies to cells that change from now on, and is independent of how many buffers the display has. A `count` of 0 is treated as 1.
+    pub fn set_flush_count(&mut self, count: usize) {
+        self.inner.buf.set_flush_count(count);
+        if let Some(primary) = &mut self.inner.primary_buf {
+            primary.set_flush_count(count);
+        }
     }
-}
 
-impl<'a, C, F> fmt::Write for Console<'a, C, F>
-where
-    Style<'a, C, F>: DrawCell<C>,
-{
-    fn write_str(&mut self, s: &str) -> fmt::Result {
-        for byte in s.bytes() {
-            self.write_byte(byte);
-        }
-        Ok(())
+    /// Choose what erased cells are filled with. The default is [`EraseMode::CurrentBackground`].
+    pub fn set_erase_mode(&mut self, mode: EraseMode) {
+        self.inner.erase_mode = mode;
     }
-}
 
-impl Handler for ConsoleInner {
-    fn input(&mut self, c: char) {
-        trace!("  [input]: {:?} @ {:?}", c, self.cursor);
-        if self.cursor.col >= self.buf.width() {
-            if !self.auto_wrap {
-                // skip this one
-                return;
-            }
-            self.cursor.col = 0;
-            self.linefeed();
-        }
-        let mut temp = self.temp;
-        temp.c = c;
-        self.buf.write(self.cursor.row, self.cursor.col, temp);
-        self.cursor.col += 1;
+    /// Draw only the foreground of cells whose background is that of the [default cell](CellBuffer::default_cell), leaving what was on the display behind them.
+    ///
+    /// This avoids the flicker of repainting a static background when moving a few characters, e.g. sprites in a game. Pixels of the background color are skipped, so the display must already show the background where these cells are drawn, and characters that are erased are not cleared. Inverse and dim cells are drawn in full. Off by default.
+    pub fn set_overlay_mode(&mut self, enabled: bool) {
+        self.overlay = enabled;
     }
 
-    fn goto(&mut self, row: usize, col: usize) {
-        trace!("Going to: line={}, col={}", row, col);
-        self.cursor.row = min(row, self.buf.height());
-        self.cursor.col = min(col, self.buf.width());
+    /// Keep drawing when the [`DrawTarget`] fails to draw a cell, e.g. on displays with transient bus errors, instead of returning the error straight away.
+    ///
+    /// A cell that fails is logged and left to be drawn again by the next call to [`Console::draw`], and the other cells are drawn as usual. The draw then returns the first error. Off by default, so that the draw stops at the first error.
+    pub fn set_resilient_draw(&mut self, enabled: bool) {
+        self.resilient_draw = enabled;
     }
 
-    fn goto_line(&mut self, row: usize) {
-        trace!("Going to line: {}", row);
-        self.goto(row, self.cursor.col)
+    /// Swap the foreground and background colors of every cell when it is drawn, e.g. to toggle between a light and a dark color scheme without changing the palette.
+    ///
+    /// Inverse cells are drawn with their normal colors while this is on. This is a setting of the application, unlike the reverse video mode (DECSCNM) of other terminals, which is set by what is written to them. Every cell is drawn again on the next call to [`Console::draw`]. Off by default.
+    pub fn set_inverted(&mut self, enabled: bool) {
+        if self.inverted != enabled {
+            self.inverted = enabled;
+            self.inner.buf.mark_all_dirty();
+        }
     }
 
-    fn goto_col(&mut self, col: usize) {
-        trace!("Going to column: {}", col);
-        self.goto(self.cursor.row, col)
+    /// Place tab stops every `width` columns, instead of the default 8.
+    ///
+    /// Tab stops are used by the tab character and the CHT (`CSI n I`) and CBT (`CSI n Z`) control sequences. A `width` of 0 is treated as 1.
+    pub fn set_tab_width(&mut self, width: usize) {
+        self.inner.tab_width = width.max(1);
     }
 
-    fn move_up(&mut self, rows: usize) {
-        trace!("Moving up: {}", rows);
-        self.goto(self.cursor.row.saturating_sub(rows), self.cursor.col)
+    /// The number of columns that `text` takes when written to the console, not counting control characters and sequences, like [`util::measure_ansi_string_width`](crate::util::measure_ansi_string_width).
+    ///
+    /// Tabs move to the console's [tab stops](Console::set_tab_width). Text isn't wrapped, so the result may be wider than the console.
+    pub fn measure_text_width(&self, text: &str) -> usize {
+        measure_with_tab_width(text, self.inner.tab_width).0
     }
 
-    fn move_down(&mut self, rows: usize) {
-        trace!("Moving down: {}", rows);
-        self.goto(
-            min(self.cursor.row + rows, self.buf.height() - 1) as _,
-            self.cursor.col,
-        )
+    /// Get the current cursor position
+    pub fn get_cursor_position(&self) -> (usize, usize) {
+        (self.inner.cursor.row, self.inner.cursor.col)
     }
 
-    fn move_forward(&mut self, cols: usize) {
-        trace!("Moving forward: {}", cols);
-        self.cursor.col = min(self.cursor.col + cols, self.buf.width() - 1);
+    /// A copy of the cell under the cursor, e.g. to draw the cursor over its character.
+    ///
+    /// While a wrap is pending after writing to the last column, this is the cell in the last column.
+    pub fn cursor_cell(&self) -> Cell {
+        let (row, col) = self.get_cursor_position();
+        self.inner
+            .buf
+            .read(row, min(col, self.columns().saturating_sub(1)))
     }
 
-    fn move_backward(&mut self, cols: usize) {
-        trace!("Moving backward: {}", cols);
-        self.cursor.col = self.cursor.col.saturating_sub(cols);
+    /// The character of the cell under the cursor, as with [`Console::cursor_cell`]
+    pub fn cursor_char(&self) -> char {
+        self.cursor_cell().c()
     }
 
-    fn move_down_and_cr(&mut self, rows: usize) {
-        trace!("Moving down and cr: {}", rows);
-        self.goto(min(self.cursor.row + rows, self.buf.height() - 1) as _, 0)
+    /// Iterate over the rows of the screen, from top to bottom, e.g. for custom renderers and exporters.
+    ///
+    /// These are the rows that [`Console::draw`] draws, in the order they appear on screen, however far the console has scrolled. The console keeps no scrollback, so rows that have scrolled off the top are not included.
+    pub fn rows_iter(&self) -> impl Iterator<Item = &[Cell]> {
+        self.inner.buf.rows()
     }
 
-    fn move_up_and_cr(&mut self, rows: usize) {
-        trace!("Moving up and cr: {}", rows);
-        self.goto(self.cursor.row.saturating_sub(rows), 0)
+    /// Iterate over the rows that contain at least one cell that still needs to be drawn, along with their row index
+    pub fn dirty_rows_iter(&self) -> impl Iterator<Item = (usize, &[Cell])> {
+        self.rows_iter().enumerate().filter(|(row, cells)| {
+            self.inner.buf.is_row_dirty(*row) && cells.iter().any(Cell::is_dirty)
+        })
     }
 
-    fn put_tab(&mut self, count: u16) {
-        let mut count = count;
-        let bg = self.temp.just_bg();
-        while self.cursor.col < self.buf.width() && count > 0 {
-            count -= 1;
-            loop {
-                self.buf.write(self.cursor.row, self.cursor.col, bg);
-                self.cursor.col += 1;
-                if self.cursor.col == self.buf.width() || self.cursor.col % 8 == 0 {
-                    break;
-                }
-            }
+    /// Record that the cell at `row`, `col` has been drawn, e.g. by a custom draw loop, so that [`Console::draw`] skips it until it changes.
+    ///
+    /// Positions that are out of bounds are ignored.
+    pub fn mark_cell_clean(&mut self, row: usize, col: usize) {
+        if row < self.rows()
+            && let Some(cell) = self.inner.buf.row_mut(row).get_mut(col)
+        {
+            cell.to_flush = 0;
         }
     }
 
-    fn backspace(&mut self) {
-        trace!("Backspace");
-        if self.cursor.col > 0 {
-            self.cursor.col -= 1;
+    /// Make sure the cell at `row`, `col` is drawn on the next call to [`Console::draw`].
+    ///
+    /// Positions that are out of bounds are ignored.
+    pub fn mark_cell_dirty(&mut self, row: usize, col: usize) {
+        let flush_count = self.inner.buf.flush_count();
+        if row < self.rows()
+            && let Some(cell) = self.inner.buf.row_mut(row).get_mut(col)
+        {
+            cell.to_flush = cell.to_flush.max(flush_count);
         }
     }
 
-    fn carriage_return(&mut self) {
-        trace!("Carriage return");
-        self.cursor.col = 0;
+    /// Save the cursor position, text attributes, scrolling region and margins
+    pub fn save_state(&self) -> SavedState {
+        self.inner.save_state()
     }
 
-    fn linefeed(&mut self) {
-        trace!("Linefeed");
-        self.cursor.col = 0;
-        if self.cursor.row < self.buf.height() - 1 {
-            self.cursor.row += 1;
-        } else {
-            self.buf.new_line(self.temp);
-        }
+    /// Restore a cursor position and text attributes previously saved with [`Console::save_state`]
+    pub fn restore_state(&mut self, state: SavedState) {
+        self.inner.restore_state(state);
     }
 
-    fn scroll_up(&mut self, rows: usize) {
-        debug!("[Unhandled CSI] scroll_up {:?}", rows);
+    /// Set the text attributes from SGR parameters, as the `CSI params m` control sequence does, e.g. `[1, 31]` for bold red text.
+    ///
+    /// Each parameter is separate, so colors are given as e.g. `[38, 5, 196]`. No parameters reset the attributes.
+    pub fn apply_sgr(&mut self, params: &[u16]) {
+        ansi::apply_sgr_parameters(&mut self.inner, params);
     }
 
-    fn scroll_down(&mut self, rows: usize) {
-        debug!("[Unhandled CSI] scroll_down {:?}", rows);
+    /// Move the cursor down `n` rows and to the first column (CNL)
+    pub fn cursor_next_line(&mut self, n: usize) {
+        self.inner.move_down_and_cr(n);
     }
 
-    fn erase_chars(&mut self, count: usize) {
-        trace!("Erasing chars: count={}, col={}", count, self.cursor.col);
-
-        let start = self.cursor.col;
-        let end = min(start + count, self.buf.width());
+    /// Move the cursor up `n` rows and to the first column (CPL)
+    pub fn cursor_prev_line(&mut self, n: usize) {
+        self.inner.move_up_and_cr(n);
+    }
 
-        // Cleared cells have current background color set.
-        let bg = self.temp.just_bg();
-        for i in start..end {
-            self.buf.write(self.cursor.row, i, bg);
+    /// Move the cursor to `row`, `col` and write `text`, as with [`fmt::Write::write_str`], so that control sequences are interpreted and text wraps as usual.
+    ///
+    /// Nothing is written, and the cursor is not moved, if the position is out of bounds.
+    pub fn write_at(&mut self, row: usize, col: usize, text: &str) {
+        if row >= self.rows() || col >= self.columns() {
+            return;
         }
+        self.inner.goto(row, col);
+        let _ = fmt::Write::write_str(self, text);
     }
-    fn delete_chars(&mut self, count: usize) {
-        let columns = self.buf.width();
-        let count = min(count, columns - self.cursor.col - 1);
-        let row = self.cursor.row;
 
-        let start = self.cursor.col;
-        let end = start + count;
+    /// Write `text` at `row`, `col` like [`Console::write_at`], or return [`ConsoleError::OutOfBounds`] if the position is out of bounds.
+    pub fn try_write_at(&mut self, row: usize, col: usize, text: &str) -> Result<(), ConsoleError> {
+        if row >= self.rows() || col >= self.columns() {
+            return Err(ConsoleError::OutOfBounds { row, col });
+        }
+        self.write_at(row, col, text);
+        Ok(())
+    }
 
-        let bg = self.temp.just_bg();
-        for i in end..columns {
-            self.buf.write(row, i - count, self.buf.read(row, i));
-            self.buf.write(row, i, bg);
+    /// Replace the cell at `row`, `col`, which is drawn again on the next call to [`Console::draw`], or return [`ConsoleError::OutOfBounds`] if the position is out of bounds.
+    ///
+    /// Unlike writing text, this doesn't move the cursor or interpret control characters. Indexing the console with `console[(row, col)] = cell` does the same, but discards cells that are out of bounds.
+    pub fn try_put_cell(&mut self, row: usize, col: usize, cell: Cell) -> Result<(), ConsoleError> {
+        if row >= self.rows() || col >= self.columns() {
+            return Err(ConsoleError::OutOfBounds { row, col });
         }
+        self.inner.buf.write(row, col, cell);
+        Ok(())
     }
 
-    /// Save current cursor position.
-    fn save_cursor_position(&mut self) {
-        trace!("Saving cursor position");
-        self.saved_cursor = self.cursor;
+    #[cfg(feature = "ratatui-backend")]
+    pub(crate) fn set_cursor_position(&mut self, row: usize, col: usize) {
+        self.inner.goto(row, col);
+        self.inner.temp = self.inner.buf.read(row, col);
     }
 
-    /// Restore cursor position.
-    fn restore_cursor_position(&mut self) {
-        trace!("Restoring cursor position");
-        self.cursor = self.saved_cursor;
+    #[cfg(feature = "ratatui-backend")]
+    pub(crate) fn set_cell(&mut self, row: usize, col: usize, cell: Cell) {
+        self.inner.buf.write(row, col, cell);
     }
 
-    fn clear_line(&mut self, mode: LineClearMode) {
-        trace!("Clearing line: {:?}", mode);
-        let bg = self.temp.just_bg();
-        match mode {
-            LineClearMode::Right => {
-                for i in self.cursor.col..self.buf.width() {
-                    self.buf.write(self.cursor.row, i, bg);
-                }
+    /// Set how the cells of `row` are drawn, as with the DECDWL, DECDHL and DECSWL escape sequences. See [`LineAttr`].
+    ///
+    /// Rows that are scrolled onto the screen are [`LineAttr::Normal`]. Does nothing if `row` is out of bounds.
+    pub fn set_line_attr(&mut self, row: usize, attr: LineAttr) {
+        self.inner.buf.set_line_attr(row, attr);
+    }
+
+    /// The [`LineAttr`] of `row`
+    pub fn line_attr(&self, row: usize) -> LineAttr {
+        self.inner.buf.line_attr(row)
+    }
+
+    /// Start a batch of updates, during which [`Console::draw`] and [`Console::draw_direct`] draw nothing.
+    ///
+    /// Cells that change during the batch stay dirty, so they are drawn once, by the first draw after [`Console::end_batch`]. Batches can be nested, and end when every `begin_batch` has been matched by an `end_batch`.
+    pub fn begin_batch(&mut self) {
+        self.batch_depth += 1;
+    }
+
+    /// End a batch of updates started with [`Console::begin_batch`]. Does nothing if no batch was started.
+    pub fn end_batch(&mut self) {
+        self.batch_depth = self.batch_depth.saturating_sub(1);
+    }
+
+    /// Whether a batch of updates started with [`Console::begin_batch`] hasn't ended yet
+    pub fn is_batching(&self) -> bool {
+        self.batch_depth > 0
+    }
+
+    /// Call `f` with the console in a batch of updates, as with [`Console::begin_batch`] and [`Console::end_batch`], and return its result
+    pub fn batch<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        self.begin_batch();
+        let result = f(self);
+        self.end_batch();
+        result
+    }
+
+    /// Draw the console to an embedded-graphics [`DrawTarget`]
+    ///
+    /// Nothing is drawn during a [batch of updates](Console::begin_batch). The draw stops at the first error of the display, unless [`Console::set_resilient_draw`] is on.
+    pub fn draw<D, P: PixelColor + From<C> + ColorInterpolate>(
+        &mut self,
+        display: &mut D,
+    ) -> Result<(), <D as DrawTarget>::Error>
+    where
+        D: DrawTarget<Color = P>,
+        C: Copy,
+    {
+        if self.is_batching() {
+            return Ok(());
+        }
+        match self.cell_style.rotation {
+            Rotation::Deg0 => self.draw_unrotated(display),
+            _ => self.draw_unrotated(&mut self.rotated(display)),
+        }
+    }
+
+    /// Draw a thin scrollbar in `color` over the right edge of the console, showing which lines of a longer history it displays, e.g. when an application shows its own scrollback.
+    ///
+    /// `first_line` is the line of the history shown on the top row, and `total_lines` is the length of the history, including the lines on screen. Nothing is drawn when the last line of the history is shown. The cells under the bar are drawn again by the next call to [`Console::draw`], which removes it, so this should be called after every draw while the bar is shown. Nothing is drawn during a [batch of updates](Console::begin_batch).
+    pub fn draw_scrollbar<D, P>(
+        &mut self,
+        display: &mut D,
+        first_line: usize,
+        total_lines: usize,
+        color: P,
+    ) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = P>,
+        P: PixelColor,
+    {
+        if self.is_batching() {
+            return Ok(());
+        }
+        let (rows, columns) = (self.rows(), self.columns());
+        if first_line + rows >= total_lines {
+            return Ok(());
+        }
+        let cell_height = self.cell_style.cell_size().height.max(1) as u64;
+        let track = rows as u64 * cell_height;
+        let total = total_lines as u64;
+        let height = (track * rows as u64 / total).max(1);
+        let top = (track * first_line as u64 / total).min(track - height);
+        // Only the cells under the bar need to be drawn again to remove it
+        for row in top / cell_height..(top + height).div_ceil(cell_height) {
+            self.mark_cell_dirty(row as usize, columns - 1);
+        }
+        let origin = self.cell_style.cell_origin(0, columns)
+            + Point::new(-(SCROLLBAR_WIDTH as i32), top as i32);
+        self.rotated(display).fill_solid(
+            &Rectangle::new(origin, Size::new(SCROLLBAR_WIDTH, height as u32)),
+            color,
+        )
+    }
+
+    fn draw_unrotated<D, P: PixelColor + From<C> + ColorInterpolate>(
+        &mut self,
+        display: &mut D,
+    ) -> Result<(), <D as DrawTarget>::Error>
+    where
+        D: DrawTarget<Color = P>,
+        C: Copy,
+    {
+        let mut first_error = None;
+        // Images are drawn again over the cells under them
+        #[cfg(feature = "sixel")]
+        {
+            let (cell_size, height) = (self.inner.cell_size, self.inner.buf.height());
+            for placed in &mut self.inner.images {
+                let bottom = placed.row + placed.image.rows(cell_size) as isize;
+                let rows = placed.row.max(0) as usize..(bottom.max(0) as usize).min(height);
+                placed.dirty |= rows.into_iter().any(|row| self.inner.buf.is_row_dirty(row));
             }
-            LineClearMode::Left => {
-                for i in 0..=self.cursor.col {
-                    self.buf.write(self.cursor.row, i, bg);
+        }
+        for row in 0..self.inner.buf.height() {
+            if !self.inner.buf.is_row_dirty(row) {
+                continue;
+            }
+            let line_attr = self.inner.buf.line_attr(row);
+            let width = self.inner.buf.width();
+            let selection = self.selection;
+            let default_bg = self.inner.buf.default_cell().bg;
+            let mut still_dirty = false;
+            for (col, cell) in self.inner.buf.row_mut(row).iter_mut().enumerate() {
+                if cell.to_flush > 0 {
+                    // The selection and inverted colors are drawn over the cell, without changing it
+                    let mut drawn = *cell;
+                    if selection.is_some_and(|selection| is_selected(selection, row, col))
+                        != self.inverted
+                    {
+                        drawn.flags.toggle(Flags::INVERSE);
+                    }
+                    let style = &self.cell_style;
+                    let overlay = self.overlay
+                        && drawn.bg == default_bg
+                        && !drawn.flags.intersects(Flags::INVERSE | Flags::DIM);
+                    let result = if overlay {
+                        let background = P::from(style.color_to_pixel(drawn.bg));
+                        let mut overlaid = Transparent {
+                            target: display,
+                            color: background,
+                        };
+                        draw_cell_on_line(style, &drawn, row, col, line_attr, width, &mut overlaid)
+                    } else {
+                        draw_cell_on_line(style, &drawn, row, col, line_attr, width, display)
+                    };
+                    match result {
+                        Ok(()) => cell.to_flush -= 1,
+                        Err(error) if self.resilient_draw => {
+                            warn!("Failed to draw the cell at ({}, {}), skipping it", row, col);
+                            first_error.get_or_insert(error);
+                        }
+                        Err(error) => return Err(error),
+                    }
+                    still_dirty |= cell.to_flush > 0;
                 }
             }
-            LineClearMode::All => {
-                for i in 0..self.buf.width() {
-                    self.buf.write(self.cursor.row, i, bg);
+            self.inner.buf.set_row_dirty(row, still_dirty);
+        }
+
+        #[cfg(feature = "sixel")]
+        for placed in self.inner.images.iter_mut().filter(|placed| placed.dirty) {
+            let size = self.cell_style.cell_size();
+            // Images can start above the top row
+            let origin = self.cell_style.cell_origin(0, placed.col)
+                + Point::new(0, placed.row as i32 * size.height as i32);
+            let style = &self.cell_style;
+            let result = placed.image.draw(
+                origin,
+                // RGB colors are never resolved ahead of time
+                |rgb| P::from(style.color_to_pixel(crate::Color::RGB(rgb))),
+                display,
+            );
+            match result {
+                Ok(()) => placed.dirty = false,
+                Err(error) if self.resilient_draw => {
+                    warn!(
+                        "Failed to draw the image at ({}, {}), skipping it",
+                        placed.row, placed.col
+                    );
+                    first_error.get_or_insert(error);
                 }
+                Err(error) => return Err(error),
             }
         }
+
+        first_error.map_or(Ok(()), Err)
     }
 
-    fn clear_screen(&mut self, mode: ClearMode) {
-        trace!("Clearing screen: {:?}", mode);
-        let bg = self.temp.just_bg();
-        let row = self.cursor.row;
-        let col = self.cursor.col;
-        match mode {
-            ClearMode::Above => {
-                for i in 0..row {
-                    for j in 0..self.buf.width() {
-                        self.buf.write(i, j, bg);
-                    }
-                }
-                for j in 0..col {
-                    self.buf.write(row, j, bg);
-                }
+    /// Draw every cell once to a display that discards the pixels, so that the first call to [`Console::draw`] isn't slower than the ones after it, e.g. from cold caches.
+    ///
+    /// `P` is the pixel color of the display that the console is drawn to. Nothing is marked as drawn.
+    pub fn prewarm<P: PixelColor + From<C> + ColorInterpolate>(&self) {
+        let mut display = DiscardingDisplay::<P>(core::marker::PhantomData);
+        for (row, cells) in self.rows_iter().enumerate() {
+            for (col, cell) in cells.iter().enumerate() {
+                let _ = self.cell_style.draw_cell(cell, row, col, &mut display);
             }
-            ClearMode::Below => {
-                for j in col..self.buf.width() {
-                    self.buf.write(row, j, bg);
-                }
-                for i in row + 1..self.buf.height() {
-                    for j in 0..self.buf.width() {
-                        self.buf.write(i, j, bg);
+        }
+    }
+
+    /// Draw the console with a custom function instead of the [`Style`].
+    ///
+    /// `pixel_fn` is called with the row, column and content of each cell that needs to be drawn, and returns the pixels to draw for it, if any. Cells are then marked as drawn, as with [`Console::draw`]. The [`Style`] offset and rotation are not applied.
+    pub fn draw_direct<D, P, FN, I>(
+        &mut self,
+        display: &mut D,
+        mut pixel_fn: FN,
+    ) -> Result<(), <D as DrawTarget>::Error>
+    where
+        D: DrawTarget<Color = P>,
+        P: PixelColor,
+        FN: FnMut(usize, usize, &Cell) -> Option<I>,
+        I: IntoIterator<Item = Pixel<P>>,
+    {
+        if self.is_batching() {
+            return Ok(());
+        }
+        for row in 0..self.inner.buf.height() {
+            if !self.inner.buf.is_row_dirty(row) {
+                continue;
+            }
+            let mut still_dirty = false;
+            for (col, cell) in self.inner.buf.row_mut(row).iter_mut().enumerate() {
+                if cell.to_flush > 0 {
+                    if let Some(pixels) = pixel_fn(row, col, cell) {
+                        display.draw_iter(pixels)?;
                     }
+                    cell.to_flush -= 1;
+                    still_dirty |= cell.to_flush > 0;
                 }
             }
-            ClearMode::All => {
-                self.buf.clear(bg);
-                self.cursor = Cursor::default();
-            }
-            _ => {}
+            self.inner.buf.set_row_dirty(row, still_dirty);
         }
+        Ok(())
     }
 
-    fn terminal_attribute(&mut self, attr: Attr) {
-        trace!("Setting attribute: {:?}", attr);
-        match attr {
-            Attr::Foreground(color) => self.temp.fg = color,
-            Attr::Background(color) => self.temp.bg = color,
-            Attr::Reset => self.temp = Cell::default(),
-            Attr::Reverse => self.temp.flags |= Flags::INVERSE,
-            Attr::CancelReverse => self.temp.flags.remove(Flags::INVERSE),
-            Attr::Bold => self.temp.flags.insert(Flags::BOLD),
-            Attr::CancelBold => self.temp.flags.remove(Flags::BOLD),
-            Attr::Dim => self.temp.flags.insert(Flags::DIM),
-            Attr::CancelBoldDim => self.temp.flags.remove(Flags::BOLD | Flags::DIM),
-            Attr::Italic => self.temp.flags.insert(Flags::ITALIC),
-            Attr::CancelItalic => self.temp.flags.remove(Flags::ITALIC),
-            Attr::Underline => self.temp.flags.insert(Flags::UNDERLINE),
-            Attr::CancelUnderline => self.temp.flags.remove(Flags::UNDERLINE),
-            Attr::Hidden => self.temp.flags.insert(Flags::HIDDEN),
-            Attr::CancelHidden => self.temp.flags.remove(Flags::HIDDEN),
-            Attr::Strike => self.temp.flags.insert(Flags::STRIKEOUT),
-            Attr::CancelStrike => self.temp.flags.remove(Flags::STRIKEOUT),
-            _ => {
-                debug!("Term got unhandled attr: {:?}", attr);
-            }
+    /// Clear the screen
+    pub fn clear_screen(&mut self, mode: ClearMode) {
+        self.inner.clear_screen(mode);
+    }
+
+    /// Clear the line
+    pub fn clear_line(&mut self, mode: LineClearMode) {
+        self.inner.clear_line(mode);
+    }
+}
+
+impl<'a, C, F> fmt::Write for Console<'a, C, F>
+where
+    Style<'a, C, F>: DrawCell<C>,
+{
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            self.write_byte(byte);
         }
+        Ok(())
     }
+}
 
-    fn set_mode(&mut self, mode: Mode) {
-        if mode == Mode::LineWrap {
-            self.auto_wrap = true;
-        } else {
-            debug!("[Unhandled CSI] Setting mode: {:?}", mode);
+impl<'a, C, F> core::ops::Index<(usize, usize)> for Console<'a, C, F> {
+    type Output = Cell;
+
+    /// The cell at `(row, col)`, or the [default cell](CellBuffer::default_cell) if it is out of bounds
+    fn index(&self, (row, col): (usize, usize)) -> &Cell {
+        self.inner.buf.get_or_default(row, col)
+    }
+}
+
+impl<'a, C, F> core::ops::IndexMut<(usize, usize)> for Console<'a, C, F> {
+    /// The mutable cell at `(row, col)`, which is drawn again on the next call to [`Console::draw`]. Changes to cells that are out of bounds are discarded.
+    fn index_mut(&mut self, (row, col): (usize, usize)) -> &mut Cell {
+        self.inner.buf.get_mut_or_scratch(row, col)
+    }
+}
+
+/// A display that computes and then discards every pixel drawn to it
+struct DiscardingDisplay<P>(core::marker::PhantomData<P>);
+
+impl<P> OriginDimensions for DiscardingDisplay<P> {
+    fn size(&self) -> Size {
+        Size::new(u32::MAX, u32::MAX)
+    }
+}
+
+impl<P: PixelColor> DrawTarget for DiscardingDisplay<P> {
+    type Color = P;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        pixels.into_iter().for_each(drop);
+        Ok(())
+    }
+}
+
+/// A display that discards the pixels of one color, e.g. to draw a cell without its background
+struct Transparent<'d, D: DrawTarget> {
+    target: &'d mut D,
+    color: D::Color,
+}
+
+impl<D: DrawTarget> Dimensions for Transparent<'_, D> {
+    fn bounding_box(&self) -> Rectangle {
+        self.target.bounding_box()
+    }
+}
+
+impl<D: DrawTarget> DrawTarget for Transparent<'_, D> {
+    type Color = D::Color;
+    type Error = D::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let color = self.color;
+        self.target.draw_iter(
+            pixels
+                .into_iter()
+                .filter(move |Pixel(_, pixel)| *pixel != color),
+        )
+    }
+}
+
+/// Draw `cell` at `row`, `col`, scaled as needed for the [`LineAttr`] of its row. Cells in the right half of a row that isn't [`LineAttr::Normal`] aren't shown.
+fn draw_cell_on_line<'a, C, F, D, P>(
+    style: &Style<'a, C, F>,
+    cell: &Cell,
+    row: usize,
+    col: usize,
+    line_attr: LineAttr,
+    width: usize,
+    display: &mut D,
+) -> Result<(), D::Error>
+where
+    Style<'a, C, F>: DrawCell<C>,
+    D: DrawTarget<Color = P>,
+    P: PixelColor + From<C> + ColorInterpolate,
+{
+    if line_attr == LineAttr::Normal {
+        style.draw_cell(cell, row, col, display)
+    } else if col * 2 < width {
+        // Draw the cell in the top left corner, to be scaled into place
+        let mut scaled = LineScaled::new(
+            display,
+            style.cell_origin(0, 0),
+            style.cell_origin(row, col * 2),
+            style.cell_size(),
+            line_attr,
+        );
+        style.draw_cell(cell, 0, 0, &mut scaled)
+    } else {
+        Ok(())
+    }
+}
+
+fn is_printable_ascii(byte: u8) -> bool {
+    (0x20..=0x7e).contains(&byte)
+}
+
+impl ConsoleInner {
+    fn save_state(&self) -> SavedState {
+        SavedState {
+            cursor: self.cursor,
+            temp: self.temp,
+            auto_wrap: self.auto_wrap,
+            scrolling_region: self.scrolling_region,
+            margins: self.margins,
         }
     }
 
-    fn unset_mode(&mut self, mode: Mode) {
-        if mode == Mode::LineWrap {
-            self.auto_wrap = false;
-        } else {
-            debug!("[Unhandled CSI] Setting mode: {:?}", mode);
+    fn restore_state(&mut self, state: SavedState) {
+        self.goto(state.cursor.row, state.cursor.col);
+        self.temp = state.temp;
+        self.auto_wrap = state.auto_wrap;
+        // The console may have been resized since
+        self.scrolling_region = state
+            .scrolling_region
+            .filter(|&(_, bottom)| bottom < self.buf.height());
+        self.margins = state.margins.filter(|&(_, right)| right < self.buf.width());
+    }
+
+    /// Move to the start of the next line, resetting the attributes if `reset_attributes_on_newline` is set
+    fn new_line_and_reset(&mut self) {
+        if self.reset_attributes_on_newline {
+            self.temp = self.buf.default_cell();
         }
+        self.next_line();
     }
 
-    fn set_scrolling_region(&mut self, top: usize, bottom: Option<usize>) {
-        let bottom = bottom.unwrap_or_else(|| self.buf.height());
-        debug!(
-            "[Unhandled CSI] Setting scrolling region: ({};{})",
-            top, bottom
+    /// Queue an event for `Console::poll_event` if the queue is on, dropping the oldest one if it is full
+    fn push_event(&mut self, event: TerminalEvent) {
+        if !self.event_queue {
+            return;
+        }
+        if self.events.len() >= EVENT_QUEUE_MAX_LEN {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+
+    /// The cell that erased cells are filled with
+    pub(crate) fn erased_cell(&self) -> Cell {
+        match self.erase_mode {
+            EraseMode::CurrentBackground => Cell {
+                bg: self.temp.bg,
+                ..self.buf.default_cell()
+            },
+            EraseMode::DefaultCell => self.buf.default_cell(),
+        }
+    }
+
+    /// The end (exclusive) of the columns that character operations at the cursor act on: the right margin if the cursor is within the margins, otherwise the end of the row
+    fn line_end(&self) -> usize {
+        match self.margins {
+            Some((left, right)) if (left..=right).contains(&self.cursor.col) => right + 1,
+            _ => self.buf.width(),
+        }
+    }
+
+    /// Save the cursor and show a cleared alternate screen buffer
+    fn enter_alternate_screen(&mut self) {
+        if self.primary_buf.is_some() {
+            return;
+        }
+        self.saved_state = self.save_state();
+        let mut alternate = CellBuffer::with_default_cell(
+            self.buf.width(),
+            self.buf.height(),
+            self.buf.default_cell(),
         );
+        alternate.set_flush_count(self.buf.flush_count());
+        alternate.clear(self.erased_cell());
+        self.primary_buf = Some(core::mem::replace(&mut self.buf, alternate));
+        // Images are shown on the primary screen, which is redrawn in full when it is shown again
+        self.drop_images(0..self.buf.height(), 0..self.buf.width());
     }
 
-    fn device_status(&mut self, arg: usize) {
-        trace!("Reporting device status: {}", arg);
-        match arg {
-            5 => {
-                for &c in b"\x1b[0n" {
-                    self.report.push_back(c);
+    /// Show the primary screen buffer again and restore the cursor
+    fn exit_alternate_screen(&mut self) {
+        if let Some(primary) = self.primary_buf.take() {
+            self.buf = primary;
+            self.buf.mark_all_dirty();
+            self.restore_state(self.saved_state);
+        }
+    }
+
+    /// Move the cursor to the start of the next line, scrolling if needed.
+    fn next_line(&mut self) {
+        self.cursor.col = 0;
+        if let Some((top, bottom)) = self.scrolling_region
+            && self.cursor.row == bottom
+        {
+            self.scroll_region_up(top, bottom);
+        } else if self.cursor.row < self.buf.height() - 1 {
+            self.cursor.row += 1;
+        } else {
+            self.buf.new_line(self.erased_cell());
+            #[cfg(feature = "sixel")]
+            {
+                let cell_size = self.cell_size;
+                for placed in &mut self.images {
+                    placed.row -= 1;
                 }
+                self.images
+                    .retain(|placed| placed.row + placed.image.rows(cell_size) as isize > 0);
             }
-            6 => {
-                let s = alloc::format!("\x1b[{};{}R", self.cursor.row + 1, self.cursor.col + 1);
-                for c in s.bytes() {
-                    self.report.push_back(c);
-                }
+        }
+    }
+
+    /// Move the rows from `top` to `bottom` (inclusive) up one row, losing the top one and blanking the bottom one
+    fn scroll_region_up(&mut self, top: usize, bottom: usize) {
+        self.drop_images(top..bottom + 1, 0..self.buf.width());
+        for row in top..bottom {
+            self.copy_row(row + 1, row);
+        }
+        self.blank_row(bottom);
+    }
+
+    /// Move the rows from `top` to `bottom` (inclusive) down one row, losing the bottom one and blanking the top one
+    fn scroll_region_down(&mut self, top: usize, bottom: usize) {
+        self.drop_images(top..bottom + 1, 0..self.buf.width());
+        for row in (top..bottom).rev() {
+            self.copy_row(row, row + 1);
+        }
+        self.blank_row(top);
+    }
+
+    /// Stop showing the images that cover any of the cells in `rows` and `cols`, e.g. when they are overwritten
+    fn drop_images(&mut self, rows: Range<usize>, cols: Range<usize>) {
+        #[cfg(feature = "sixel")]
+        {
+            let cell_size = self.cell_size;
+            self.images
+                .retain(|placed| !placed.overlaps(&rows, &cols, cell_size));
+        }
+        #[cfg(not(feature = "sixel"))]
+        let _ = (rows, cols);
+    }
+
+    /// Copy the cells and line attribute of row `from` to row `to`
+    fn copy_row(&mut self, from: usize, to: usize) {
+        for col in 0..self.buf.width() {
+            self.buf.write(to, col, self.buf.read(from, col));
+        }
+        self.buf.set_line_attr(to, self.buf.line_attr(from));
+    }
+
+    /// Erase every cell of `row`, as if it had scrolled onto the screen
+    fn blank_row(&mut self, row: usize) {
+        let bg = self.erased_cell();
+        for col in 0..self.buf.width() {
+            self.buf.write(row, col, bg);
+        }
+        self.buf.set_line_attr(row, LineAttr::Normal);
+    }
+
+    /// Place a sixel image at the cursor, and move the cursor to the start of the line below it
+    #[cfg(feature = "sixel")]
+    fn place_sixel(&mut self, dcs: &DcsString) {
+        let max_width = (self.buf.width() - self.cursor.col) * self.cell_size.width as usize;
+        let max_height = self.buf.height() * self.cell_size.height as usize;
+        let image = SixelImage::decode(&dcs.params, &dcs.payload, max_width, max_height);
+        let rows = image.rows(self.cell_size);
+        trace!("Placing {}x{} sixel image", image.width, image.height);
+        self.images.push(PlacedImage {
+            row: self.cursor.row as isize,
+            col: self.cursor.col,
+            image,
+            dirty: true,
+        });
+        for _ in 0..rows {
+            self.next_line();
+        }
+    }
+}
+
+/// Writes text as-is, without interpreting control sequences.
+impl fmt::Write for ConsoleInner {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            self.input(c);
+        }
+        Ok(())
+    }
+}
+
+impl Handler for ConsoleInner {
+    fn input(&mut self, c: char) {
+        trace!("  [input]: {:?} @ {:?}", c, self.cursor);
+        // Characters that are joined to the one before, e.g. in emoji sequences, take no space and are dropped
+        if self.joiner.take() == Some(self.cursor) {
+            return;
+        }
+        if char_width(c) == 0 {
+            if c == '\u{200d}' {
+                self.joiner = Some(self.cursor);
             }
-            _ => debug!("unknown device status query: {}", arg),
+            return;
+        }
+        if self.cursor.col >= self.buf.width() {
+            if !self.auto_wrap {
+                // skip this one
+                return;
+            }
+            // Mark the row as continuing on the next one
+            let last = self.buf.width() - 1;
+            if let Some(cell) = self.buf.row_mut(self.cursor.row).get_mut(last) {
+                cell.flags.insert(Flags::WRAPLINE);
+            }
+            self.next_line();
         }
+        let mut temp = self.temp;
+        temp.c = c;
+        let (row, col) = (self.cursor.row, self.cursor.col);
+        self.drop_images(row..row + 1, col..col + 1);
+        self.buf.write(row, col, temp);
+        self.cursor.col += 1;
+        self.last_char = Some(c);
+    }
+
+    fn goto(&mut self, row: usize, col: usize) {
+        trace!("Going to: line={}, col={}", row, col);
+        // The cursor is only past the last column when a wrap is pending after writing to it
+        self.cursor.row = min(row, self.buf.height().saturating_sub(1));
+        self.cursor.col = min(col, self.buf.width().saturating_sub(1));
+    }
+
+    fn goto_line(&mut self, row: usize) {
+        trace!("Going to line: {}", row);
+        self.goto(row, self.cursor.col)
+    }
+
+    fn goto_col(&mut self, col: usize) {
+        trace!("Going to column: {}", col);
+        self.goto(self.cursor.row, col)
+    }
+
+    fn move_up(&mut self, rows: usize) {
+        trace!("Moving up: {}", rows);
+        self.goto(self.cursor.row.saturating_sub(rows), self.cursor.col)
+    }
+
+    fn move_down(&mut self, rows: usize) {
+        trace!("Moving down: {}", rows);
+        self.goto(
+            min(self.cursor.row + rows, self.buf.height() - 1) as _,
+            self.cursor.col,
+        )
+    }
+
+    fn move_forward(&mut self, cols: usize) {
+        trace!("Moving forward: {}", cols);
+        self.cursor.col = min(self.cursor.col + cols, self.buf.width() - 1);
+    }
+
+    fn move_backward(&mut self, cols: usize) {
+        trace!("Moving backward: {}", cols);
+        self.cursor.col = self.cursor.col.saturating_sub(cols);
+    }
+
+    fn move_down_and_cr(&mut self, rows: usize) {
+        trace!("Moving down and cr: {}", rows);
+        self.goto(min(self.cursor.row + rows, self.buf.height() - 1) as _, 0)
+    }
+
+    fn move_up_and_cr(&mut self, rows: usize) {
+        trace!("Moving up and cr: {}", rows);
+        self.goto(self.cursor.row.saturating_sub(rows), 0)
+    }
+
+    fn put_tab(&mut self, count: u16) {
+        let mut count = count;
+        let bg = self.erased_cell();
+        while self.cursor.col < self.buf.width() && count > 0 {
+            count -= 1;
+            loop {
+                self.buf.write(self.cursor.row, self.cursor.col, bg);
+                self.cursor.col += 1;
+                if self.cursor.col == self.buf.width()
+                    || self.cursor.col.is_multiple_of(self.tab_width)
+                {
+                    break;
+                }
+            }
+        }
+    }
+
+    fn move_forward_tabs(&mut self, count: u16) {
+        trace!("Moving forward tabs: {}", count);
+        let last = self.buf.width() - 1;
+        for _ in 0..count {
+            if self.cursor.col >= last {
+                break;
+            }
+            self.cursor.col = min(
+                self.cursor.col - self.cursor.col % self.tab_width + self.tab_width,
+                last,
+            );
+        }
+    }
+
+    fn move_backward_tabs(&mut self, count: u16) {
+        trace!("Moving backward tabs: {}", count);
+        for _ in 0..count {
+            if self.cursor.col == 0 {
+                break;
+            }
+            let col = min(self.cursor.col, self.buf.width()) - 1;
+            self.cursor.col = col - col % self.tab_width;
+        }
+    }
+
+    fn backspace(&mut self) {
+        trace!("Backspace");
+        if self.cursor.col > 0 {
+            self.cursor.col -= 1;
+        } else if self.reverse_wrap && self.cursor.row > 0 {
+            self.cursor.row -= 1;
+            self.cursor.col = self.buf.width() - 1;
+        }
+    }
+
+    fn carriage_return(&mut self) {
+        trace!("Carriage return");
+        if self.newline_mode == NewlineMode::Cr {
+            self.new_line_and_reset();
+        } else {
+            self.cursor.col = 0;
+        }
+    }
+
+    fn linefeed(&mut self) {
+        trace!("Linefeed");
+        match self.newline_mode {
+            NewlineMode::Lf => self.new_line_and_reset(),
+            NewlineMode::Cr => {}
+            NewlineMode::CrLf if self.linefeed_newline => self.new_line_and_reset(),
+            NewlineMode::CrLf => {
+                let col = min(self.cursor.col, self.buf.width() - 1);
+                self.new_line_and_reset();
+                self.cursor.col = col;
+            }
+        }
+    }
+
+    fn reverse_index(&mut self) {
+        trace!("Reverse index");
+        let (top, bottom) = self.scrolling_region.unwrap_or((0, self.buf.height() - 1));
+        if self.cursor.row == top {
+            self.scroll_region_down(top, bottom);
+        } else {
+            self.cursor.row = self.cursor.row.saturating_sub(1);
+        }
+    }
+
+    fn bell(&mut self) {
+        trace!("Bell");
+        self.bell_count = self.bell_count.wrapping_add(1);
+        self.push_event(TerminalEvent::Bell);
+        if let Some(callback) = self.bell_callback {
+            callback();
+        }
+    }
+
+    fn scroll_up(&mut self, rows: usize) {
+        debug!("[Unhandled CSI] scroll_up {:?}", rows);
+    }
+
+    fn scroll_down(&mut self, rows: usize) {
+        debug!("[Unhandled CSI] scroll_down {:?}", rows);
+    }
+
+    fn scroll_left(&mut self, cols: usize) {
+        trace!("Scrolling left: cols={}", cols);
+        let (left, right) = self.margins.unwrap_or((0, self.buf.width() - 1));
+        let (top, bottom) = self.scrolling_region.unwrap_or((0, self.buf.height() - 1));
+        let bg = self.erased_cell();
+        for row in top..=bottom {
+            for col in left..=right {
+                let cell = match col.checked_add(cols) {
+                    Some(from) if from <= right => self.buf.read(row, from),
+                    _ => bg,
+                };
+                self.buf.write(row, col, cell);
+            }
+        }
+    }
+
+    fn scroll_right(&mut self, cols: usize) {
+        trace!("Scrolling right: cols={}", cols);
+        let (left, right) = self.margins.unwrap_or((0, self.buf.width() - 1));
+        let (top, bottom) = self.scrolling_region.unwrap_or((0, self.buf.height() - 1));
+        let bg = self.erased_cell();
+        for row in top..=bottom {
+            for col in (left..=right).rev() {
+                let cell = match col.checked_sub(cols) {
+                    Some(from) if from >= left => self.buf.read(row, from),
+                    _ => bg,
+                };
+                self.buf.write(row, col, cell);
+            }
+        }
+    }
+
+    fn erase_chars(&mut self, count: usize) {
+        trace!("Erasing chars: count={}, col={}", count, self.cursor.col);
+
+        let start = self.cursor.col;
+        let end = min(start.saturating_add(count), self.line_end());
+
+        let bg = self.erased_cell();
+        for i in start..end {
+            self.buf.write(self.cursor.row, i, bg);
+        }
+    }
+    fn delete_chars(&mut self, count: usize) {
+        trace!("Deleting chars: count={}, col={}", count, self.cursor.col);
+        let row = self.cursor.row;
+        // Only the cells up to the right margin are shifted
+        let start = min(self.cursor.col, self.buf.width() - 1);
+        let end = self.line_end();
+
+        let bg = self.erased_cell();
+        for col in start..end {
+            let cell = match col.saturating_add(count) {
+                from if from < end => self.buf.read(row, from),
+                _ => bg,
+            };
+            self.buf.write(row, col, cell);
+        }
+    }
+
+    fn insert_blank(&mut self, count: usize) {
+        trace!("Inserting blanks: count={}, col={}", count, self.cursor.col);
+        let row = self.cursor.row;
+        // Cells that are shifted past the right margin are lost
+        let start = min(self.cursor.col, self.buf.width() - 1);
+        let end = self.line_end();
+
+        let bg = self.erased_cell();
+        for col in (start..end).rev() {
+            let cell = match col.checked_sub(count) {
+                Some(from) if from >= start => self.buf.read(row, from),
+                _ => bg,
+            };
+            self.buf.write(row, col, cell);
+        }
+    }
+
+    fn repeat_char(&mut self, count: usize) {
+        let Some(c) = self.last_char else {
+            return;
+        };
+        trace!("Repeating: {:?} count={}", c, count);
+        // Within the margins, repetition stops at the right margin
+        let count = match self.margins {
+            Some((left, right)) if (left..=right).contains(&self.cursor.col) => {
+                min(count, right + 1 - self.cursor.col)
+            }
+            _ => count,
+        };
+        for _ in 0..count {
+            self.input(c);
+        }
+    }
+
+    /// Save current cursor position.
+    fn save_cursor_position(&mut self) {
+        trace!("Saving cursor position");
+        self.saved_state = self.save_state();
+    }
+
+    /// Restore cursor position.
+    fn restore_cursor_position(&mut self) {
+        trace!("Restoring cursor position");
+        self.restore_state(self.saved_state);
+    }
+
+    fn set_line_attr(&mut self, attr: LineAttr) {
+        trace!("Setting line attribute: {:?}", attr);
+        self.buf.set_line_attr(self.cursor.row, attr);
+    }
+
+    fn clear_line(&mut self, mode: LineClearMode) {
+        trace!("Clearing line: {:?}", mode);
+        let bg = self.erased_cell();
+        let (row, col, width) = (self.cursor.row, self.cursor.col, self.buf.width());
+        match mode {
+            LineClearMode::Right => self.drop_images(row..row + 1, col..width),
+            LineClearMode::Left => self.drop_images(row..row + 1, 0..col + 1),
+            LineClearMode::All => self.drop_images(row..row + 1, 0..width),
+        }
+        match mode {
+            LineClearMode::Right => {
+                for i in self.cursor.col..self.buf.width() {
+                    self.buf.write(self.cursor.row, i, bg);
+                }
+            }
+            LineClearMode::Left => {
+                for i in 0..=self.cursor.col {
+                    self.buf.write(self.cursor.row, i, bg);
+                }
+            }
+            LineClearMode::All => {
+                for i in 0..self.buf.width() {
+                    self.buf.write(self.cursor.row, i, bg);
+                }
+            }
+        }
+    }
+
+    fn clear_screen(&mut self, mode: ClearMode) {
+        trace!("Clearing screen: {:?}", mode);
+        let bg = self.erased_cell();
+        let row = self.cursor.row;
+        let col = self.cursor.col;
+        let (width, height) = (self.buf.width(), self.buf.height());
+        match mode {
+            ClearMode::Above => {
+                self.drop_images(0..row, 0..width);
+                self.drop_images(row..row + 1, 0..col);
+            }
+            ClearMode::Below => {
+                self.drop_images(row..row + 1, col..width);
+                self.drop_images(row + 1..height, 0..width);
+            }
+            ClearMode::All => self.drop_images(0..height, 0..width),
+            _ => {}
+        }
+        match mode {
+            ClearMode::Above => {
+                for i in 0..row {
+                    for j in 0..self.buf.width() {
+                        self.buf.write(i, j, bg);
+                    }
+                }
+                for j in 0..col {
+                    self.buf.write(row, j, bg);
+                }
+            }
+            ClearMode::Below => {
+                for j in col..self.buf.width() {
+                    self.buf.write(row, j, bg);
+                }
+                for i in row + 1..self.buf.height() {
+                    for j in 0..self.buf.width() {
+                        self.buf.write(i, j, bg);
+                    }
+                }
+            }
+            ClearMode::All => {
+                self.buf.clear(bg);
+                self.cursor = Cursor::default();
+            }
+            _ => {}
+        }
+    }
+
+    fn terminal_attribute(&mut self, attr: Attr) {
+        trace!("Setting attribute: {:?}", attr);
+        match attr {
+            Attr::Foreground(color) => self.temp.fg = color,
+            Attr::Background(color) => self.temp.bg = color,
+            Attr::DefaultForeground => self.temp.fg = self.buf.default_cell().fg,
+            Attr::DefaultBackground => self.temp.bg = self.buf.default_cell().bg,
+            Attr::UnderlineColor(color) => self.temp.underline_color = color,
+            Attr::Reset => self.temp = self.buf.default_cell(),
+            Attr::Reverse => self.temp.flags |= Flags::INVERSE,
+            Attr::CancelReverse => self.temp.flags.remove(Flags::INVERSE),
+            Attr::Bold => self.temp.flags.insert(Flags::BOLD),
+            Attr::CancelBold => self.temp.flags.remove(Flags::BOLD),
+            Attr::Dim => self.temp.flags.insert(Flags::DIM),
+            Attr::CancelBoldDim => self.temp.flags.remove(Flags::BOLD | Flags::DIM),
+            Attr::Italic => self.temp.flags.insert(Flags::ITALIC),
+            Attr::CancelItalic => self.temp.flags.remove(Flags::ITALIC),
+            Attr::Underline => self.temp.flags.insert(Flags::UNDERLINE),
+            Attr::CancelUnderline => self.temp.flags.remove(Flags::UNDERLINE),
+            Attr::Hidden => self.temp.flags.insert(Flags::HIDDEN),
+            Attr::CancelHidden => self.temp.flags.remove(Flags::HIDDEN),
+            Attr::Strike => self.temp.flags.insert(Flags::STRIKEOUT),
+            Attr::CancelStrike => self.temp.flags.remove(Flags::STRIKEOUT),
+            _ => {
+                debug!("Term got unhandled attr: {:?}", attr);
+            }
+        }
+    }
+
+    fn set_mode(&mut self, mode: Mode) {
+        match mode {
+            Mode::LineWrap => self.auto_wrap = true,
+            Mode::LineFeedNewLine => self.linefeed_newline = true,
+            Mode::ReverseWrap => self.reverse_wrap = true,
+            Mode::BlinkingCursor => self.cursor_blinking = true,
+            Mode::SwapScreenAndSetRestoreCursor => self.enter_alternate_screen(),
+            _ => debug!("[Unhandled CSI] Setting mode: {:?}", mode),
+        }
+    }
+
+    fn unset_mode(&mut self, mode: Mode) {
+        match mode {
+            Mode::LineWrap => self.auto_wrap = false,
+            Mode::LineFeedNewLine => self.linefeed_newline = false,
+            Mode::ReverseWrap => self.reverse_wrap = false,
+            Mode::BlinkingCursor => self.cursor_blinking = false,
+            Mode::SwapScreenAndSetRestoreCursor => self.exit_alternate_screen(),
+            _ => debug!("[Unhandled CSI] Setting mode: {:?}", mode),
+        }
+    }
+
+    fn set_scrolling_region(&mut self, top: usize, bottom: Option<usize>) {
+        let height = self.buf.height();
+        let bottom = bottom.unwrap_or(height);
+        if top >= bottom || bottom > height {
+            debug!("Ignoring invalid scrolling region: ({};{})", top, bottom);
+            return;
+        }
+        trace!("Setting scrolling region: ({};{})", top, bottom);
+        self.scrolling_region = (top > 1 || bottom < height).then_some((top - 1, bottom - 1));
+        self.goto(0, 0);
+    }
+
+    fn set_cursor_style(&mut self, style: u16) {
+        trace!("Setting cursor style: {}", style);
+        // 0 and the odd styles blink, the even ones are steady
+        self.cursor_blinking = style == 0 || style % 2 == 1;
+    }
+
+    fn set_margins(&mut self, left: usize, right: Option<usize>) {
+        let width = self.buf.width();
+        let right = right.unwrap_or(width);
+        if left >= right || right > width {
+            debug!("Ignoring invalid margins: ({};{})", left, right);
+            return;
+        }
+        trace!("Setting margins: ({};{})", left, right);
+        self.margins = (left > 1 || right < width).then_some((left - 1, right - 1));
+        self.goto(0, 0);
+    }
+
+    fn device_status(&mut self, arg: usize) {
+        trace!("Reporting device status: {}", arg);
+        match arg {
+            5 => {
+                self.report.extend(b"\x1b[0n");
+            }
+            6 => {
+                // The cursor is past the last column while a wrap is pending, but is reported in it
+                let col = min(self.cursor.col, self.buf.width() - 1);
+                let s = alloc::format!("\x1b[{};{}R", self.cursor.row + 1, col + 1);
+                self.report.extend(s.bytes());
+            }
+            _ => debug!("unknown device status query: {}", arg),
+        }
+    }
+
+    fn dcs_hook(&mut self, params: Vec<u16>, intermediates: &[u8], action: char) {
+        trace!("DCS hook: {:?} {:?} {:?}", params, intermediates, action);
+        let is_sixel = cfg!(feature = "sixel") && action == 'q';
+        if self.dcs_callback.is_some() || is_sixel {
+            self.dcs = Some(DcsString {
+                params,
+                intermediates: intermediates.to_vec(),
+                action,
+                payload: Vec::new(),
+            });
+        }
+    }
+
+    fn dcs_put(&mut self, byte: u8) {
+        if let Some(dcs) = &mut self.dcs {
+            dcs.payload.push(byte);
+        }
+    }
+
+    fn dcs_unhook(&mut self) {
+        trace!("DCS unhook");
+        let Some(dcs) = self.dcs.take() else {
+            return;
+        };
+        #[cfg(feature = "sixel")]
+        if dcs.action == 'q' {
+            self.place_sixel(&dcs);
+        }
+        if let Some(callback) = self.dcs_callback {
+            callback(&dcs);
+        }
+    }
+
+    fn inline_image(&mut self, image: InlineImage) {
+        trace!("Inline image: {:?}", image.params);
+        if let Some(callback) = self.inline_image_callback {
+            callback(&image);
+        }
+    }
+
+    fn set_title(&mut self, title: Option<String>) {
+        trace!("Setting title: {:?}", title);
+        if self.event_queue {
+            self.push_event(TerminalEvent::TitleChange(
+                title.clone().unwrap_or_default(),
+            ));
+        }
+        self.title = title;
+    }
+
+    fn push_title(&mut self) {
+        trace!("Pushing title: {:?}", self.title);
+        if self.title_stack.len() >= TITLE_STACK_MAX_DEPTH {
+            self.title_stack.remove(0);
+        }
+        self.title_stack.push(self.title.clone());
+    }
+
+    fn pop_title(&mut self) {
+        trace!("Popping title");
+        if let Some(title) = self.title_stack.pop() {
+            self.set_title(title);
+        }
+    }
+
+    fn request_resize(&mut self, rows: usize, cols: usize) {
+        trace!("Resize requested: {}x{}", rows, cols);
+        self.push_event(TerminalEvent::ResizeRequest(rows, cols));
+    }
+
+    fn paste_start(&mut self) {
+        trace!("Paste start");
+        self.push_event(TerminalEvent::PasteStart);
+    }
+
+    fn paste_end(&mut self) {
+        trace!("Paste end");
+        self.push_event(TerminalEvent::PasteEnd);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::NamedColor;
+    use crate::test_display::{CountingDisplay, NullDisplay, row_text};
+    use core::fmt::Write;
+    use embedded_graphics::mono_font::MonoFont;
+    use embedded_graphics::pixelcolor::Rgb888;
+    use embedded_graphics::primitives::Rectangle;
+
+    #[test]
+    fn test_newline_modes() {
+        let modes = [
+            (NewlineMode::Lf, ["B   ", "    "], ["A   ", "B   "]),
+            (NewlineMode::Cr, ["A   ", "B   "], ["AB  ", "    "]),
+            (NewlineMode::CrLf, ["B   ", "    "], ["A   ", " B  "]),
+        ];
+        for (mode, cr, lf) in modes {
+            for (text, expected) in [("A\rB", cr), ("A\nB", lf), ("A\r\nB", ["A   ", "B   "])] {
+                let mut console = Console::new(4, 2, Style::default());
+                console.set_newline_mode(mode);
+                console.write_str(text).unwrap();
+                let rows: Vec<String> = console.rows_iter().map(row_text).collect();
+                assert_eq!(rows, expected, "{:?} {:?}", mode, text);
+            }
+        }
+
+        // LNM makes LF return to the first column
+        let mut console = Console::new(4, 2, Style::default());
+        console.set_newline_mode(NewlineMode::CrLf);
+        console.write_str("\x1b[20hA\nB").unwrap();
+        let rows: Vec<String> = console.rows_iter().map(row_text).collect();
+        assert_eq!(rows, ["A   ", "B   "]);
+    }
+
+    #[test]
+    fn test_reset_attributes_on_newline() {
+        let mut console = Console::new(10, 3, Style::default());
+        console.set_reset_attributes_on_newline(true);
+        console.write_str("\x1b[31mred\nplain").unwrap();
+
+        assert_eq!(
+            console.inner.buf.read(0, 0).fg,
+            Color::Named(NamedColor::Red)
+        );
+        assert_eq!(
+            console.inner.buf.read(1, 0),
+            Cell {
+                c: 'p',
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_rows_iter_follows_scrolling() {
+        let mut console = Console::new(2, 3, Style::default());
+        console.write_str("a\nb\nc\nd").unwrap();
+
+        let rows: Vec<String> = console.rows_iter().map(row_text).collect();
+        assert_eq!(rows, ["b ", "c ", "d "]);
+    }
+
+    #[test]
+    fn test_dirty_rows_iter() {
+        let mut console = Console::new(4, 3, Style::default());
+        console.draw(&mut NullDisplay).unwrap();
+        assert_eq!(console.dirty_rows_iter().count(), 0);
+
+        console.write_str("\x1b[2;1Hab").unwrap();
+        let dirty: Vec<(usize, String)> = console
+            .dirty_rows_iter()
+            .map(|(row, cells)| (row, row_text(cells)))
+            .collect();
+        assert_eq!(dirty, [(1, String::from("ab  "))]);
+    }
+
+    #[test]
+    fn test_draw_skips_clean_rows() {
+        let mut console = Console::new(4, 3, Style::default());
+        console.draw(&mut NullDisplay).unwrap();
+        assert!((0..3).all(|row| !console.inner.buf.is_row_dirty(row)));
+
+        console.write_str("\x1b[2;1Hx").unwrap();
+        assert!(!console.inner.buf.is_row_dirty(0));
+        assert!(console.inner.buf.is_row_dirty(1));
+        assert!(!console.inner.buf.is_row_dirty(2));
+
+        let mut display = CountingDisplay::default();
+        console.draw(&mut display).unwrap();
+        assert_eq!(display.pixels, 9 * 18);
+        assert!(!console.inner.buf.is_row_dirty(1));
+    }
+
+    #[test]
+    fn test_mark_cell_clean_and_dirty() {
+        let mut console = Console::new(4, 3, Style::default());
+        console.write_str("ab").unwrap();
+        console.mark_cell_clean(0, 0);
+        console.mark_cell_clean(5, 5);
+        let mut display = CountingDisplay::default();
+        console.draw(&mut display).unwrap();
+        assert_eq!(display.pixels, 11 * 9 * 18);
+
+        console.mark_cell_dirty(2, 3);
+        console.mark_cell_dirty(0, 4);
+        assert_eq!(
+            console
+                .dirty_rows_iter()
+                .map(|(row, _)| row)
+                .collect::<Vec<_>>(),
+            [2]
+        );
+        let mut display = CountingDisplay::default();
+        console.draw(&mut display).unwrap();
+        assert_eq!(display.pixels, 9 * 18);
+    }
+
+    #[test]
+    fn test_prewarm() {
+        let mut console = Console::new(4, 3, Style::default());
+        console.write_str("\x1b[1mab").unwrap();
+        console.prewarm::<Rgb888>();
+        assert!(console.rows_iter().flatten().all(Cell::is_dirty));
+
+        let mut display = CountingDisplay::default();
+        console.draw(&mut display).unwrap();
+        assert_eq!(display.pixels, 12 * 9 * 18);
+    }
+
+    #[test]
+    fn test_line_attrs() {
+        use crate::test_display::Framebuffer;
+
+        let mut console = Console::new(4, 3, Style::default());
+        let (width, height) = console.image_dimensions();
+        let mut display = Framebuffer::new(Size::new(width, height), Rgb888::BLACK);
+        console
+            .write_str("\x1b#6\x1b[41m \x1b[42m \x1b[m\r\n\x1b#3\x1b[44m \x1b[m\r\n\x1b#4\x1b[44m_")
+            .unwrap();
+        assert_eq!(console.line_attr(0), LineAttr::DoubleWidth);
+        assert_eq!(console.line_attr(2), LineAttr::DoubleHeightBottom);
+        console.draw(&mut display).unwrap();
+
+        let (red, green, blue) = (
+            Rgb888::new(194, 54, 33),
+            Rgb888::new(37, 188, 36),
+            Rgb888::new(73, 46, 225),
+        );
+        // Each cell of the first row covers two columns
+        assert_eq!(display.pixel(17, 17), red);
+        assert_eq!(display.pixel(18, 0), green);
+        assert_eq!(display.pixel(35, 17), green);
+        assert_eq!(display.pixel(17, 18), blue);
+        // The underscore is in the bottom half of the double height cell
+        let underscore = |y| (0..18).filter(|&x| display.pixel(x, y) != blue).count();
+        assert!((36..54).map(underscore).sum::<usize>() > 0);
+
+        // Scrolling resets the attribute of the new row
+        console.write_str("\r\n").unwrap();
+        assert_eq!(console.line_attr(1), LineAttr::DoubleHeightBottom);
+        assert_eq!(console.line_attr(2), LineAttr::Normal);
+        console.set_line_attr(2, LineAttr::DoubleWidth);
+        assert_eq!(console.line_attr(2), LineAttr::DoubleWidth);
+    }
+
+    #[test]
+    fn test_overlay_mode() {
+        use crate::test_display::Framebuffer;
+
+        let mut console = Console::new(3, 1, Style::default());
+        let (width, height) = console.image_dimensions();
+        let scenery = Rgb888::new(1, 2, 3);
+        let mut display = Framebuffer::new(Size::new(width, height), scenery);
+        console.set_overlay_mode(true);
+        console.write_str("#\x1b[41m \x1b[7m ").unwrap();
+        console.draw(&mut display).unwrap();
+
+        // The glyph is drawn, but not the default background around it
+        let glyph = (0..9)
+            .flat_map(|x| (0..18).map(move |y| (x, y)))
+            .filter(|&(x, y)| display.pixel(x, y) != scenery)
+            .count();
+        assert!(glyph > 0 && glyph < 9 * 18);
+        assert_eq!(display.pixel(0, 0), scenery);
+        // Other backgrounds and inverse cells are drawn in full
+        assert_eq!(display.pixel(9, 0), Rgb888::new(194, 54, 33));
+        assert_ne!(display.pixel(18, 0), scenery);
+
+        console.set_overlay_mode(false);
+        console.write_str("\x1b[m\r ").unwrap();
+        console.draw(&mut display).unwrap();
+        assert_eq!(display.pixel(0, 0), Rgb888::BLACK);
+    }
+
+    #[test]
+    fn test_resilient_draw() {
+        use crate::test_display::Framebuffer;
+
+        /// A display that fails to draw the second cell while `failing` is set
+        struct FailingDisplay {
+            framebuffer: Framebuffer,
+            failing: bool,
+        }
+
+        impl Dimensions for FailingDisplay {
+            fn bounding_box(&self) -> Rectangle {
+                self.framebuffer.bounding_box()
+            }
+        }
+
+        impl DrawTarget for FailingDisplay {
+            type Color = Rgb888;
+            type Error = ();
+
+            fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+            where
+                I: IntoIterator<Item = Pixel<Self::Color>>,
+            {
+                let pixels: Vec<_> = pixels.into_iter().collect();
+                if self.failing
+                    && pixels
+                        .iter()
+                        .any(|Pixel(point, _)| (9..18).contains(&point.x))
+                {
+                    return Err(());
+                }
+                let _ = self.framebuffer.draw_iter(pixels);
+                Ok(())
+            }
+        }
+
+        let red = Rgb888::new(194, 54, 33);
+        for resilient in [false, true] {
+            let mut console = Console::new(3, 1, Style::default());
+            let (width, height) = console.image_dimensions();
+            let mut display = FailingDisplay {
+                framebuffer: Framebuffer::new(Size::new(width, height), Rgb888::BLUE),
+                failing: true,
+            };
+            console.set_resilient_draw(resilient);
+            console.write_str("\x1b[41m   ").unwrap();
+            assert_eq!(console.draw(&mut display), Err(()));
+
+            let painted = [0, 9, 18].map(|x| display.framebuffer.pixel(x, 0) == red);
+            // The cells after the failing one are only drawn in the resilient mode
+            assert_eq!(painted, [true, false, resilient]);
+
+            display.failing = false;
+            console.draw(&mut display).unwrap();
+            let painted = [0, 9, 18].map(|x| display.framebuffer.pixel(x, 0) == red);
+            assert_eq!(painted, [true, true, true]);
+        }
+    }
+
+    #[test]
+    fn test_cursor_cell() {
+        let mut console = Console::new(4, 2, Style::default());
+        console.write_str("ab\x1b[31mcd\x1b[1;2H").unwrap();
+        assert_eq!(console.cursor_char(), 'b');
+        // A wrap is pending after writing to the last column
+        console.write_str("\x1b[1;4Hd").unwrap();
+        assert_eq!(console.cursor_char(), 'd');
+        assert_eq!(console.cursor_cell().fg(), Color::Named(NamedColor::Red));
+        console.write_str("\n").unwrap();
+        assert_eq!(console.cursor_char(), ' ');
+    }
+
+    #[test]
+    fn test_flush_count() {
+        use crate::test_display::Framebuffer;
+
+        let mut console = Console::new(2, 1, Style::default());
+        let mut display = Framebuffer::new(Size::new(18, 18), Rgb888::BLACK);
+        console.draw(&mut display).unwrap();
+        console.set_flush_count(3);
+        console.write_str("a").unwrap();
+        let mut draws = 0;
+        while console[(0, 0)].is_dirty() {
+            console.draw(&mut display).unwrap();
+            draws += 1;
+        }
+        assert_eq!(draws, 3);
+        assert!(!console[(0, 1)].is_dirty());
+    }
+
+    #[test]
+    fn test_scroll_left_right() {
+        let mut console = Console::new(5, 2, Style::default());
+        console.write_str("abcde\r\nfghij").unwrap();
+
+        console.write_str("\x1b[41m\x1b[2 @").unwrap();
+        let rows: Vec<String> = console.rows_iter().map(row_text).collect();
+        assert_eq!(rows, ["cde  ", "hij  "]);
+        assert_eq!(console[(0, 4)].bg(), Color::Named(NamedColor::Red));
+        assert_eq!(console[(0, 2)].bg(), Color::Named(NamedColor::Black));
+
+        console.write_str("\x1b[ A").unwrap();
+        let rows: Vec<String> = console.rows_iter().map(row_text).collect();
+        assert_eq!(rows, [" cde ", " hij "]);
+        console.write_str("\x1b[9 A").unwrap();
+        let rows: Vec<String> = console.rows_iter().map(row_text).collect();
+        assert_eq!(rows, ["     ", "     "]);
+    }
+
+    #[test]
+    fn test_rows_iter_after_scrolling() {
+        let mut console = Console::new(2, 3, Style::default());
+        console.write_str("a\r\nb\r\nc\r\nd\r\ne").unwrap();
+        let rows: Vec<String> = console.rows_iter().map(row_text).collect();
+        assert_eq!(rows, ["c ", "d ", "e "]);
+    }
+
+    #[test]
+    fn test_margins() {
+        // Margins around columns 3 to 6, "cdef"
+        for (operation, expected) in [
+            ("\x1b[1;4H\x1b[P", "abcef gh"),   // DCH
+            ("\x1b[1;4H\x1b[9P", "abc   gh"),  // DCH past the margin
+            ("\x1b[1;4H\x1b[2@", "abc  dgh"),  // ICH
+            ("\x1b[1;4H\x1b[9X", "abc   gh"),  // ECH
+            ("\x1b[1;5Hx\x1b[9b", "abcdxxgh"), // REP
+            ("\x1b[1;8H\x1b[P", "abcdefg "),   // Outside the margins
+            ("\x1b[1;8H\x1b[ @", "abdef gh"),  // SL
+            ("\x1b[2 A", "ab  cdgh"),          // SR
+        ] {
+            let mut console = Console::new(8, 1, Style::default());
+            console.write_str("abcdefgh\x1b[3;6s").unwrap();
+            assert_eq!(console.get_cursor_position(), (0, 0));
+            console.write_str(operation).unwrap();
+            let row = console.rows_iter().map(row_text).next().unwrap();
+            assert_eq!(row, expected, "{:?}", operation);
+        }
+
+        // Without margins, the whole row is used
+        let mut console = Console::new(8, 1, Style::default());
+        console.write_str("abcdefgh\x1b[s\x1b[1;4H\x1b[2@").unwrap();
+        let row = console.rows_iter().map(row_text).next().unwrap();
+        assert_eq!(row, "abc  def");
+    }
+
+    #[test]
+    fn test_scrolling_region() {
+        let mut console = Console::new(1, 5, Style::default());
+        console.write_str("a\r\nb\r\nc\r\nd\r\ne\x1b[2;4r").unwrap();
+        assert_eq!(console.get_cursor_position(), (0, 0));
+
+        // RI at the top of the region scrolls the region down
+        console.write_str("\x1b[2;1H\x1bM").unwrap();
+        let rows: Vec<String> = console.rows_iter().map(row_text).collect();
+        assert_eq!(rows, ["a", " ", "b", "c", "e"]);
+        assert_eq!(console.get_cursor_position(), (1, 0));
+
+        // Anywhere else it moves up
+        console.write_str("\x1b[3;1H\x1bM").unwrap();
+        assert_eq!(console.get_cursor_position(), (1, 0));
+
+        // LF at the bottom of the region scrolls the region up
+        console.write_str("\x1b[4;1H\n").unwrap();
+        let rows: Vec<String> = console.rows_iter().map(row_text).collect();
+        assert_eq!(rows, ["a", "b", "c", " ", "e"]);
+        assert_eq!(console.get_cursor_position(), (3, 0));
+
+        // RI at the top of the screen scrolls the whole screen without a region
+        console.write_str("\x1b[r\x1bM").unwrap();
+        let rows: Vec<String> = console.rows_iter().map(row_text).collect();
+        assert_eq!(rows, [" ", "a", "b", "c", " "]);
+    }
+
+    #[test]
+    fn test_carriage_return_overwrite() {
+        use crate::test_display::Framebuffer;
+
+        let mut console = Console::new(10, 2, Style::default());
+        let mut display = Framebuffer::new(Size::new(90, 36), Rgb888::BLACK);
+        // Filling the line leaves a wrap pending, which CR cancels
+        console.write_str("[=====...]").unwrap();
+        console.draw(&mut display).unwrap();
+        console.write_str("\r[##").unwrap();
+        let rows: Vec<String> = console.rows_iter().map(row_text).collect();
+        assert_eq!(rows, ["[##===...]", "          "]);
+        assert_eq!(console.get_cursor_position(), (0, 3));
+        assert!(
+            console
+                .rows_iter()
+                .flatten()
+                .all(|cell| !cell.flags().contains(Flags::WRAPLINE))
+        );
+
+        // Only the rewritten cells are drawn again
+        let dirty: Vec<usize> = (0..10)
+            .filter(|&col| console[(0, col)].is_dirty())
+            .collect();
+        assert_eq!(dirty, [0, 1, 2]);
+        console.draw(&mut display).unwrap();
+        assert_eq!(console.dirty_rows_iter().count(), 0);
+    }
+
+    #[test]
+    fn test_inverted() {
+        use crate::test_display::Framebuffer;
+
+        let mut console = Console::new(2, 1, Style::default());
+        let (width, height) = console.image_dimensions();
+        let mut display = Framebuffer::new(Size::new(width, height), Rgb888::RED);
+        console.write_str(" \x1b[7m ").unwrap();
+        console.draw(&mut display).unwrap();
+        let (fg, bg) = (display.pixel(9, 0), display.pixel(0, 0));
+        assert_ne!(fg, bg);
+
+        console.set_inverted(true);
+        console.draw(&mut display).unwrap();
+        assert_eq!((display.pixel(0, 0), display.pixel(9, 0)), (fg, bg));
+
+        console.set_inverted(false);
+        console.draw(&mut display).unwrap();
+        assert_eq!((display.pixel(0, 0), display.pixel(9, 0)), (bg, fg));
+    }
+
+    #[test]
+    fn test_scrollbar() {
+        use crate::test_display::Framebuffer;
+
+        let mut console = Console::new(4, 4, Style::default());
+        let mut display = Framebuffer::new(Size::new(36, 72), Rgb888::BLACK);
+        console.draw(&mut display).unwrap();
+        let red = Rgb888::RED;
+        // Lines 4 to 7 of 16
+        console.draw_scrollbar(&mut display, 4, 16, red).unwrap();
+        let bar = |display: &Framebuffer| {
+            (0..72)
+                .filter(|&y| display.pixel(34, y) == red && display.pixel(35, y) == red)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(bar(&display), (18..36).collect::<Vec<_>>());
+        assert!((0..72).all(|y| display.pixel(33, y) != red));
+        let dirty = |console: &Console<Rgb888, MonoFont>| {
+            (0..4)
+                .filter(|&row| console[(row, 3)].is_dirty())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(dirty(&console), [1]);
+
+        // The bar is removed by the next draw, and not drawn at the bottom of the history
+        console.draw(&mut display).unwrap();
+        console.draw_scrollbar(&mut display, 12, 16, red).unwrap();
+        assert_eq!(bar(&display), []);
+        assert_eq!(dirty(&console), []);
+    }
+
+    #[test]
+    fn test_write_at() {
+        let mut console = Console::new(8, 5, Style::default());
+        console.write_at(3, 5, "\x1b[31mabcd");
+        console.write_at(5, 0, "x");
+        console.write_at(0, 8, "y");
+
+        let rows: Vec<String> = console.rows_iter().map(row_text).collect();
+        assert_eq!(
+            rows,
+            ["        ", "        ", "        ", "     abc", "d       "]
+        );
+        assert_eq!(console[(3, 5)].fg(), Color::Named(NamedColor::Red));
+        assert_eq!(console.get_cursor_position(), (4, 1));
+    }
+
+    #[test]
+    fn test_index() {
+        let mut console = Console::new(3, 2, Style::default());
+        let mut display = NullDisplay;
+        console.write_str("ab").unwrap();
+        console.draw(&mut display).unwrap();
+
+        assert_eq!(console[(0, 1)].c(), 'b');
+        assert_eq!(console[(5, 5)], console.inner.buf.default_cell());
+        assert_eq!(console.inner.buf.get(1, 3), None);
+        assert_eq!(console.inner.buf.get(0, 0).map(Cell::c), Some('a'));
+
+        console[(1, 2)].c = 'x';
+        console[(9, 9)].c = 'y';
+        assert_eq!(console[(1, 2)].c(), 'x');
+        assert!(console[(1, 2)].is_dirty());
+        assert!(!console[(0, 0)].is_dirty());
+        assert_eq!(console[(9, 9)].c(), ' ');
+    }
+
+    #[test]
+    fn test_batch() {
+        let mut console = Console::new(4, 3, Style::default());
+        let mut display = CountingDisplay::default();
+        console.draw(&mut display).unwrap();
+
+        let mut display = CountingDisplay::default();
+        console.batch(|console| {
+            console.write_str("a").unwrap();
+            console.begin_batch();
+            console.write_str("\x08b").unwrap();
+            console.end_batch();
+            console.draw(&mut display).unwrap();
+            console
+                .draw_direct(&mut display, |_, _, _| {
+                    Some([Pixel(Point::zero(), Rgb888::RED)])
+                })
+                .unwrap();
+        });
+        assert_eq!(display.pixels, 0);
+        assert!(!console.is_batching());
+
+        console.draw(&mut display).unwrap();
+        assert_eq!(display.pixels, 9 * 18);
+    }
+
+    #[test]
+    fn test_with_buffer() {
+        let mut buf = CellBuffer::new(3, 2);
+        let cell = Cell::new(
+            'x',
+            Color::Named(NamedColor::Green),
+            Color::Named(NamedColor::Blue),
+            Flags::BOLD,
+        );
+        buf.write(1, 2, cell);
+
+        let mut console = Console::with_buffer(buf, Style::default());
+        assert_eq!((console.rows(), console.columns()), (2, 3));
+        let rows: Vec<String> = console.rows_iter().map(row_text).collect();
+        assert_eq!(rows, ["   ", "  x"]);
+        assert_eq!(console.rows_iter().nth(1).unwrap()[2], cell);
+
+        console.draw(&mut NullDisplay).unwrap();
+        assert!(!console.rows_iter().nth(1).unwrap()[2].is_dirty());
+    }
+
+    #[test]
+    fn test_underline_color() {
+        let mut console = Console::new(10, 3, Style::default());
+        console.write_str("\x1b[58;5;9m\x1b[4mu\x1b[59mv").unwrap();
+
+        let cell = console.inner.buf.read(0, 0);
+        assert!(cell.flags.contains(Flags::UNDERLINE));
+        assert_eq!(cell.underline_color(), Some(Color::Indexed(9)));
+        assert_eq!(console.inner.buf.read(0, 1).underline_color(), None);
+    }
+
+    #[test]
+    fn test_write_bytes_as_ascii() {
+        let mut console = Console::new(8, 2, Style::default());
+        console.write_bytes_as_ascii(b"a\x1b[1mb\n");
+        console.cursor_next_line(1);
+        console.write_bytes_printable_only(b"a\x1b[1mb\n");
+
+        let rows: Vec<String> = console.rows_iter().map(row_text).collect();
+        assert_eq!(rows, ["a.[1mb. ", "a[1mb   "]);
+        assert_eq!(console.inner.temp, Cell::default());
+    }
+
+    #[test]
+    fn test_zero_width_characters() {
+        let mut console = Console::new(10, 2, Style::default());
+        // A family emoji, a heart with an emoji variation selector, and a combining acute accent
+        console
+            .write_str("a👨\u{200d}👩\u{200d}👧b❤\u{fe0f}e\u{301}|")
+            .unwrap();
+        assert_eq!(console.get_cursor_position(), (0, 6));
+        assert_eq!(row_text(console.rows_iter().next().unwrap()), "a👨b❤e|    ");
+
+        // A joiner doesn't join characters across cursor movement
+        console.write_str("\r\nx\u{200d}\x1b[Cy").unwrap();
+        assert_eq!(row_text(console.rows_iter().nth(1).unwrap()), "x y       ");
+    }
+
+    #[test]
+    fn test_write_as_escape() {
+        let mut console = Console::new(16, 2, Style::default());
+        console.write_bytes_as_escape_sequence(b"\x1b[1mA\n");
+        console.cursor_next_line(1);
+        for c in ['\x07', 'é', ' ', '\u{85}'] {
+            console.write_unicode_codepoint_as_escape(c);
+        }
+
+        let rows: Vec<String> = console.rows_iter().map(row_text).collect();
+        assert_eq!(rows, ["\\x1b[1mA\\x0a    ", "U+0007é U+0085  "]);
+        assert_eq!(console.inner.temp, Cell::default());
+    }
+
+    #[test]
+    fn test_overlong_osc_is_abandoned() {
+        use core::sync::atomic::{AtomicUsize, Ordering};
+        static OVERFLOWS: AtomicUsize = AtomicUsize::new(0);
+
+        let mut console = Console::new(10, 3, Style::default());
+        console.set_max_string_length(Some(8));
+        console.set_string_overflow_callback(Some(|kind| {
+            assert_eq!(kind, ControlString::Osc);
+            OVERFLOWS.fetch_add(1, Ordering::Relaxed);
+        }));
+        console.write_str("\x1b]0;title\x07").unwrap();
+        assert_eq!(console.title(), Some("title"));
+
+        console.write_str("\x1b]0;runaway title\r\nhello").unwrap();
+        assert_eq!(OVERFLOWS.load(Ordering::Relaxed), 1);
+        assert_eq!(console.title(), Some("title"));
+        let rows: Vec<String> = console.rows_iter().map(row_text).collect();
+        assert_eq!(rows[1], "hello     ");
+    }
+
+    #[test]
+    fn test_ansi_sys_save_and_restore_cursor() {
+        let mut console = Console::new(10, 3, Style::default());
+        console
+            .write_str("\x1b[2;3H\x1b[31m\x1b[s\x1b[3;1H")
+            .unwrap();
+        // Left and right margins rather than a save, which don't change the saved cursor
+        console.write_str("\x1b[2;5s\x1b[0m\x1b[H").unwrap();
+        console.write_str("\x1b[u").unwrap();
+        assert_eq!(console.get_cursor_position(), (1, 2));
+        assert_eq!(console.inner.temp.fg, Color::Named(NamedColor::Red));
+    }
+
+    #[test]
+    fn test_save_and_restore_state() {
+        let mut console = Console::new(10, 3, Style::default());
+        console.write_str("\x1b[2;3H\x1b[1;31m\x1b7").unwrap();
+        let state = console.save_state();
+        console.write_str("\x1b[0m\x1b[?7l\x1b[3;5H").unwrap();
+
+        console.write_str("\x1b8").unwrap();
+        assert_eq!(console.get_cursor_position(), (1, 2));
+        assert_eq!(console.inner.temp.fg, Color::Named(NamedColor::Red));
+        assert!(console.inner.temp.flags.contains(Flags::BOLD));
+        assert!(console.inner.auto_wrap);
+
+        console.write_str("\x1b[0m\x1b[H").unwrap();
+        console.restore_state(state);
+        assert_eq!(console.get_cursor_position(), (1, 2));
+        assert_eq!(console.inner.temp.fg, Color::Named(NamedColor::Red));
+
+        // The scrolling region and margins are saved too
+        console
+            .write_str("\x1b[2;3r\x1b[2;5s\x1b7\x1b[r\x1b[1;10s")
+            .unwrap();
+        assert_eq!(
+            (console.inner.scrolling_region, console.inner.margins),
+            (None, None)
+        );
+        console.write_str("\x1b8").unwrap();
+        assert_eq!(console.inner.scrolling_region, Some((1, 2)));
+        assert_eq!(console.inner.margins, Some((1, 4)));
+    }
+
+    #[test]
+    fn test_write_plain() {
+        let text = "A plain log line that is long enough to wrap, ünïcode included";
+        let mut plain = Console::new(10, 3, Style::default());
+        plain.write_plain(text);
+        let mut parsed = Console::new(10, 3, Style::default());
+        parsed.write_str(text).unwrap();
+
+        assert!(plain.rows_iter().eq(parsed.rows_iter()));
+        assert_eq!(plain.get_cursor_position(), parsed.get_cursor_position());
+    }
+
+    #[test]
+    fn test_write_ansi_strip() {
+        let mut console = Console::new(10, 2, Style::default());
+        console.write_str("\x1b[1m").unwrap();
+        console.write_ansi_strip("\x1b[31mred\x1b[0m\r\n\x1b]0;title\x07\x1b[2Jok");
+
+        let row = console.rows_iter().next().unwrap();
+        assert_eq!(row_text(row), "redok     ");
+        assert!(row[..5].iter().all(|cell| cell.flags() == Flags::BOLD));
+        assert_eq!(row[0].fg(), Cell::default().fg());
+        assert_eq!(console.title(), None);
+    }
+
+    #[test]
+    #[should_panic]
+    #[cfg(debug_assertions)]
+    fn test_write_plain_control() {
+        let mut console = Console::new(10, 3, Style::default());
+        console.write_plain("a\nb");
+    }
+
+    #[test]
+    fn test_alternate_screen() {
+        let mut console = Console::new(10, 3, Style::default());
+        console.write_str("primary\r\nab").unwrap();
+        assert!(!console.is_alternate_screen());
+
+        console.write_str("\x1b[?1049h").unwrap();
+        assert!(console.is_alternate_screen());
+        assert_eq!(console.get_cursor_position(), (1, 2));
+        console.write_str("\x1b[Halt").unwrap();
+        assert_eq!(row_text(console.rows_iter().next().unwrap()), "alt       ");
+
+        console.write_str("\x1b[?1049l").unwrap();
+        assert!(!console.is_alternate_screen());
+        assert_eq!(console.get_cursor_position(), (1, 2));
+        assert_eq!(row_text(console.rows_iter().next().unwrap()), "primary   ");
+        assert_eq!(console.dirty_rows_iter().count(), 3);
+    }
+
+    #[test]
+    fn test_apply_sgr() {
+        let mut console = Console::new(4, 1, Style::default());
+        console.apply_sgr(&[1, 31, 4]);
+        assert_eq!(console.inner.temp.flags, Flags::BOLD | Flags::UNDERLINE);
+        assert_eq!(console.inner.temp.fg, Color::Named(NamedColor::Red));
+
+        console.apply_sgr(&[48, 5, 196, 22]);
+        assert_eq!(console.inner.temp.flags, Flags::UNDERLINE);
+        assert_eq!(console.inner.temp.bg, Color::Indexed(196));
+
+        console.apply_sgr(&[]);
+        assert_eq!(console.inner.temp, Cell::default());
+    }
+
+    #[test]
+    fn test_reverse_wrap() {
+        let mut console = Console::new(4, 3, Style::default());
+        console.write_str("abcdef\x08\x08\x08").unwrap();
+        assert_eq!(console.get_cursor_position(), (1, 0));
+
+        console.write_str("\x1b[?45h\x08").unwrap();
+        assert_eq!(console.get_cursor_position(), (0, 3));
+        console.write_str("\x1b[H\x08").unwrap();
+        assert_eq!(console.get_cursor_position(), (0, 0));
+
+        console.write_str("\x1b[?45l\x1b[2;1H\x08").unwrap();
+        assert_eq!(console.get_cursor_position(), (1, 0));
+    }
+
+    #[test]
+    fn test_measure_text_width() {
+        let mut console = Console::new(40, 2, Style::default());
+        console.set_tab_width(4);
+        let text = "a\tbcd\t\x1b[31me\x1b[0m\tf";
+        assert_eq!(console.measure_text_width(text), 13);
+        console.write_str(text).unwrap();
+        assert_eq!(console.get_cursor_position(), (0, 13));
+    }
+
+    #[test]
+    fn test_tab_width() {
+        let mut console = Console::new(20, 3, Style::default());
+        console.set_tab_width(4);
+        console.write_str("\t").unwrap();
+        assert_eq!(console.get_cursor_position(), (0, 4));
+        console.write_str("ab\t").unwrap();
+        assert_eq!(console.get_cursor_position(), (0, 8));
+        // CHT
+        console.write_str("\x1b[2I").unwrap();
+        assert_eq!(console.get_cursor_position(), (0, 16));
+        // CBT
+        console.write_str("a\x1b[Z").unwrap();
+        assert_eq!(console.get_cursor_position(), (0, 16));
+        console.write_str("\x1b[3Z").unwrap();
+        assert_eq!(console.get_cursor_position(), (0, 4));
+        // CHT stops at the last column
+        console.write_str("\x1b[9I").unwrap();
+        assert_eq!(console.get_cursor_position(), (0, 19));
+    }
+
+    #[test]
+    fn test_forward_tabs_keep_text() {
+        let mut console = Console::new(12, 1, Style::default());
+        console.set_tab_width(4);
+        console.write_str("abcdefghijkl\r\x1b[2IX").unwrap();
+        let rows: Vec<String> = console.rows_iter().map(row_text).collect();
+        assert_eq!(rows, ["abcdefghXjkl"]);
+    }
+
+    #[test]
+    fn test_draw_direct() {
+        let mut console = Console::new(4, 2, Style::default());
+        console.write_str("ab").unwrap();
+        console.draw(&mut NullDisplay).unwrap();
+        console.write_str("\r\nc").unwrap();
+
+        let mut display = CountingDisplay::default();
+        let mut drawn = Vec::new();
+        console
+            .draw_direct(&mut display, |row, col, cell| {
+                drawn.push((row, col, cell.c()));
+                (cell.c() != ' ').then(|| [Pixel(Point::new(col as i32, row as i32), Rgb888::RED)])
+            })
+            .unwrap();
+
+        assert_eq!(drawn, [(1, 0, 'c')]);
+        assert_eq!(display.pixels, 1);
+        assert_eq!(console.dirty_rows_iter().count(), 0);
+    }
+
+    #[test]
+    fn test_goto_clamps_to_last_cell() {
+        let mut console = Console::new(10, 3, Style::default());
+        console.inner.goto(1000, 1000);
+        assert_eq!(console.get_cursor_position(), (2, 9));
+
+        console.write_str("\x1b[1;1H\x1b[1000;1000Hab").unwrap();
+        // The first character is written in the last cell, and the second wraps, scrolling the console
+        let rows = console.rows_iter().map(row_text).collect::<Vec<_>>();
+        assert_eq!(rows, ["          ", "         a", "b         "]);
+        assert_eq!(console.get_cursor_position(), (2, 1));
+    }
+
+    #[test]
+    fn test_resize() {
+        let mut console = Console::new(4, 3, Style::default());
+        console.write_str("abcd\r\nefgh\r\nijkl").unwrap();
+        console.draw(&mut NullDisplay).unwrap();
+
+        console.resize(2, 2);
+        let rows = console.rows_iter().map(row_text).collect::<Vec<_>>();
+        assert_eq!(rows, ["ab", "ef"]);
+        assert_eq!(console.get_cursor_position(), (1, 1));
+        assert_eq!(console.dirty_rows_iter().count(), 2);
+
+        console.resize(3, 3);
+        let rows = console.rows_iter().map(row_text).collect::<Vec<_>>();
+        assert_eq!(rows, ["ab ", "ef ", "   "]);
+    }
+
+    /// Apply each erase operation to a full console with a blue background set, and return the erased cells
+    fn erased_cells(mode: EraseMode) -> Vec<Cell> {
+        let operations = [
+            "\x1b[2;2H\x1b[X",  // ECH
+            "\x1b[2;2H\x1b[P",  // DCH
+            "\x1b[2;2H\x1b[K",  // EL
+            "\x1b[2;2H\x1b[J",  // ED
+            "\x1b[2;2H\x1b[2J", // ED all
+            "\x1b[2;2H\t",      // HT
+            "\x1b[3;1H\n",      // Scrolling
+        ];
+        operations
+            .iter()
+            .map(|operation| {
+                let mut console = Console::new(4, 3, Style::default());
+                console.set_erase_mode(mode);
+                console.write_str("abcd\r\nefgh\r\nijkl").unwrap();
+                console.write_str("\x1b[44;4m").unwrap();
+                console.write_str(operation).unwrap();
+                console
+                    .rows_iter()
+                    .flatten()
+                    .copied()
+                    .find(|cell| cell.c() == ' ')
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_erase_to_current_background() {
+        let blue_bg = Cell {
+            bg: Color::Named(NamedColor::Blue),
+            ..Cell::default()
+        };
+        assert!(
+            erased_cells(EraseMode::CurrentBackground)
+                .iter()
+                .all(|&cell| cell == blue_bg)
+        );
+    }
+
+    #[test]
+    fn test_erase_to_default_cell() {
+        assert!(
+            erased_cells(EraseMode::DefaultCell)
+                .iter()
+                .all(|&cell| cell == Cell::default())
+        );
+    }
+
+    #[test]
+    fn test_with_default_cell() {
+        let blue = Color::Named(NamedColor::Blue);
+        let default_cell = Cell::new('.', blue, blue, Flags::empty());
+        let mut console = Console::with_default_cell(3, 2, default_cell, Style::default());
+        assert!(
+            console
+                .rows_iter()
+                .flatten()
+                .all(|&cell| cell == default_cell)
+        );
+
+        console.write_str("\x1b[1;31ma\x1b[0mb").unwrap();
+        let row = console.rows_iter().next().unwrap();
+        assert_eq!(row[0].fg(), Color::Named(NamedColor::Red));
+        assert_eq!((row[1].c(), row[1].fg(), row[1].bg()), ('b', blue, blue));
+        assert_eq!(row[2], default_cell);
+
+        console.write_str("\x1b[42m\x1b[2J").unwrap();
+        let erased = console.rows_iter().next().unwrap()[0];
+        assert_eq!((erased.c(), erased.fg()), ('.', blue));
+        assert_eq!(erased.bg(), Color::Named(NamedColor::Green));
+    }
+
+    #[test]
+    fn test_default_colors_of_default_cell() {
+        let green = Color::Named(NamedColor::Green);
+        let default_cell = Cell::new(' ', green, Color::Named(NamedColor::Blue), Flags::empty());
+        let mut console = Console::with_default_cell(4, 2, default_cell, Style::default());
+        console.write_str("\x1b[31;41m\x1b[39;49mx").unwrap();
+        let x = console.rows_iter().next().unwrap()[0];
+        assert_eq!((x.fg(), x.bg()), (green, default_cell.bg()));
+        // DECRC without DECSC restores the attributes of the default cell
+        console.write_str("\x1b[31m\x1b8").unwrap();
+        assert_eq!(console.inner.temp, default_cell);
+
+        // Colors of the default cell are left out of the export
+        let mut console = Console::with_default_cell(4, 1, default_cell, Style::default());
+        console.write_str("\x1b[31ma\x1b[39mb").unwrap();
+        assert!(console.to_ansi().contains("\x1b[0mb"));
+    }
+
+    #[test]
+    fn test_try_new() {
+        assert!(Console::try_new(80, 24, Style::default()).is_ok());
+        assert_eq!(
+            Console::try_new(usize::MAX / 2, 3, Style::default()).err(),
+            Some(ConsoleError::TooLarge {
+                width: usize::MAX / 2,
+                height: 3
+            })
+        );
+        assert!(Console::try_new(400, 200, Style::default()).is_ok());
+        assert_eq!(
+            Console::try_new_with_limit(1000, 1000, Some(1 << 16), Style::default()).err(),
+            Some(ConsoleError::TooLarge {
+                width: 1000,
+                height: 1000
+            })
+        );
+        assert_eq!(
+            Console::try_new(0, 24, Style::default()).err(),
+            Some(ConsoleError::ZeroDimension)
+        );
+    }
+
+    #[test]
+    fn test_console_errors() {
+        use alloc::string::ToString;
+
+        let mut console = Console::try_new_with_limit(4, 2, Some(100), Style::default()).unwrap();
+        assert_eq!(
+            console.try_write_at(2, 0, "a"),
+            Err(ConsoleError::OutOfBounds { row: 2, col: 0 })
+        );
+        assert_eq!(console.try_write_at(1, 3, "a"), Ok(()));
+        let cell = Cell::new(
+            'b',
+            Color::Named(NamedColor::Red),
+            Color::Named(NamedColor::Black),
+            Flags::empty(),
+        );
+        assert_eq!(
+            console.try_put_cell(0, 4, cell),
+            Err(ConsoleError::OutOfBounds { row: 0, col: 4 })
+        );
+        assert_eq!(console.try_put_cell(0, 0, cell), Ok(()));
+        assert_eq!(console[(0, 0)], cell);
+        assert_eq!(console[(1, 3)].c(), 'a');
+
+        assert_eq!(console.try_resize(0, 2), Err(ConsoleError::ZeroDimension));
+        assert_eq!(
+            console.try_resize(20, 6),
+            Err(ConsoleError::TooLarge {
+                width: 20,
+                height: 6
+            })
+        );
+        assert_eq!(
+            console.try_resize(usize::MAX, 2),
+            Err(ConsoleError::TooLarge {
+                width: usize::MAX,
+                height: 2
+            })
+        );
+        assert_eq!((console.columns(), console.rows()), (4, 2));
+        assert_eq!(console.try_resize(5, 3), Ok(()));
+        assert_eq!((console.columns(), console.rows()), (5, 3));
+
+        console.write_str("\x1b[6n").unwrap();
+        let mut buf = [0; 4];
+        assert_eq!(
+            console.try_read_reports(&mut buf),
+            Err(ConsoleError::ReportOverflow { len: 6 })
+        );
+        let mut buf = [0; 8];
+        assert_eq!(console.try_read_reports(&mut buf), Ok(6));
+        assert_eq!(&buf[..6], b"\x1b[2;5R");
+        assert_eq!(console.try_read_reports(&mut buf), Ok(0));
+
+        assert_eq!(
+            ConsoleError::OutOfBounds { row: 2, col: 0 }.to_string(),
+            "row 2, column 0 is outside the console"
+        );
+        assert_eq!(
+            ConsoleError::TooLarge {
+                width: 20,
+                height: 6
+            }
+            .to_string(),
+            "a console of 20 by 6 cells is too large"
+        );
+    }
+
+    #[test]
+    fn test_flush_reports_to() {
+        let mut console = Console::new(10, 3, Style::default());
+        console.write_str("\x1b[2;3H\x1b[6n\x1b[5n").unwrap();
+
+        let mut reports = String::new();
+        console.flush_reports_to(&mut reports).unwrap();
+        assert_eq!(reports, "\x1b[2;3R\x1b[0n");
+        assert_eq!(console.pop_report(), None);
+    }
+
+    /// The cursor position reported for DSR 6
+    fn report_cursor(console: &mut Console<'static, Rgb888, MonoFont<'static>>) -> String {
+        console.write_str("\x1b[6n").unwrap();
+        let mut report = String::new();
+        console.flush_reports_to(&mut report).unwrap();
+        report
+    }
+
+    #[test]
+    fn test_cursor_position_report() {
+        let mut console = Console::new(4, 2, Style::default());
+        assert_eq!(report_cursor(&mut console), "\x1b[1;1R");
+        console.write_str("ab").unwrap();
+        assert_eq!(report_cursor(&mut console), "\x1b[1;3R");
+
+        // A wrap is pending at the right margin
+        console.write_str("cd").unwrap();
+        assert_eq!(console.get_cursor_position(), (0, 4));
+        assert_eq!(report_cursor(&mut console), "\x1b[1;4R");
+        console.write_str("e").unwrap();
+        assert_eq!(report_cursor(&mut console), "\x1b[2;2R");
+
+        // After scrolling, the cursor stays on the last row
+        console.write_str("fgh\r\nij").unwrap();
+        assert_eq!(row_text(console.rows_iter().next().unwrap()), "efgh");
+        assert_eq!(report_cursor(&mut console), "\x1b[2;3R");
+        console.write_str("kl").unwrap();
+        assert_eq!(report_cursor(&mut console), "\x1b[2;4R");
+
+        // Clearing below the cursor doesn't move it, and clearing the whole screen moves it home
+        console.write_str("\x1b[J").unwrap();
+        assert_eq!(report_cursor(&mut console), "\x1b[2;4R");
+        console.write_str("\x1b[2J").unwrap();
+        assert_eq!(report_cursor(&mut console), "\x1b[1;1R");
+    }
+
+    #[test]
+    fn test_poll_event() {
+        let mut console = Console::new(10, 3, Style::default());
+        // Nothing is queued until the queue is turned on
+        console.write_str("\x07").unwrap();
+        assert_eq!(console.poll_event(), None);
+        console.set_event_queue(true);
+        console
+            .write_str("\x07\x1b]2;hi\x07\x1b[5n\x1b[8;24;80t\x1b[200~x\x1b[201~")
+            .unwrap();
+
+        // Reports are read on their own
+        assert_eq!(console.pop_report(), Some(b'\x1b'));
+        let events = (0..)
+            .map_while(|_| console.poll_event())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            [
+                TerminalEvent::Bell,
+                TerminalEvent::TitleChange(String::from("hi")),
+                TerminalEvent::ResizeRequest(24, 80),
+                TerminalEvent::PasteStart,
+                TerminalEvent::PasteEnd,
+            ]
+        );
+        assert_eq!(console.pop_report(), Some(b'['));
+        assert_eq!(row_text(console.rows_iter().next().unwrap()), "x         ");
+
+        // A burst of events drops the oldest ones, but no reports
+        for _ in 0..100 {
+            console.write_str("\x07").unwrap();
+        }
+        console.write_str("\x1b]2;last\x07").unwrap();
+        let events = (0..)
+            .map_while(|_| console.poll_event())
+            .collect::<Vec<_>>();
+        assert_eq!(events.len(), 32);
+        assert_eq!(events[31], TerminalEvent::TitleChange(String::from("last")));
+        let mut reports = String::new();
+        console.flush_reports_to(&mut reports).unwrap();
+        assert_eq!(reports, "0n");
+    }
+
+    #[test]
+    fn test_bell() {
+        use core::sync::atomic::{AtomicUsize, Ordering};
+        static RINGS: AtomicUsize = AtomicUsize::new(0);
+
+        let mut console = Console::new(10, 3, Style::default());
+        console.set_bell_callback(|| {
+            RINGS.fetch_add(1, Ordering::Relaxed);
+        });
+        console.write_str("a\x07b\x07").unwrap();
+        // BEL terminating an OSC is not a bell
+        console.write_str("\x1b]0;title\x07").unwrap();
+
+        assert_eq!(console.bell_count(), 2);
+        assert_eq!(RINGS.load(Ordering::Relaxed), 2);
+        assert_eq!(row_text(console.rows_iter().next().unwrap()), "ab        ");
+    }
+
+    #[test]
+    fn test_dcs_callback() {
+        extern crate std;
+        use std::sync::Mutex;
+        static RECEIVED: Mutex<Vec<DcsString>> = Mutex::new(Vec::new());
+
+        let mut console = Console::new(10, 3, Style::default());
+        console.set_dcs_callback(Some(|dcs| RECEIVED.lock().unwrap().push(dcs.clone())));
+        console.write_str("\x1bP1;2ppayload\x1b\\after").unwrap();
+
+        let received = RECEIVED.lock().unwrap();
+        assert_eq!(
+            *received,
+            [DcsString {
+                params: vec![1, 2],
+                intermediates: vec![],
+                action: 'p',
+                payload: b"payload".to_vec(),
+            }]
+        );
+        assert_eq!(row_text(console.rows_iter().next().unwrap()), "after     ");
+    }
+
+    #[test]
+    fn test_inline_image_callback() {
+        extern crate std;
+        use crate::ImageProtocol;
+        use std::sync::Mutex;
+        static RECEIVED: Mutex<Vec<InlineImage>> = Mutex::new(Vec::new());
+
+        let mut console = Console::new(10, 3, Style::default());
+        console
+            .set_inline_image_callback(Some(|image| RECEIVED.lock().unwrap().push(image.clone())));
+        console
+            .write_str("\x1b]1337;File=name=YS5wbmc=;width=4;inline=1:iVBORw0K\x07a")
+            .unwrap();
+        console.write_str("\x1b_Ga=T,f=100;AAAA\x1b\\b").unwrap();
+
+        let received = RECEIVED.lock().unwrap();
+        let owned = |params: &[(&str, &str)]| {
+            params
+                .iter()
+                .map(|&(k, v)| (String::from(k), String::from(v)))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            *received,
+            [
+                InlineImage {
+                    protocol: ImageProtocol::Iterm,
+                    params: owned(&[("name", "YS5wbmc="), ("width", "4"), ("inline", "1")]),
+                    payload: b"iVBORw0K".to_vec(),
+                },
+                InlineImage {
+                    protocol: ImageProtocol::Kitty,
+                    params: owned(&[("a", "T"), ("f", "100")]),
+                    payload: b"AAAA".to_vec(),
+                },
+            ]
+        );
+        assert_eq!(row_text(console.rows_iter().next().unwrap()), "ab        ");
+    }
+
+    #[test]
+    fn test_title_stack() {
+        let mut console = Console::new(10, 3, Style::default());
+        console.write_str("\x1b]2;first\x07").unwrap();
+        assert_eq!(console.title(), Some("first"));
+
+        console.write_str("\x1b[22t\x1b]0;second\x07").unwrap();
+        assert_eq!(console.title(), Some("second"));
+
+        console.write_str("\x1b[23t").unwrap();
+        assert_eq!(console.title(), Some("first"));
+    }
+
+    #[test]
+    fn test_attributes_persist_across_newline_by_default() {
+        let mut console = Console::new(10, 3, Style::default());
+        console.write_str("\x1b[31mred\nstill").unwrap();
+
+        assert_eq!(
+            console.inner.buf.read(1, 0).fg,
+            Color::Named(NamedColor::Red)
+        );
     }
 }
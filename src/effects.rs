@@ -0,0 +1,300 @@
+use alloc::vec::Vec;
+use core::cmp::min;
+
+use core::fmt::Write;
+
+use crate::Console;
+use crate::ansi::{Handler, LineClearMode};
+use crate::cell::Flags;
+use crate::color::{Color, NamedColor};
+use crate::style::{DrawCell, Style};
+
+/// How long [`Console::write_terminal_bell_visual`] inverts the screen for, in milliseconds
+const VISUAL_BELL_DURATION_MS: u32 = 100;
+
+/// How long the cursor stays in each blink phase by default, in milliseconds
+pub(crate) const DEFAULT_CURSOR_BLINK_INTERVAL_MS: u32 = 500;
+
+/// The colors that [`Console::write_rainbow_row`] cycles through
+const RAINBOW: [NamedColor; 6] = [
+    NamedColor::BrightRed,
+    NamedColor::BrightYellow,
+    NamedColor::BrightGreen,
+    NamedColor::BrightCyan,
+    NamedColor::BrightBlue,
+    NamedColor::BrightMagenta,
+];
+
+impl<'a, C, F> Console<'a, C, F>
+where
+    Style<'a, C, F>: DrawCell<C>,
+{
+    /// Write `text` with a foreground color that fades from `fg_start` at the first character to `fg_end` at the last.
+    ///
+    /// Colors are mixed with [`Color::blend`]. `text` is written without interpreting control sequences, and the other attributes of the text are unchanged.
+    pub fn write_color_gradient_text(&mut self, text: &str, fg_start: Color, fg_end: Color) {
+        let fg = self.inner.temp.fg;
+        let last = text.chars().count().saturating_sub(1).max(1);
+        for (i, c) in text.chars().enumerate() {
+            self.inner.temp.fg = fg_start.blend(fg_end, (i * 255 / last) as u8);
+            self.inner.input(c);
+        }
+        self.inner.temp.fg = fg;
+    }
+
+    /// Write `text` at the start of `row`, with each character in the next of the bright rainbow colors, and clear the rest of the row.
+    ///
+    /// Text beyond the end of the row is not written. The cursor is left at the end of the text.
+    pub fn write_rainbow_row(&mut self, row: usize, text: &str) {
+        let fg = self.inner.temp.fg;
+        self.inner.goto(min(row, self.rows() - 1), 0);
+        for (c, color) in text
+            .chars()
+            .take(self.columns())
+            .zip(RAINBOW.iter().cycle())
+        {
+            self.inner.temp.fg = Color::Named(*color);
+            self.inner.input(c);
+        }
+        self.inner.temp.fg = fg;
+        self.inner.clear_line(LineClearMode::Right);
+    }
+
+    /// Flash the screen by inverting every cell, as a visual alternative to the bell.
+    ///
+    /// The screen is restored by [`Console::restore_visual_bell`], which [`Console::tick`] calls once 100ms have passed. Cells take back the inverse attribute they had before the flash, even if they have been written to since. Flashing again while the screen is inverted only restarts the timer.
+    pub fn write_terminal_bell_visual(&mut self) {
+        if let Some((_, remaining)) = &mut self.visual_bell {
+            *remaining = VISUAL_BELL_DURATION_MS;
+            return;
+        }
+        let mut saved = Vec::with_capacity(self.rows() * self.columns());
+        let flush_count = self.inner.buf.flush_count();
+        for row in 0..self.rows() {
+            for cell in self.inner.buf.row_mut(row) {
+                saved.push(cell.flags.contains(Flags::INVERSE));
+                cell.flags.toggle(Flags::INVERSE);
+                cell.to_flush = cell.to_flush.max(flush_count);
+            }
+        }
+        self.visual_bell = Some((saved, VISUAL_BELL_DURATION_MS));
+    }
+
+    /// Undo the inversion of [`Console::write_terminal_bell_visual`]. Does nothing if the screen isn't inverted.
+    pub fn restore_visual_bell(&mut self) {
+        let Some((saved, _)) = self.visual_bell.take() else {
+            return;
+        };
+        let mut saved = saved.into_iter();
+        let flush_count = self.inner.buf.flush_count();
+        for row in 0..self.rows() {
+            for cell in self.inner.buf.row_mut(row) {
+                let inverse = saved.next().unwrap_or(false);
+                cell.flags.set(Flags::INVERSE, inverse);
+                cell.to_flush = cell.to_flush.max(flush_count);
+            }
+        }
+    }
+
+    /// Advance the console's timers by `elapsed_ms` milliseconds, e.g. from a timer interrupt or the main loop.
+    ///
+    /// This restores the screen once a [visual bell](Console::write_terminal_bell_visual) is over.
+    pub fn tick(&mut self, elapsed_ms: u32) {
+        if let Some((_, remaining)) = &mut self.visual_bell {
+            *remaining = remaining.saturating_sub(elapsed_ms);
+            if *remaining == 0 {
+                self.restore_visual_bell();
+            }
+        }
+    }
+
+    /// Advance the cursor blink timer by `elapsed_ms` milliseconds, independently of [`Console::tick`] and [`Console::draw`].
+    ///
+    /// The cursor changes between its visible and hidden phase every [interval](Console::set_cursor_blink_interval), and the cell under it is drawn again on the next call to [`Console::draw`] when it does, so that a cursor drawn over it can be redrawn. Steady cursors, as set with DECSCUSR (`CSI Ps SP q`) or `CSI ? 12 l`, stay in the visible phase. See [`Console::cursor_blink_visible`].
+    pub fn cursor_tick(&mut self, elapsed_ms: u32) {
+        if !self.inner.cursor_blinking {
+            self.cursor_blink_elapsed_ms = 0;
+            self.set_cursor_phase(true);
+            return;
+        }
+        let elapsed = self.cursor_blink_elapsed_ms.saturating_add(elapsed_ms);
+        let interval = self.cursor_blink_interval_ms;
+        self.cursor_blink_elapsed_ms = elapsed % interval;
+        if (elapsed / interval) % 2 == 1 {
+            self.set_cursor_phase(!self.cursor_phase_visible);
+        }
+    }
+
+    /// Whether the cursor is in the visible phase of its blink, as advanced by [`Console::cursor_tick`]. Always `true` for steady cursors.
+    pub fn cursor_blink_visible(&self) -> bool {
+        !self.inner.cursor_blinking || self.cursor_phase_visible
+    }
+
+    /// Set how long the cursor stays in each phase of its blink, in milliseconds. The default is 500ms, and 0 is treated as 1.
+    pub fn set_cursor_blink_interval(&mut self, interval_ms: u32) {
+        self.cursor_blink_interval_ms = interval_ms.max(1);
+    }
+
+    /// Choose whether the cursor blinks, as DECSCUSR (`CSI Ps SP q`) and `CSI ? 12 h` do. Cursors blink by default.
+    pub fn set_cursor_blinking(&mut self, blinking: bool) {
+        self.inner.cursor_blinking = blinking;
+    }
+
+    /// Change the cursor blink phase, and draw the cell under the cursor again if it changes
+    fn set_cursor_phase(&mut self, visible: bool) {
+        if self.cursor_phase_visible != visible {
+            self.cursor_phase_visible = visible;
+            let (row, col) = self.get_cursor_position();
+            self.mark_cell_dirty(row, min(col, self.columns() - 1));
+        }
+    }
+
+    /// Write `text` as an OSC 8 hyperlink to `url`, underlined and in the colors `fg` and `bg`.
+    ///
+    /// The text attributes are restored afterwards.
+    pub fn write_ansi_hyperlink_clickable(&mut self, url: &str, text: &str, fg: Color, bg: Color) {
+        let temp = self.inner.temp;
+        let _ = write!(self, "\x1b]8;;{}\x1b\\", url);
+        self.inner.temp.fg = fg;
+        self.inner.temp.bg = bg;
+        self.inner.temp.flags.insert(Flags::UNDERLINE);
+        let _ = self.write_str(text);
+        let _ = self.write_str("\x1b]8;;\x1b\\");
+        self.inner.temp = temp;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Rgb888;
+    use crate::test_display::row_text;
+    use crate::{Cell, NamedColor};
+    use embedded_graphics::mono_font::MonoFont;
+
+    #[test]
+    fn test_visual_bell() {
+        let mut console = Console::new(3, 2, Style::default());
+        console.write_str("a\x1b[7mb").unwrap();
+        fn inverse(console: &Console<'static, Rgb888, MonoFont<'static>>) -> Vec<bool> {
+            console
+                .rows_iter()
+                .flatten()
+                .map(|cell| cell.flags().contains(Flags::INVERSE))
+                .collect()
+        }
+        console.restore_visual_bell();
+        assert_eq!(inverse(&console), [false, true, false, false, false, false]);
+
+        console.write_terminal_bell_visual();
+        assert_eq!(inverse(&console), [true, false, true, true, true, true]);
+        console.tick(60);
+        console.write_terminal_bell_visual();
+        console.tick(60);
+        assert!(inverse(&console)[0]);
+        console.tick(40);
+        assert_eq!(inverse(&console), [false, true, false, false, false, false]);
+        assert!(console.visual_bell.is_none());
+    }
+
+    #[test]
+    fn test_cursor_blink() {
+        use crate::test_display::Framebuffer;
+        use embedded_graphics::prelude::*;
+
+        let mut console = Console::new(3, 1, Style::default());
+        let mut display = Framebuffer::new(Size::new(27, 18), Rgb888::BLACK);
+        console.write_str("ab").unwrap();
+        console.draw(&mut display).unwrap();
+
+        console.cursor_tick(499);
+        assert!(console.cursor_blink_visible());
+        assert!(!console[(0, 2)].is_dirty());
+        console.cursor_tick(1);
+        assert!(!console.cursor_blink_visible());
+        assert!(console[(0, 2)].is_dirty());
+        assert!(!console[(0, 1)].is_dirty());
+        console.draw(&mut display).unwrap();
+
+        // Two phases at once leave it as it was
+        console.set_cursor_blink_interval(100);
+        console.cursor_tick(250);
+        assert!(!console.cursor_blink_visible());
+        console.cursor_tick(50);
+        assert!(console.cursor_blink_visible());
+
+        // Steady cursors don't blink
+        console.write_str("\x1b[2 q").unwrap();
+        console.draw(&mut display).unwrap();
+        console.cursor_tick(100);
+        assert!(console.cursor_blink_visible());
+        assert!(!console[(0, 2)].is_dirty());
+        console.write_str("\x1b[1 q").unwrap();
+        console.cursor_tick(100);
+        assert!(!console.cursor_blink_visible());
+    }
+
+    #[test]
+    fn test_color_gradient_text() {
+        let mut console = Console::new(10, 1, Style::default());
+        let black = Color::RGB(Rgb888::new(0, 0, 0));
+        let white = Color::RGB(Rgb888::new(255, 255, 255));
+        console.write_str("\x1b[42m").unwrap();
+        console.write_color_gradient_text("abc", black, white);
+        console.write_str("d").unwrap();
+
+        let row = console.rows_iter().next().unwrap();
+        let fgs = row[..4]
+            .iter()
+            .map(|cell| cell.fg())
+            .collect::<alloc::vec::Vec<_>>();
+        assert_eq!(
+            fgs,
+            [
+                black,
+                Color::RGB(Rgb888::new(127, 127, 127)),
+                white,
+                Cell::default().fg(),
+            ]
+        );
+        assert!(
+            row[..4]
+                .iter()
+                .all(|cell| cell.bg() == Color::Named(NamedColor::Green))
+        );
+    }
+
+    #[test]
+    fn test_rainbow_row() {
+        let mut console = Console::new(8, 3, Style::default());
+        console.write_str("xxxxxxxx\r\nxxxxxxxx").unwrap();
+        console.write_rainbow_row(1, "rainbow");
+
+        let row = console.rows_iter().nth(1).unwrap();
+        let text = row_text(row);
+        assert_eq!(text, "rainbow ");
+        assert_eq!(row[0].fg(), Color::Named(NamedColor::BrightRed));
+        assert_eq!(row[5].fg(), Color::Named(NamedColor::BrightMagenta));
+        assert_eq!(row[6].fg(), Color::Named(NamedColor::BrightRed));
+        assert_eq!(console.get_cursor_position(), (1, 7));
+    }
+
+    #[test]
+    fn test_hyperlink() {
+        let mut console = Console::new(10, 1, Style::default());
+        let blue = Color::Named(NamedColor::Blue);
+        let black = Color::Named(NamedColor::Black);
+        console.write_str("\x1b[1m").unwrap();
+        console.write_ansi_hyperlink_clickable("https://example.com", "link", blue, black);
+        console.write_str("!").unwrap();
+
+        let row = console.rows_iter().next().unwrap();
+        let text = row_text(row);
+        assert_eq!(text, "link!     ");
+        assert!(row[..4].iter().all(|cell| cell.fg() == blue
+            && cell.bg() == black
+            && cell.flags().contains(Flags::UNDERLINE | Flags::BOLD)));
+        assert_eq!(row[4].fg(), Cell::default().fg());
+        assert_eq!(row[4].flags(), Flags::BOLD);
+    }
+}
@@ -0,0 +1,566 @@
+use core::cmp::min;
+use core::fmt::Write;
+
+use crate::Console;
+use crate::style::{DrawCell, Style};
+
+/// The position of text within a fixed-width field
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum Alignment {
+    /// Text at the start of the field, padding after
+    #[default]
+    Left,
+    /// Padding before, text at the end of the field
+    Right,
+    /// Padding split evenly on both sides, with the extra character after the text
+    Center,
+}
+
+/// How [`Console::write_timestamp`] formats a time
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum TimestampFormat {
+    /// Milliseconds since the time `ms`, e.g. `[123456 ms]`
+    Elapsed {
+        /// The start time, in milliseconds
+        ms: u64,
+    },
+    /// Hours, minutes and seconds, e.g. `[12:34:56]`. Hours are not wrapped at 24.
+    HhMmSs,
+    /// Hours, minutes, seconds and milliseconds, e.g. `[12:34:56.789]`. Hours are not wrapped at 24.
+    HhMmSsMs,
+    /// Date and time of a Unix timestamp, in UTC, e.g. `[2024-01-31T12:34:56]`
+    Iso8601Simple,
+}
+
+impl<'a, C, F> Console<'a, C, F>
+where
+    Style<'a, C, F>: DrawCell<C>,
+{
+    /// Write `text` in a field of `width` characters, filling the rest of the field with `pad_char`.
+    ///
+    /// Text that is longer than `width` is truncated, with its last visible character replaced by `…`.
+    pub fn write_padded(&mut self, text: &str, width: usize, align: Alignment, pad_char: char) {
+        let len = text.chars().count();
+        if len > width {
+            if width > 0 {
+                for c in text.chars().take(width - 1) {
+                    let _ = self.write_char(c);
+                }
+                let _ = self.write_char('…');
+            }
+            return;
+        }
+        self.write_aligned(text, len, width, align, pad_char);
+    }
+
+    /// Write `text`, which is `len` characters long, padded with `pad_char` to at least `width` characters
+    fn write_aligned(
+        &mut self,
+        text: &str,
+        len: usize,
+        width: usize,
+        align: Alignment,
+        pad_char: char,
+    ) {
+        let padding = width.saturating_sub(len);
+        let before = match align {
+            Alignment::Left => 0,
+            Alignment::Right => padding,
+            Alignment::Center => padding / 2,
+        };
+        self.write_repeated(pad_char, before);
+        let _ = self.write_str(text);
+        self.write_repeated(pad_char, padding - before);
+    }
+
+    /// Write `key` padded to `key_width` characters, followed by `separator` and `value`, e.g. `Voltage   : 3.30V`.
+    ///
+    /// Keys longer than `key_width` are truncated as in [`Console::write_padded`].
+    pub fn write_kv_pair(&mut self, key: &str, value: &str, separator: &str, key_width: usize) {
+        self.write_padded(key, key_width, Alignment::Left, ' ');
+        let _ = self.write_str(separator);
+        let _ = self.write_str(value);
+    }
+
+    /// Write each `(key, value)` of `pairs` with [`Console::write_kv_pair`], starting each one on a new line.
+    ///
+    /// There is no newline after the last pair.
+    pub fn write_kv_list(&mut self, pairs: &[(&str, &str)], key_width: usize, separator: &str) {
+        for (i, (key, value)) in pairs.iter().enumerate() {
+            if i > 0 {
+                let _ = self.write_str("\r\n");
+            }
+            self.write_kv_pair(key, value, separator, key_width);
+        }
+    }
+
+    /// Write a table of contents, with each `(label, number)` of `entries` on its own line, e.g. `Setup.........12`.
+    ///
+    /// Each label is followed by dots up to the last `number_width + 1` columns of the console, and then its number, right-aligned in `number_width` columns, leaving the last column blank. Labels longer than half of the console's width are truncated as in [`Console::write_padded`]. Lines start at the cursor, which should be in the first column, and there is no newline after the last entry.
+    pub fn write_table_of_contents(&mut self, entries: &[(&str, usize)], number_width: usize) {
+        let dots_end = self.columns().saturating_sub(number_width + 1);
+        for (i, (label, number)) in entries.iter().enumerate() {
+            if i > 0 {
+                let _ = self.write_str("\r\n");
+            }
+            let label_width = min(label.chars().count(), self.columns() / 2);
+            self.write_padded(label, label_width, Alignment::Left, ' ');
+            self.write_repeated('.', dots_end.saturating_sub(label_width));
+            self.write_unsigned_number_base(
+                *number as u64,
+                10,
+                number_width,
+                Alignment::Right,
+                false,
+            );
+        }
+    }
+
+    /// Write a timestamp at the cursor, without a trailing newline.
+    ///
+    /// `value` is a time in milliseconds, e.g. from a real-time clock or a tick counter. No memory is allocated.
+    pub fn write_timestamp(&mut self, value: u64, format: TimestampFormat) {
+        let seconds = value / 1000;
+        let mut buf = NumberBuffer::new();
+        buf.push(b']');
+        match format {
+            TimestampFormat::Elapsed { ms } => {
+                buf.push_str(b" ms");
+                buf.push_digits(value.saturating_sub(ms), 10, 1);
+            }
+            TimestampFormat::HhMmSs => buf.push_hh_mm_ss(seconds),
+            TimestampFormat::HhMmSsMs => {
+                buf.push_digits(value % 1000, 10, 3);
+                buf.push(b'.');
+                buf.push_hh_mm_ss(seconds);
+            }
+            TimestampFormat::Iso8601Simple => {
+                let (year, month, day) = civil_from_days(seconds / 86400);
+                buf.push_hh_mm_ss(seconds % 86400);
+                buf.push(b'T');
+                buf.push_digits(day, 10, 2);
+                buf.push(b'-');
+                buf.push_digits(month, 10, 2);
+                buf.push(b'-');
+                buf.push_digits(year, 10, 4);
+            }
+        }
+        buf.push(b'[');
+        buf.write_to(self, 0, Alignment::Left);
+    }
+
+    /// Write `value` in decimal, padded with spaces to at least `width` characters.
+    ///
+    /// Numbers are never truncated. No memory is allocated, which makes this suitable for frequently updated values.
+    pub fn write_signed_number(&mut self, value: i64, width: usize, align: Alignment) {
+        let mut buf = NumberBuffer::new();
+        buf.push_digits(value.unsigned_abs(), 10, 1);
+        if value < 0 {
+            buf.push(b'-');
+        }
+        buf.write_to(self, width, align);
+    }
+
+    /// Write `value` in `base`, from 2 to 36, padded with spaces to at least `width` characters.
+    ///
+    /// Numbers are never truncated. Digits above 9 are lowercase letters. With `prefix`, bases 16, 8 and 2 are prefixed with `0x`, `0o` and `0b`. No memory is allocated.
+    pub fn write_unsigned_number_base(
+        &mut self,
+        value: u64,
+        base: u8,
+        width: usize,
+        align: Alignment,
+        prefix: bool,
+    ) {
+        debug_assert!((2..=36).contains(&base), "invalid base {}", base);
+        let base = base.clamp(2, 36);
+        let mut buf = NumberBuffer::new();
+        buf.push_digits(value, base, 1);
+        if prefix {
+            match base {
+                16 => buf.push_str(b"0x"),
+                8 => buf.push_str(b"0o"),
+                2 => buf.push_str(b"0b"),
+                _ => (),
+            }
+        }
+        buf.write_to(self, width, align);
+    }
+
+    /// Write `value` with `decimal_places` digits after the decimal point, rounded, and padded with spaces to at least `width` characters, e.g. `-12.345`.
+    ///
+    /// Numbers are formatted with integer arithmetic, without allocating. `NaN`, `+Inf` and `-Inf` are written for values that aren't finite, and `+Inf` and `-Inf` also for values whose magnitude times 10 to the power of `decimal_places` doesn't fit in a `u64`, e.g. above about 1.8e17 with 2 decimal places. At most 19 decimal places are written. See `Console::write_float` (with the `float-display` feature) for `f64` values.
+    pub fn write_float_f32(
+        &mut self,
+        value: f32,
+        decimal_places: u8,
+        width: usize,
+        align: Alignment,
+    ) {
+        self.write_float_value(value, decimal_places, width, align);
+    }
+
+    /// Write `value` like [`Console::write_float_f32`], with `f64` arithmetic.
+    #[cfg(feature = "float-display")]
+    pub fn write_float(&mut self, value: f64, decimal_places: u8, width: usize, align: Alignment) {
+        self.write_float_value(value, decimal_places, width, align);
+    }
+
+    fn write_float_value<V: FloatValue>(
+        &mut self,
+        value: V,
+        decimal_places: u8,
+        width: usize,
+        align: Alignment,
+    ) {
+        let decimal_places = decimal_places.min(MAX_DECIMAL_PLACES);
+        let scaled = if value.is_nan() || value.is_infinite() {
+            None
+        } else {
+            value.scaled(decimal_places)
+        };
+        let text = match scaled {
+            Some(scaled) => {
+                self.write_fixed_point(
+                    value.is_sign_negative(),
+                    scaled,
+                    decimal_places,
+                    width,
+                    align,
+                );
+                return;
+            }
+            None if value.is_nan() => "NaN",
+            // Infinite, or too large to be formatted with integers
+            None if value.is_sign_negative() => "-Inf",
+            None => "+Inf",
+        };
+        self.write_aligned(text, text.len(), width, align, ' ');
+    }
+
+    /// Write `scaled`, which has `decimal_places` digits after the decimal point
+    fn write_fixed_point(
+        &mut self,
+        negative: bool,
+        scaled: u64,
+        decimal_places: u8,
+        width: usize,
+        align: Alignment,
+    ) {
+        let scale = 10u64.pow(decimal_places as u32);
+        let mut buf = NumberBuffer::new();
+        if decimal_places > 0 {
+            buf.push_digits(scaled % scale, 10, decimal_places as usize);
+            buf.push(b'.');
+        }
+        buf.push_digits(scaled / scale, 10, 1);
+        // Values that round to zero aren't negative
+        if negative && scaled > 0 {
+            buf.push(b'-');
+        }
+        buf.write_to(self, width, align);
+    }
+
+    fn write_repeated(&mut self, c: char, n: usize) {
+        for _ in 0..n {
+            let _ = self.write_char(c);
+        }
+    }
+}
+
+/// The most decimal places that [`Console::write_float_f32`] writes, so that the scaled value fits in a `u64`
+const MAX_DECIMAL_PLACES: u8 = 19;
+
+/// The floating point types that can be written as fixed point numbers
+trait FloatValue: Copy {
+    fn is_nan(self) -> bool;
+    fn is_infinite(self) -> bool;
+    fn is_sign_negative(self) -> bool;
+    /// The magnitude of the value times 10 to the power of `decimal_places`, rounded, if it fits in a `u64`
+    fn scaled(self, decimal_places: u8) -> Option<u64>;
+}
+
+macro_rules! impl_float_value {
+    ($float:ty) => {
+        impl FloatValue for $float {
+            fn is_nan(self) -> bool {
+                <$float>::is_nan(self)
+            }
+
+            fn is_infinite(self) -> bool {
+                <$float>::is_infinite(self)
+            }
+
+            fn is_sign_negative(self) -> bool {
+                <$float>::is_sign_negative(self)
+            }
+
+            fn scaled(self, decimal_places: u8) -> Option<u64> {
+                let magnitude = if self < 0.0 { -self } else { self };
+                let scaled = magnitude * 10u64.pow(decimal_places as u32) as $float + 0.5;
+                // Casts saturate, so compare before casting
+                (scaled < u64::MAX as $float).then_some(scaled as u64)
+            }
+        }
+    };
+}
+
+impl_float_value!(f32);
+#[cfg(feature = "float-display")]
+impl_float_value!(f64);
+
+/// A number formatted on the stack, filled from the end
+struct NumberBuffer {
+    // Enough for a sign or prefix, and the 64 binary digits of a `u64`
+    bytes: [u8; 66],
+    start: usize,
+}
+
+impl NumberBuffer {
+    fn new() -> Self {
+        Self {
+            bytes: [0; 66],
+            start: 66,
+        }
+    }
+
+    /// Add `byte` before the current contents
+    fn push(&mut self, byte: u8) {
+        self.start -= 1;
+        self.bytes[self.start] = byte;
+    }
+
+    /// Add `bytes` before the current contents
+    fn push_str(&mut self, bytes: &[u8]) {
+        for &byte in bytes.iter().rev() {
+            self.push(byte);
+        }
+    }
+
+    /// Add the digits of `value` in `base`, padded with zeros to at least `min_digits` digits, before the current contents
+    fn push_digits(&mut self, value: u64, base: u8, min_digits: usize) {
+        let mut value = value;
+        let mut digits = 0;
+        while value > 0 || digits < min_digits.max(1) {
+            let digit = (value % base as u64) as u8;
+            self.push(match digit {
+                0..=9 => b'0' + digit,
+                _ => b'a' + digit - 10,
+            });
+            value /= base as u64;
+            digits += 1;
+        }
+    }
+
+    /// Add `seconds` as hours, minutes and seconds, e.g. `12:34:56`, before the current contents
+    fn push_hh_mm_ss(&mut self, seconds: u64) {
+        self.push_digits(seconds % 60, 10, 2);
+        self.push(b':');
+        self.push_digits(seconds / 60 % 60, 10, 2);
+        self.push(b':');
+        self.push_digits(seconds / 3600, 10, 2);
+    }
+
+    /// Write the number to `console`, padded to `width`
+    fn write_to<'a, C, F>(&self, console: &mut Console<'a, C, F>, width: usize, align: Alignment)
+    where
+        Style<'a, C, F>: DrawCell<C>,
+    {
+        // Only ASCII is pushed
+        let text = core::str::from_utf8(&self.bytes[self.start..]).unwrap_or_default();
+        console.write_aligned(text, text.len(), width, align, ' ');
+    }
+}
+
+/// The (year, month, day) of a number of days since 1970-01-01
+///
+/// From Howard Hinnant's `civil_from_days` algorithm.
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let z = days + 719468;
+    let era = z / 146097;
+    let doe = z % 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_display::row_text;
+    use alloc::string::String;
+    use embedded_graphics::mono_font::MonoFont;
+    use embedded_graphics::pixelcolor::Rgb888;
+
+    fn padded(text: &str, width: usize, align: Alignment) -> String {
+        let mut console = Console::new(12, 1, Style::default());
+        console.write_str("|").unwrap();
+        console.write_padded(text, width, align, '.');
+        console.write_str("|").unwrap();
+        row_text(console.rows_iter().next().unwrap())
+    }
+
+    #[test]
+    fn test_write_padded() {
+        assert_eq!(padded("abc", 6, Alignment::Left), "|abc...|    ");
+        assert_eq!(padded("abc", 6, Alignment::Right), "|...abc|    ");
+        assert_eq!(padded("abc", 6, Alignment::Center), "|.abc..|    ");
+        assert_eq!(padded("abc", 3, Alignment::Center), "|abc|       ");
+        assert_eq!(padded("abcdefgh", 4, Alignment::Left), "|abc…|      ");
+        assert_eq!(padded("abc", 0, Alignment::Left), "||          ");
+    }
+
+    fn number(write: impl FnOnce(&mut Console<'static, Rgb888, MonoFont<'static>>)) -> String {
+        let mut console = Console::new(80, 1, Style::default());
+        write(&mut console);
+        console.write_str("|").unwrap();
+        String::from(row_text(console.rows_iter().next().unwrap()).trim_end())
+    }
+
+    #[test]
+    fn test_write_numbers() {
+        assert_eq!(
+            number(|c| c.write_signed_number(-42, 6, Alignment::Right)),
+            "   -42|"
+        );
+        assert_eq!(
+            number(|c| c.write_signed_number(i64::MIN, 0, Alignment::Left)),
+            "-9223372036854775808|"
+        );
+        assert_eq!(
+            number(|c| c.write_unsigned_number_base(255, 16, 6, Alignment::Left, true)),
+            "0xff  |"
+        );
+        assert_eq!(
+            number(|c| c.write_unsigned_number_base(u64::MAX, 2, 0, Alignment::Left, true)),
+            format!("0b{}|", "1".repeat(64))
+        );
+        assert_eq!(
+            number(|c| c.write_unsigned_number_base(8, 8, 4, Alignment::Center, false)),
+            " 10 |"
+        );
+        assert_eq!(
+            number(|c| c.write_signed_number(12345, 2, Alignment::Right)),
+            "12345|"
+        );
+        assert_eq!(
+            number(|c| c.write_unsigned_number_base(0, 36, 0, Alignment::Left, true)),
+            "0|"
+        );
+    }
+
+    #[test]
+    fn test_write_float() {
+        assert_eq!(
+            number(|c| c.write_float_f32(3.3, 2, 0, Alignment::Left)),
+            "3.30|"
+        );
+        assert_eq!(
+            number(|c| c.write_float_f32(-12.3456, 3, 9, Alignment::Right)),
+            "  -12.346|"
+        );
+        assert_eq!(
+            number(|c| c.write_float_f32(-0.001, 2, 0, Alignment::Left)),
+            "0.00|"
+        );
+        assert_eq!(
+            number(|c| c.write_float_f32(2.5, 0, 0, Alignment::Left)),
+            "3|"
+        );
+        assert_eq!(
+            number(|c| c.write_float_f32(f32::NAN, 2, 5, Alignment::Right)),
+            "  NaN|"
+        );
+        assert_eq!(
+            number(|c| c.write_float_f32(f32::NEG_INFINITY, 2, 0, Alignment::Left)),
+            "-Inf|"
+        );
+        assert_eq!(
+            number(|c| c.write_float_f32(1e30, 1, 0, Alignment::Left)),
+            "+Inf|"
+        );
+        assert_eq!(
+            number(|c| c.write_float_f32(-1e18, 2, 0, Alignment::Left)),
+            "-Inf|"
+        );
+    }
+
+    #[cfg(feature = "float-display")]
+    #[test]
+    fn test_write_float_f64() {
+        assert_eq!(
+            number(|c| c.write_float(123456789.125, 2, 14, Alignment::Center)),
+            " 123456789.13 |"
+        );
+        assert_eq!(
+            number(|c| c.write_float(f64::INFINITY, 2, 0, Alignment::Left)),
+            "+Inf|"
+        );
+    }
+
+    #[test]
+    fn test_write_kv_list() {
+        let mut console = Console::new(16, 3, Style::default());
+        console.write_kv_list(&[("Voltage", "3.30V"), ("Temperature", "21C")], 8, ": ");
+        let rows = console
+            .rows_iter()
+            .map(row_text)
+            .collect::<alloc::vec::Vec<_>>();
+        assert_eq!(
+            rows,
+            ["Voltage : 3.30V ", "Tempera…: 21C   ", "                "]
+        );
+        assert_eq!(console.get_cursor_position(), (1, 13));
+    }
+
+    #[test]
+    fn test_write_table_of_contents() {
+        let mut console = Console::new(16, 3, Style::default());
+        console
+            .write_table_of_contents(&[("Setup", 2), ("Troubleshooting", 114), ("Index", 999)], 3);
+        let rows = console
+            .rows_iter()
+            .map(row_text)
+            .collect::<alloc::vec::Vec<_>>();
+        assert_eq!(
+            rows,
+            ["Setup.......  2 ", "Trouble…....114 ", "Index.......999 "]
+        );
+        assert_eq!(console.get_cursor_position(), (2, 15));
+    }
+
+    fn timestamp(value: u64, format: TimestampFormat) -> String {
+        let mut console = Console::new(24, 1, Style::default());
+        console.write_timestamp(value, format);
+        console.write_str("x").unwrap();
+        String::from(row_text(console.rows_iter().next().unwrap()).trim_end())
+    }
+
+    #[test]
+    fn test_write_timestamp() {
+        assert_eq!(
+            timestamp(123_956, TimestampFormat::Elapsed { ms: 500 }),
+            "[123456 ms]x"
+        );
+        assert_eq!(
+            timestamp(45_296_789, TimestampFormat::HhMmSs),
+            "[12:34:56]x"
+        );
+        assert_eq!(
+            timestamp(45_296_789, TimestampFormat::HhMmSsMs),
+            "[12:34:56.789]x"
+        );
+        assert_eq!(
+            timestamp(1_706_704_496_000, TimestampFormat::Iso8601Simple),
+            "[2024-01-31T12:34:56]x"
+        );
+        assert_eq!(
+            timestamp(0, TimestampFormat::Iso8601Simple),
+            "[1970-01-01T00:00:00]x"
+        );
+    }
+}
@@ -0,0 +1,225 @@
+use alloc::vec::Vec;
+use core::convert::Infallible;
+
+use embedded_graphics::image::ImageDrawable;
+use embedded_graphics::pixelcolor::Rgb888;
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::Rectangle;
+
+use crate::Console;
+use crate::style::{ColorInterpolate, DrawCell, Style};
+
+impl<'a, C, F> Console<'a, C, F>
+where
+    Style<'a, C, F>: DrawCell<C>,
+    Rgb888: From<C>,
+{
+    /// Render the whole console to an in-memory image, ignoring the [`Style`] offset.
+    ///
+    /// Returns RGBA8 pixels in row-major order, with the size given by [`Console::image_dimensions`]. Unlike [`Console::draw`], every cell is rendered and no cell is marked as drawn.
+    pub fn export_as_image(&self) -> Vec<u8>
+    where
+        C: Copy,
+    {
+        self.render_image::<Rgb888>()
+            .pixels
+            .iter()
+            .flat_map(|color| [color.r(), color.g(), color.b(), 255])
+            .collect()
+    }
+}
+
+impl<'a, C, F> Console<'a, C, F>
+where
+    Style<'a, C, F>: DrawCell<C>,
+{
+    /// Render the whole console to a [`ConsoleImage`], which can be drawn like any other image, e.g. with [`Image`](embedded_graphics::image::Image) at any position.
+    ///
+    /// The image has the size given by [`Console::image_dimensions`], and the [`Style`] offset is ignored. As with [`Console::export_as_image`], every cell is rendered and no cell is marked as drawn. This takes a pixel for each point of the console, so it is best kept for displays that have memory to spare.
+    pub fn render_image<P: PixelColor + From<C> + ColorInterpolate>(&self) -> ConsoleImage<P>
+    where
+        C: Copy,
+    {
+        let (width, height) = self.image_dimensions();
+        let style = &self.cell_style;
+        let background = P::from(style.color_to_pixel(self.inner.buf.default_cell().bg));
+        let mut image = ConsoleImage {
+            pixels: vec![background; width as usize * height as usize],
+            size: Size::new(width, height),
+        };
+        let mut canvas = Canvas {
+            pixels: &mut image.pixels,
+            size: image.size,
+            offset: Point::new(style.offset.0 as i32, style.offset.1 as i32),
+        };
+        let mut target = self.rotated(&mut canvas);
+        for (row, cells) in self.rows_iter().enumerate() {
+            for (col, cell) in cells.iter().enumerate() {
+                let Ok(()) = style.draw_cell(cell, row, col, &mut target);
+            }
+        }
+        image
+    }
+}
+
+/// The pixels of a [`Console`], as rendered by [`Console::render_image`]
+///
+/// It implements [`ImageDrawable`], so that it can be drawn at any position with [`Image`](embedded_graphics::image::Image), and cropped with [`ImageDrawableExt::sub_image`](embedded_graphics::image::ImageDrawableExt::sub_image).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConsoleImage<P> {
+    pixels: Vec<P>,
+    size: Size,
+}
+
+impl<P: PixelColor> ConsoleImage<P> {
+    /// The pixel at `point`, or `None` if it is outside the image
+    pub fn pixel(&self, point: Point) -> Option<P> {
+        if point.x < 0
+            || point.y < 0
+            || point.x >= self.size.width as i32
+            || point.y >= self.size.height as i32
+        {
+            return None;
+        }
+        Some(self.pixels[point.y as usize * self.size.width as usize + point.x as usize])
+    }
+}
+
+impl<P> OriginDimensions for ConsoleImage<P> {
+    fn size(&self) -> Size {
+        self.size
+    }
+}
+
+impl<P: PixelColor> ImageDrawable for ConsoleImage<P> {
+    type Color = P;
+
+    fn draw<D>(&self, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = P>,
+    {
+        target.fill_contiguous(&self.bounding_box(), self.pixels.iter().copied())
+    }
+
+    fn draw_sub_image<D>(&self, target: &mut D, area: &Rectangle) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = P>,
+    {
+        // Parts of `area` outside the image are left out
+        let visible = area.intersection(&self.bounding_box());
+        target.fill_contiguous(
+            &Rectangle::new(visible.top_left - area.top_left, visible.size),
+            visible.points().filter_map(|point| self.pixel(point)),
+        )
+    }
+}
+
+/// Draws to row-major `pixels` of `size`, shifting everything drawn to it by `-offset`
+struct Canvas<'p, P> {
+    pixels: &'p mut [P],
+    size: Size,
+    offset: Point,
+}
+
+impl<P> Dimensions for Canvas<'_, P> {
+    fn bounding_box(&self) -> Rectangle {
+        Rectangle::new(self.offset, self.size)
+    }
+}
+
+impl<P: PixelColor> DrawTarget for Canvas<'_, P> {
+    type Color = P;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            let point = point - self.offset;
+            if point.x < 0
+                || point.y < 0
+                || point.x >= self.size.width as i32
+                || point.y >= self.size.height as i32
+            {
+                continue;
+            }
+            self.pixels[point.y as usize * self.size.width as usize + point.x as usize] = color;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Console, Style};
+    use core::fmt::Write;
+
+    #[test]
+    fn test_export_as_image() {
+        let mut console = Console::new(2, 1, Style::default());
+        console.write_str(" \x1b[41m ").unwrap();
+
+        let (width, height) = console.image_dimensions();
+        assert_eq!((width, height), (18, 18));
+
+        let image = console.export_as_image();
+        assert_eq!(image.len(), 18 * 18 * 4);
+        // Top left of the first cell has the default black background
+        assert_eq!(image[0..4], [0, 0, 0, 255]);
+        // The second cell has a red background
+        let i = (10 * 18 + 12) * 4;
+        assert_eq!(image[i..i + 4], [194, 54, 33, 255]);
+    }
+
+    #[test]
+    fn test_render_image() {
+        use crate::test_display::Framebuffer;
+        use embedded_graphics::image::{Image, ImageDrawableExt};
+        use embedded_graphics::pixelcolor::Rgb888;
+        use embedded_graphics::prelude::*;
+        use embedded_graphics::primitives::Rectangle;
+
+        let mut console = Console::new(2, 1, Style::default());
+        console.write_str(" \x1b[41m ").unwrap();
+        let image = console.render_image::<Rgb888>();
+        assert_eq!(image.size(), Size::new(18, 18));
+        // Rendering doesn't mark anything as drawn
+        assert!(console.rows_iter().flatten().all(|cell| cell.is_dirty()));
+
+        let red = Rgb888::new(194, 54, 33);
+        let mut display = Framebuffer::new(Size::new(40, 30), Rgb888::BLUE);
+        Image::new(&image, Point::new(5, 3))
+            .draw(&mut display)
+            .unwrap();
+        assert_eq!(display.pixel(4, 3), Rgb888::BLUE);
+        assert_eq!(display.pixel(5, 3), Rgb888::BLACK);
+        assert_eq!(display.pixel(5 + 12, 3 + 10), red);
+        assert_eq!(display.pixel(5 + 18, 3), Rgb888::BLUE);
+
+        // Only the second cell
+        let cell = image.sub_image(&Rectangle::new(Point::new(9, 0), Size::new(9, 18)));
+        Image::new(&cell, Point::new(30, 0))
+            .draw(&mut display)
+            .unwrap();
+        assert_eq!(display.pixel(30, 0), red);
+        assert_eq!(display.pixel(39, 0), Rgb888::BLUE);
+
+        // Colors are resolved as when the console is drawn
+        let resolved = Style {
+            color_to_pixel: |_| Rgb888::GREEN,
+            ..Style::default()
+        }
+        .resolve_colors();
+        let style = Style {
+            resolved_colors: Some(&resolved),
+            ..Style::default()
+        };
+        let image = Console::new(2, 1, style).render_image::<Rgb888>();
+        assert!(
+            (0..18)
+                .flat_map(|x| (0..18).map(move |y| Point::new(x, y)))
+                .all(|point| image.pixel(point) == Some(Rgb888::GREEN))
+        );
+    }
+}
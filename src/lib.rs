@@ -18,23 +18,72 @@ mod ratatui_backend;
 #[cfg(feature = "ratatui-backend")]
 pub use ratatui_backend::*;
 
-#[cfg(feature = "fontdue")]
+#[cfg(any(feature = "fontdue", feature = "tinybmp"))]
 mod text;
-#[cfg(feature = "fontdue")]
+#[cfg(any(feature = "fontdue", feature = "tinybmp"))]
 pub use text::{Mono8BitFont, Mono8BitTextStyle};
 
+#[cfg(feature = "tinybmp")]
+mod atlas;
+#[cfg(feature = "tinybmp")]
+pub use atlas::BitmapAtlasFont;
+
+#[cfg(feature = "image-export")]
+mod image_export;
+#[cfg(feature = "image-export")]
+pub use image_export::ConsoleImage;
+
+#[cfg(feature = "sixel")]
+mod sixel;
+
+#[cfg(feature = "banner")]
+mod banner;
+#[cfg(feature = "banner")]
+pub use banner::BannerStyle;
+
+#[cfg(feature = "embedded-io")]
+mod serial;
+
 mod ansi;
+mod ansi_export;
 mod cell;
 mod cell_buffer;
 mod color;
 mod console;
+mod effects;
+mod format;
+mod measure;
+mod region;
+mod search;
+mod selection;
+mod snapshot;
 mod style;
+#[cfg(test)]
+mod test_display;
+#[cfg(feature = "test-utils")]
+mod test_pattern;
+mod widgets;
 
+pub use ansi::{ControlString, DcsString, ImageProtocol, InlineImage};
+pub use cell::{Cell, Flags};
+pub use cell_buffer::{CellBuffer, LineAttr};
 pub use color::{Color, NamedColor};
-pub use console::Console;
-pub use style::{ColorInterpolate, Style, color_to_rgb, dim_rgb};
+pub use console::{Console, ConsoleError, EraseMode, NewlineMode, SavedState, TerminalEvent};
+pub use format::{Alignment, TimestampFormat};
+pub use region::ConsoleRegion;
+pub use snapshot::ConsoleSnapshot;
+pub use style::{
+    ColorInterpolate, ResolvedColors, Rotation, Style, color_to_rgb, color_to_rgb_with, dim_rgb,
+};
+pub use widgets::{ClockStyle, HeatmapPalette};
 
 /// Utility functions
 pub mod util {
-    pub use super::style::interpolate_8bit_values;
+    pub use super::measure::{
+        measure_ansi_string_dimensions, measure_ansi_string_width, strip_ansi,
+    };
+    pub use super::style::{
+        interpolate_8bit_values, interpolate_8bit_values_rounded,
+        interpolate_8bit_values_truncated, nearest_named_color,
+    };
 }
@@ -0,0 +1,167 @@
+use alloc::string::String;
+
+/// Distance between tab stops, unless set with [`Console::set_tab_width`](crate::Console::set_tab_width)
+pub(crate) const DEFAULT_TAB_WIDTH: usize = 8;
+
+/// The number of columns taken by `c` when displayed: 0 for combining and zero-width characters, 2 for wide East Asian characters and emoji, and 1 otherwise.
+pub(crate) fn char_width(c: char) -> usize {
+    match c as u32 {
+        0x0300..=0x036f
+        | 0x1ab0..=0x1aff
+        | 0x1dc0..=0x1dff
+        | 0x200b..=0x200d
+        | 0x20d0..=0x20ff
+        | 0xfe00..=0xfe0f
+        | 0xfe20..=0xfe2f
+        | 0xe0100..=0xe01ef => 0,
+        0x1100..=0x115f
+        | 0x2e80..=0x303e
+        | 0x3041..=0x33ff
+        | 0x3400..=0x4dbf
+        | 0x4e00..=0x9fff
+        | 0xa000..=0xa4cf
+        | 0xac00..=0xd7a3
+        | 0xf900..=0xfaff
+        | 0xfe30..=0xfe4f
+        | 0xff00..=0xff60
+        | 0xffe0..=0xffe6
+        | 0x1f300..=0x1f64f
+        | 0x1f900..=0x1f9ff
+        | 0x20000..=0x3fffd => 2,
+        _ => 1,
+    }
+}
+
+/// A minimal state machine that finds the characters of a string that are not part of a control sequence.
+#[derive(Debug, Default)]
+pub(crate) struct AnsiStripper {
+    state: StripState,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum StripState {
+    #[default]
+    Ground,
+    Escape,
+    /// `ESC` followed by intermediate bytes, e.g. a charset designation
+    EscapeIntermediate,
+    Csi,
+    /// OSC, DCS, SOS, PM or APC, which end with `ST`, or `BEL` for OSC
+    String,
+    StringEscape,
+}
+
+impl AnsiStripper {
+    /// Advance with the next character `c`. Returns whether `c` is printable text.
+    pub(crate) fn advance(&mut self, c: char) -> bool {
+        use StripState::*;
+
+        let (state, printable) = match (self.state, c) {
+            (String | StringEscape, '\x07') => (Ground, false),
+            (StringEscape, '\\') => (Ground, false),
+            (String | StringEscape, '\x1b') => (StringEscape, false),
+            (String | StringEscape, _) => (String, false),
+            (_, '\x18' | '\x1a') => (Ground, false),
+            (_, '\x1b') => (Escape, false),
+            (Escape, '[') => (Csi, false),
+            (Escape, ']' | 'P' | 'X' | '^' | '_') => (String, false),
+            (Escape | EscapeIntermediate, ' '..='/') => (EscapeIntermediate, false),
+            (Escape | EscapeIntermediate, _) => (Ground, false),
+            // Parameter and intermediate bytes, then a final byte
+            (Csi, '\x20'..='\x3f') => (Csi, false),
+            (Csi, _) => (Ground, false),
+            (Ground, c) => (Ground, !c.is_control()),
+        };
+        self.state = state;
+        printable
+    }
+
+    /// Whether the last character was part of a control sequence that hasn't ended yet
+    pub(crate) fn in_sequence(&self) -> bool {
+        self.state != StripState::Ground
+    }
+}
+
+/// `text` without its control sequences, e.g. to log text that was colored for a terminal.
+///
+/// Control characters that aren't part of a sequence, like newlines, are kept.
+pub fn strip_ansi(text: &str) -> String {
+    let mut stripper = AnsiStripper::default();
+    text.chars()
+        .filter(|&c| {
+            let in_sequence = stripper.in_sequence();
+            stripper.advance(c) || !(in_sequence || stripper.in_sequence())
+        })
+        .collect()
+}
+
+/// The number of columns that `s` takes when displayed, not counting control characters and sequences.
+///
+/// Wide characters count as two columns, and tabs move to the next multiple of 8 columns. See [`measure_ansi_string_dimensions`].
+pub fn measure_ansi_string_width(s: &str) -> usize {
+    measure_ansi_string_dimensions(s).0
+}
+
+/// The number of columns that `s` takes when displayed, and the number of visible characters in it, not counting control characters and sequences.
+///
+/// These differ by the number of wide (two column) characters in `s`, and by the columns that tabs move over.
+pub fn measure_ansi_string_dimensions(s: &str) -> (usize, usize) {
+    measure_with_tab_width(s, DEFAULT_TAB_WIDTH)
+}
+
+/// [`measure_ansi_string_dimensions`], with tab stops every `tab_width` columns
+pub(crate) fn measure_with_tab_width(s: &str, tab_width: usize) -> (usize, usize) {
+    let mut stripper = AnsiStripper::default();
+    let (mut columns, mut chars) = (0, 0);
+    for c in s.chars() {
+        let in_sequence = stripper.in_sequence();
+        if stripper.advance(c) {
+            let width = char_width(c);
+            if width > 0 {
+                columns += width;
+                chars += 1;
+            }
+        } else if c == '\t' && !in_sequence {
+            columns = (columns / tab_width + 1) * tab_width;
+        }
+    }
+    (columns, chars)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_measure_ansi_string() {
+        assert_eq!(measure_ansi_string_width("plain"), 5);
+        assert_eq!(measure_ansi_string_width("\x1b[1;31mred\x1b[0m"), 3);
+        assert_eq!(
+            measure_ansi_string_width("\x1b]0;title\x07a\x1b]8;;http://a\x1b\\b"),
+            2
+        );
+        assert_eq!(measure_ansi_string_width("\x1b(Bx\r\n"), 1);
+        assert_eq!(
+            measure_ansi_string_dimensions("\x1b[32m日本語\x1b[m!"),
+            (7, 4)
+        );
+        assert_eq!(measure_ansi_string_dimensions("e\u{301}"), (1, 1));
+    }
+
+    #[test]
+    fn test_measure_tabs() {
+        assert_eq!(measure_ansi_string_dimensions("ab\tc"), (9, 3));
+        assert_eq!(measure_ansi_string_width("\x1b[1m\t\x1b[0m\tx"), 17);
+        assert_eq!(measure_ansi_string_width("12345678\t"), 16);
+        assert_eq!(measure_with_tab_width("ab\tc", 4), (5, 3));
+    }
+
+    #[test]
+    fn test_strip_ansi() {
+        assert_eq!(strip_ansi("plain\ttext"), "plain\ttext");
+        assert_eq!(
+            strip_ansi("\x1b[1;31mred\x1b[0m\r\n\x1b]8;;http://a\x1b\\link\x1b(B!"),
+            "red\r\nlink!"
+        );
+    }
+}
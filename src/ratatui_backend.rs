@@ -1,5 +1,6 @@
 use embedded_graphics::pixelcolor::Rgb888;
-use embedded_graphics::prelude::{DrawTarget, PixelColor};
+use embedded_graphics::prelude::{Dimensions, DrawTarget, Pixel, PixelColor};
+use embedded_graphics::primitives::Rectangle;
 use ratatui::backend::{ClearType, WindowSize};
 use ratatui::buffer::Cell as RatatuiCell;
 use ratatui::prelude::{Position, Size};
@@ -13,7 +14,7 @@ use crate::style::{ColorInterpolate, DrawCell, Style};
 /// A set of glyphs to be used by [`Mono8BitFont`] that contains the border characters Ratatui uses.
 ///
 /// Includes ASCII characters and the [box drawing and block element characters](https://en.wikipedia.org/wiki/Box-drawing_characters).
-pub const RATATUI_GLYPHS: &'static str = "\0\u{20}\u{7e}\0\u{2500}\u{259f}";
+pub const RATATUI_GLYPHS: &str = "\0\u{20}\u{7e}\0\u{2500}\u{259f}";
 
 /// A [`ratatui::backend::Backend`] implementation for the Embedded Temu
 pub struct EmbeddedTemuBackend<'a, C, E, P, FD: FlushableDisplay<E, P>, F> {
@@ -33,6 +34,19 @@ impl<'a, C, E, P, FD: FlushableDisplay<E, P>, F> EmbeddedTemuBackend<'a, C, E, P
             _marker2: core::marker::PhantomData,
         }
     }
+
+    /// Resize the console to `new_cols` by `new_rows`, e.g. after the display has been rotated or replaced.
+    ///
+    /// The whole console is redrawn on the next flush. Ratatui's [`Terminal::resize`](ratatui::Terminal::resize) should be called afterwards, so that its buffers match the new size.
+    pub fn resize_console(&mut self, new_cols: u16, new_rows: u16) -> Result<(), BackendError<E>>
+    where
+        E: core::fmt::Debug,
+        Style<'a, C, F>: DrawCell<C>,
+    {
+        self.console.resize(new_cols as usize, new_rows as usize);
+        self.console.force_full_redraw();
+        Ok(())
+    }
 }
 
 /// A trait for displays that can be flushed
@@ -46,6 +60,35 @@ pub trait FlushableDisplay<E, C>: DrawTarget<Error = E, Color = C> {
     fn flush(&mut self) -> Result<(), E>;
 }
 
+/// A [`FlushableDisplay`] for any [`DrawTarget`] that draws directly to the screen, and so has nothing to flush.
+pub struct NoFlush<D>(pub D);
+
+impl<D: DrawTarget> FlushableDisplay<D::Error, D::Color> for NoFlush<D> {
+    const NUM_BUFFERS: usize = 1;
+
+    fn flush(&mut self) -> Result<(), D::Error> {
+        Ok(())
+    }
+}
+
+impl<D: DrawTarget> DrawTarget for NoFlush<D> {
+    type Color = D::Color;
+    type Error = D::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        self.0.draw_iter(pixels)
+    }
+}
+
+impl<D: Dimensions> Dimensions for NoFlush<D> {
+    fn bounding_box(&self) -> Rectangle {
+        self.0.bounding_box()
+    }
+}
+
 /// Errors that can occur when using the [`EmbeddedTemuBackend`]
 #[derive(Debug)]
 pub enum BackendError<E: core::fmt::Debug> {
@@ -65,7 +108,7 @@ impl<E: core::fmt::Display + core::fmt::Debug> core::error::Error for BackendErr
 
 impl<
     'a,
-    C,
+    C: Copy,
     E: core::fmt::Display + core::fmt::Debug,
     P: PixelColor + From<C> + ColorInterpolate,
     FD: FlushableDisplay<E, P>,
@@ -178,11 +221,15 @@ fn ratatui_cell_to_cell(cell: &RatatuiCell, num_buffers: usize) -> Cell {
         c: cell.symbol().chars().next().unwrap(),
         fg: ratatui_color_to_color(&cell.fg, false),
         bg: ratatui_color_to_color(&cell.bg, true),
+        underline_color: None,
         flags: ratatui_modifier_to_flags(&cell.modifier),
         to_flush: num_buffers,
     }
 }
 
+/// The [`Color`] of a ratatui color.
+///
+/// Indexed colors are kept as [`Color::Indexed`], so that they are resolved by the console's [`Style::color_to_pixel`], like indexed colors written with escape sequences.
 fn ratatui_color_to_color(color: &RatatuiColor, bg: bool) -> Color {
     match color {
         RatatuiColor::Reset => {
@@ -238,3 +285,82 @@ fn ratatui_modifier_to_flags(modifier: &RatatuiModifier) -> Flags {
     }
     flags
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Console;
+    use crate::test_display::CountingDisplay;
+    use embedded_graphics::prelude::Size as EgSize;
+    use ratatui::Terminal;
+    use ratatui::widgets::Paragraph;
+
+    #[test]
+    fn test_no_flush_backend() {
+        let console = Console::new(10, 2, Style::default());
+        let backend = EmbeddedTemuBackend::new(console, NoFlush(CountingDisplay::default()));
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal
+            .draw(|f| f.render_widget(Paragraph::new("Hi"), f.area()))
+            .unwrap();
+
+        let backend = terminal.backend();
+        assert_eq!(backend.console.rows_iter().next().unwrap()[0].c(), 'H');
+        assert!(backend.display.0.pixels > 0);
+    }
+
+    #[test]
+    fn test_indexed_colors_use_console_style() {
+        use crate::style::color_to_rgb;
+        use crate::test_display::Framebuffer;
+        use ratatui::style::Stylize;
+
+        let teal = Rgb888::new(0, 128, 128);
+        let style = Style {
+            color_to_pixel: |color| match color {
+                Color::Indexed(200) => Rgb888::new(0, 128, 128),
+                color => color_to_rgb(color),
+            },
+            ..Style::default()
+        };
+        let console = Console::new(2, 1, style);
+        let display = Framebuffer::new(EgSize::new(18, 18), Rgb888::new(0, 0, 0));
+        let backend = EmbeddedTemuBackend::new(console, NoFlush(display));
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal
+            .draw(|f| {
+                f.render_widget(
+                    Paragraph::new("  ").bg(RatatuiColor::Indexed(200)),
+                    f.area(),
+                )
+            })
+            .unwrap();
+
+        let backend = terminal.backend();
+        assert_eq!(
+            backend.console.rows_iter().next().unwrap()[0].bg(),
+            Color::Indexed(200)
+        );
+        assert_eq!(backend.display.0.pixel(0, 0), teal);
+        assert_eq!(backend.display.0.pixel(17, 17), teal);
+    }
+
+    #[test]
+    fn test_resize_console() {
+        use ratatui::backend::Backend;
+        use ratatui::layout::Rect;
+
+        let console = Console::new(10, 2, Style::default());
+        let backend = EmbeddedTemuBackend::new(console, NoFlush(CountingDisplay::default()));
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal.backend_mut().resize_console(5, 3).unwrap();
+        terminal.resize(Rect::new(0, 0, 5, 3)).unwrap();
+        terminal
+            .draw(|f| f.render_widget(Paragraph::new("a\nb\nc"), f.area()))
+            .unwrap();
+
+        let backend = terminal.backend();
+        assert_eq!(backend.size().unwrap(), Size::new(5, 3));
+        assert_eq!(backend.console.rows_iter().nth(2).unwrap()[0].c(), 'c');
+    }
+}
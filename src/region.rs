@@ -0,0 +1,214 @@
+use alloc::string::String;
+use core::cmp::min;
+use core::fmt;
+
+use crate::Console;
+use crate::ansi::Handler;
+use crate::measure::{AnsiStripper, char_width};
+use crate::style::{DrawCell, Style};
+
+/// A rectangular part of a [`Console`] that can be written to without affecting the rest of it, e.g. to give each widget of a UI its own area.
+///
+/// Created with [`Console::region`]. The region has its own cursor, relative to its top left corner. Text wraps at the right edge of the region, and text past its bottom edge is discarded.
+///
+/// Control characters move the region's cursor: `\r` to the first column, `\n` down a row and backspace left a column. SGR (`CSI … m`) sequences set the text attributes of the console, and other control sequences are ignored, so that they can't move the cursor or erase text outside of the region.
+pub struct ConsoleRegion<'r, 'a, C, F> {
+    console: &'r mut Console<'a, C, F>,
+    origin_row: usize,
+    origin_col: usize,
+    rows: usize,
+    cols: usize,
+    // Cursor, relative to the origin
+    row: usize,
+    col: usize,
+    // Finds the control sequences in the text, which are collected in `sequence`
+    stripper: AnsiStripper,
+    sequence: String,
+}
+
+impl<'a, C, F> Console<'a, C, F>
+where
+    Style<'a, C, F>: DrawCell<C>,
+{
+    /// The [`ConsoleRegion`] of `rows` by `cols` cells with its top left corner at `row`, `col`.
+    ///
+    /// The region is clipped to the bounds of the console.
+    pub fn region(
+        &mut self,
+        row: usize,
+        col: usize,
+        rows: usize,
+        cols: usize,
+    ) -> ConsoleRegion<'_, 'a, C, F> {
+        let origin_row = min(row, self.rows());
+        let origin_col = min(col, self.columns());
+        ConsoleRegion {
+            origin_row,
+            origin_col,
+            rows: min(rows, self.rows() - origin_row),
+            cols: min(cols, self.columns() - origin_col),
+            console: self,
+            row: 0,
+            col: 0,
+            stripper: AnsiStripper::default(),
+            sequence: String::new(),
+        }
+    }
+}
+
+impl<'r, 'a, C, F> ConsoleRegion<'r, 'a, C, F>
+where
+    Style<'a, C, F>: DrawCell<C>,
+{
+    /// Number of rows
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Number of columns
+    pub fn columns(&self) -> usize {
+        self.cols
+    }
+
+    /// Get the cursor position, relative to the top left corner of the region
+    pub fn get_cursor_position(&self) -> (usize, usize) {
+        (self.row, self.col)
+    }
+
+    /// Move the cursor to `row`, `col`, relative to the top left corner of the region
+    pub fn goto(&mut self, row: usize, col: usize) {
+        self.row = min(row, self.rows.saturating_sub(1));
+        self.col = min(col, self.cols.saturating_sub(1));
+    }
+
+    /// Erase every cell of the region, as the console erases cells, and move the cursor to its top left corner
+    pub fn clear(&mut self) {
+        let inner = &mut self.console.inner;
+        let cell = inner.erased_cell();
+        for row in self.origin_row..self.origin_row + self.rows {
+            for col in self.origin_col..self.origin_col + self.cols {
+                inner.buf.write(row, col, cell);
+            }
+        }
+        self.row = 0;
+        self.col = 0;
+    }
+
+    fn input(&mut self, c: char) {
+        // Combining and joining characters go to the cell that was just written, without taking a column
+        if char_width(c) == 0 {
+            self.console.inner.input(c);
+            return;
+        }
+        if self.col >= self.cols {
+            self.row += 1;
+            self.col = 0;
+        }
+        if self.row >= self.rows || self.cols == 0 {
+            return;
+        }
+        let cursor = (self.origin_row + self.row, self.origin_col + self.col);
+        // Moving the cursor would break up a sequence of joined characters
+        if self.console.get_cursor_position() != cursor {
+            self.console.inner.goto(cursor.0, cursor.1);
+        }
+        self.console.inner.input(c);
+        // Characters that are joined to the one before don't move the cursor
+        self.col += self
+            .console
+            .get_cursor_position()
+            .1
+            .saturating_sub(cursor.1);
+    }
+
+    fn execute(&mut self, c: char) {
+        match c {
+            '\r' => self.col = 0,
+            '\n' => self.row = min(self.row + 1, self.rows),
+            '\x08' => self.col = min(self.col, self.cols).saturating_sub(1),
+            _ => (),
+        }
+    }
+
+    /// Pass a complete control sequence on to the console if it only sets attributes
+    fn end_sequence(&mut self) {
+        let sequence = core::mem::take(&mut self.sequence);
+        if sequence.starts_with("\x1b[") && sequence.ends_with('m') {
+            let _ = fmt::Write::write_str(self.console, &sequence);
+        }
+    }
+}
+
+impl<'r, 'a, C, F> fmt::Write for ConsoleRegion<'r, 'a, C, F>
+where
+    Style<'a, C, F>: DrawCell<C>,
+{
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            let in_sequence = self.stripper.in_sequence() || c == '\x1b';
+            if self.stripper.advance(c) {
+                self.input(c);
+            } else if in_sequence {
+                self.sequence.push(c);
+                if !self.stripper.in_sequence() {
+                    self.end_sequence();
+                }
+            } else {
+                self.execute(c);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Cell;
+    use crate::color::{Color, NamedColor};
+    use crate::test_display::row_text;
+    use alloc::vec::Vec;
+    use fmt::Write;
+
+    #[test]
+    fn test_region_wraps_and_clips() {
+        let mut console = Console::new(8, 4, Style::default());
+        console.write_str("\x1b[2;2H").unwrap();
+        let mut region = console.region(1, 2, 2, 4);
+        region
+            .write_str("abcdef\x1b[31m\x1b[Hgh\x1b[2J\r\nijk")
+            .unwrap();
+        assert_eq!(region.get_cursor_position(), (2, 0));
+
+        let rows = console.rows_iter().map(row_text).collect::<Vec<_>>();
+        assert_eq!(rows, ["        ", "  abcd  ", "  efgh  ", "        "]);
+        let row = console.rows_iter().nth(2).unwrap();
+        assert_eq!(row[3].fg(), Cell::default().fg());
+        assert_eq!(row[4].fg(), Color::Named(NamedColor::Red));
+    }
+
+    #[test]
+    fn test_region_zero_width() {
+        let mut console = Console::new(6, 2, Style::default());
+        let mut region = console.region(0, 1, 2, 5);
+        region
+            .write_str("e\u{301}x\u{2764}\u{fe0f}\u{200d}\u{1f525}y")
+            .unwrap();
+        assert_eq!(region.get_cursor_position(), (0, 4));
+        let rows = console.rows_iter().map(row_text).collect::<Vec<_>>();
+        assert_eq!(rows, [" ex\u{2764}y ", "      "]);
+    }
+
+    #[test]
+    fn test_region_clear() {
+        let mut console = Console::new(6, 3, Style::default());
+        console.write_str("xxxxxx\r\nxxxxxx\r\nxxxxxx").unwrap();
+        let mut region = console.region(1, 4, 5, 5);
+        assert_eq!((region.rows(), region.columns()), (2, 2));
+        region.write_str("ab").unwrap();
+        region.clear();
+        region.write_str("c").unwrap();
+        let rows = console.rows_iter().map(row_text).collect::<Vec<_>>();
+        assert_eq!(rows, ["xxxxxx", "xxxxc ", "xxxx  "]);
+    }
+}
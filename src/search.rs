@@ -0,0 +1,101 @@
+use alloc::vec::Vec;
+
+use crate::Console;
+use crate::cell::Flags;
+use crate::style::{DrawCell, Style};
+
+/// Whether `a` and `b` are the same character, ignoring case if `case_sensitive` is false
+fn chars_match(a: char, b: char, case_sensitive: bool) -> bool {
+    a == b || (!case_sensitive && a.to_lowercase().eq(b.to_lowercase()))
+}
+
+/// The `(row, col)` of the first occurrence of `query` in `line` that starts on or after row `from_line`
+fn find_in_line(
+    line: &[(char, usize, usize)],
+    query: &[char],
+    from_line: usize,
+    case_sensitive: bool,
+) -> Option<(usize, usize)> {
+    line.windows(query.len())
+        .find(|window| {
+            window[0].1 >= from_line
+                && window
+                    .iter()
+                    .zip(query)
+                    .all(|(&(c, _, _), &q)| chars_match(c, q, case_sensitive))
+        })
+        .map(|window| (window[0].1, window[0].2))
+}
+
+impl<'a, C, F> Console<'a, C, F>
+where
+    Style<'a, C, F>: DrawCell<C>,
+{
+    /// The `(row, col)` of the first occurrence of `query` that starts on or after row `from_line`, or `None` if there isn't one.
+    ///
+    /// Rows that were wrapped onto the next one are searched as a single line, so matches can span rows. The spacers after wide characters are skipped, and an empty `query` never matches.
+    pub fn search(
+        &self,
+        query: &str,
+        from_line: usize,
+        case_sensitive: bool,
+    ) -> Option<(usize, usize)> {
+        let query = query.chars().collect::<Vec<_>>();
+        if query.is_empty() {
+            return None;
+        }
+        // The characters of the current line, and their positions
+        let mut line: Vec<(char, usize, usize)> = Vec::new();
+        for (row, cells) in self.rows_iter().enumerate() {
+            line.extend(
+                cells
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, cell)| !cell.flags.contains(Flags::WIDE_CHAR_SPACER))
+                    .map(|(col, cell)| (cell.c, row, col)),
+            );
+            if cells
+                .last()
+                .is_some_and(|cell| cell.flags.contains(Flags::WRAPLINE))
+            {
+                continue;
+            }
+            let found = find_in_line(&line, &query, from_line, case_sensitive);
+            if found.is_some() {
+                return found;
+            }
+            line.clear();
+        }
+        // The last row wraps, so its line hasn't been searched yet
+        find_in_line(&line, &query, from_line, case_sensitive)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::fmt::Write;
+
+    #[test]
+    fn test_search() {
+        let mut console = Console::new(8, 4, Style::default());
+        console
+            .write_str("boot ok\r\nERROR: disk\r\nerror")
+            .unwrap();
+
+        assert_eq!(console.search("error", 0, true), Some((3, 0)));
+        assert_eq!(console.search("error", 0, false), Some((1, 0)));
+        assert_eq!(console.search("error", 2, false), Some((3, 0)));
+        assert_eq!(console.search("error", 4, false), None);
+        // Wrapped onto the next row
+        assert_eq!(console.search(": disk", 0, true), Some((1, 5)));
+        // Not wrapped, so not a match
+        assert_eq!(console.search("okERROR", 0, true), None);
+        assert_eq!(console.search("", 0, true), None);
+
+        // The last row wraps onto a row that has scrolled off
+        let mut console = Console::new(4, 2, Style::default());
+        console.write_str("abcdefgh\x1b[H\x1bM").unwrap();
+        assert_eq!(console.search("abcd", 0, true), Some((1, 0)));
+    }
+}
@@ -0,0 +1,161 @@
+use alloc::string::String;
+
+use crate::Console;
+use crate::cell::Flags;
+use crate::style::{DrawCell, Style};
+
+/// Whether `(row, col)` is within `selection`, from `(start_row, start_col)` to `(end_row, end_col)` inclusive in reading order
+pub(crate) fn is_selected(selection: (usize, usize, usize, usize), row: usize, col: usize) -> bool {
+    let (start_row, start_col, end_row, end_col) = selection;
+    (start_row, start_col) <= (row, col) && (row, col) <= (end_row, end_col)
+}
+
+impl<'a, C, F> Console<'a, C, F>
+where
+    Style<'a, C, F>: DrawCell<C>,
+{
+    /// Highlight the cells from `(start_row, start_col)` to `(end_row, end_col)` inclusive, or remove the highlight with `None`.
+    ///
+    /// The selection runs in reading order, like text selected with a mouse: from the start to the end of the first row, over every row in between, and from the start of the last row to the end. The ends can be given in either order. Selected cells are drawn inverted, without changing the cells themselves, and the cells that are selected or deselected are drawn again on the next flush.
+    pub fn set_selection(&mut self, selection: Option<(usize, usize, usize, usize)>) {
+        let selection = selection.map(|(start_row, start_col, end_row, end_col)| {
+            if (start_row, start_col) <= (end_row, end_col) {
+                (start_row, start_col, end_row, end_col)
+            } else {
+                (end_row, end_col, start_row, start_col)
+            }
+        });
+        if selection == self.selection {
+            return;
+        }
+        self.mark_selection_dirty();
+        self.selection = selection;
+        self.mark_selection_dirty();
+    }
+
+    /// The selected cells, as `(start_row, start_col, end_row, end_col)` with the start first, or `None` if nothing is selected
+    pub fn selection(&self) -> Option<(usize, usize, usize, usize)> {
+        self.selection
+    }
+
+    /// The characters of the selected cells, or `None` if nothing is selected
+    ///
+    /// Rows are separated by newlines, except where a row was wrapped onto the next one, and spaces at the end of each line are trimmed. The spacers after wide characters are skipped.
+    pub fn selection_text(&self) -> Option<String> {
+        let selection @ (start_row, _, end_row, _) = self.selection?;
+        let mut text = String::new();
+        for (row, cells) in self
+            .rows_iter()
+            .enumerate()
+            .take(end_row + 1)
+            .skip(start_row)
+        {
+            text.extend(
+                cells
+                    .iter()
+                    .enumerate()
+                    .filter(|&(col, cell)| {
+                        is_selected(selection, row, col)
+                            && !cell.flags.contains(Flags::WIDE_CHAR_SPACER)
+                    })
+                    .map(|(_, cell)| cell.c),
+            );
+            let wrapped = cells
+                .last()
+                .is_some_and(|cell| cell.flags.contains(Flags::WRAPLINE));
+            if !wrapped || row == end_row {
+                text.truncate(text.trim_end_matches(' ').len());
+                if row < end_row.min(self.rows() - 1) {
+                    text.push('\n');
+                }
+            }
+        }
+        Some(text)
+    }
+
+    /// Make sure the selected cells are drawn on the next flush
+    fn mark_selection_dirty(&mut self) {
+        let Some(selection @ (start_row, _, end_row, _)) = self.selection else {
+            return;
+        };
+        for row in start_row..=end_row.min(self.rows().saturating_sub(1)) {
+            for col in 0..self.columns() {
+                if is_selected(selection, row, col) {
+                    self.mark_cell_dirty(row, col);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cell::Flags;
+    use crate::test_display::Framebuffer;
+    use alloc::string::String;
+    use core::fmt::Write;
+    use embedded_graphics::pixelcolor::Rgb888;
+    use embedded_graphics::prelude::*;
+
+    #[test]
+    fn test_selection() {
+        let mut console = Console::new(4, 2, Style::default());
+        console.write_str("abcdefgh").unwrap();
+        let mut display = Framebuffer::new(Size::new(36, 36), Rgb888::new(1, 2, 3));
+        console.draw(&mut display).unwrap();
+        let background = display.pixel(1, 1);
+
+        console.set_selection(Some((1, 1, 0, 2)));
+        assert_eq!(console.selection(), Some((0, 2, 1, 1)));
+        console.draw(&mut display).unwrap();
+        // The top left pixel of each cell
+        let corners = (0..2)
+            .flat_map(|row| (0..4).map(move |col| (row, col)))
+            .map(|(row, col)| display.pixel(col * 9 + 1, row * 18 + 1) != background)
+            .collect::<alloc::vec::Vec<_>>();
+        assert_eq!(
+            corners,
+            [false, false, true, true, true, true, false, false]
+        );
+
+        // The cells themselves are unchanged
+        let text = console
+            .rows_iter()
+            .flatten()
+            .map(|cell| cell.c())
+            .collect::<String>();
+        assert_eq!(text, "abcdefgh");
+        assert!(
+            console
+                .rows_iter()
+                .flatten()
+                .all(|cell| !cell.flags().contains(Flags::INVERSE))
+        );
+
+        assert_eq!(console.selection_text().as_deref(), Some("cdef"));
+
+        console.set_selection(None);
+        assert_eq!(console.selection_text(), None);
+        console.draw(&mut display).unwrap();
+        assert_eq!(display.pixel(2 * 9 + 1, 1), background);
+        assert_eq!(display.pixel(9 + 1, 18 + 1), background);
+    }
+
+    #[test]
+    fn test_selection_text() {
+        let mut console = Console::new(6, 4, Style::default());
+        console.write_str("ab  \r\ncdefghij\r\nkl").unwrap();
+
+        console.set_selection(Some((0, 1, 1, 2)));
+        assert_eq!(console.selection_text().as_deref(), Some("b\ncde"));
+        // The second row was wrapped onto the third, and the last row is empty
+        console.set_selection(Some((0, 0, 3, 5)));
+        assert_eq!(
+            console.selection_text().as_deref(),
+            Some("ab\ncdefghij\nkl")
+        );
+        console.set_selection(Some((1, 4, 2, 1)));
+        assert_eq!(console.selection_text().as_deref(), Some("ghij"));
+    }
+}
@@ -0,0 +1,103 @@
+use core::fmt;
+
+use crate::Console;
+use crate::cell::Flags;
+use crate::style::{DrawCell, Style};
+
+/// Write formatted `args` to `out`, returning the error of `out` if there is one
+fn write_fmt_to<W: embedded_io::Write>(out: &mut W, args: fmt::Arguments) -> Result<(), W::Error> {
+    struct Adapter<'w, W: embedded_io::Write> {
+        out: &'w mut W,
+        result: Result<(), W::Error>,
+    }
+
+    impl<W: embedded_io::Write> fmt::Write for Adapter<'_, W> {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            self.out.write_all(s.as_bytes()).map_err(|error| {
+                self.result = Err(error);
+                fmt::Error
+            })
+        }
+    }
+
+    let mut adapter = Adapter {
+        out,
+        result: Ok(()),
+    };
+    // Formatting can only fail when writing does
+    let _ = fmt::write(&mut adapter, args);
+    adapter.result
+}
+
+impl<'a, C, F> Console<'a, C, F>
+where
+    Style<'a, C, F>: DrawCell<C>,
+{
+    /// Write the content of the console to `out` as plain text, e.g. to a serial port on a device without a display.
+    ///
+    /// A header with the dimensions and the cursor position is written first, followed by each row with its trailing spaces trimmed. Lines end with `\r\n`. Control characters in cells are written as `[U+XXXX]`, and nothing is allocated.
+    pub fn debug_dump_to_serial<W: embedded_io::Write>(&self, out: &mut W) -> Result<(), W::Error> {
+        let (row, col) = self.get_cursor_position();
+        write_fmt_to(
+            out,
+            format_args!(
+                "Console {}x{}, cursor at row {} column {}\r\n",
+                self.columns(),
+                self.rows(),
+                row,
+                col
+            ),
+        )?;
+        for cells in self.rows_iter() {
+            let len = cells
+                .iter()
+                .rposition(|cell| cell.c != ' ')
+                .map_or(0, |last| last + 1);
+            for cell in cells[..len]
+                .iter()
+                .filter(|cell| !cell.flags.contains(Flags::WIDE_CHAR_SPACER))
+            {
+                if cell.c.is_control() {
+                    write_fmt_to(out, format_args!("[U+{:04X}]", cell.c as u32))?;
+                } else {
+                    out.write_all(cell.c.encode_utf8(&mut [0; 4]).as_bytes())?;
+                }
+            }
+            out.write_all(b"\r\n")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Cell;
+    use crate::color::{Color, NamedColor};
+    use core::fmt::Write;
+
+    #[test]
+    fn test_debug_dump_to_serial() {
+        let mut console = Console::new(6, 3, Style::default());
+        console.write_str("héllo\r\n\x1b[1mab").unwrap();
+        let cell = Cell::new(
+            '\x07',
+            Color::Named(NamedColor::White),
+            Color::Named(NamedColor::Black),
+            Flags::empty(),
+        );
+        console.inner.buf.write(2, 1, cell);
+
+        let mut buf = [0; 128];
+        let mut out = &mut buf[..];
+        console.debug_dump_to_serial(&mut out).unwrap();
+        let written = 128 - out.len();
+        assert_eq!(
+            core::str::from_utf8(&buf[..written]).unwrap(),
+            "Console 6x3, cursor at row 1 column 2\r\nhéllo\r\nab\r\n [U+0007]\r\n"
+        );
+
+        let mut small = [0; 8];
+        assert!(console.debug_dump_to_serial(&mut &mut small[..]).is_err());
+    }
+}
@@ -0,0 +1,361 @@
+use alloc::vec::Vec;
+use core::cmp::min;
+use core::ops::Range;
+
+use embedded_graphics::pixelcolor::Rgb888;
+use embedded_graphics::prelude::*;
+
+/// The default color registers of a VT340, in percent
+const DEFAULT_PALETTE: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (20, 20, 80),
+    (80, 13, 13),
+    (20, 80, 20),
+    (80, 20, 80),
+    (20, 80, 80),
+    (80, 80, 20),
+    (53, 53, 53),
+    (26, 26, 26),
+    (33, 33, 60),
+    (60, 26, 26),
+    (33, 60, 33),
+    (60, 33, 60),
+    (33, 60, 60),
+    (60, 60, 33),
+    (80, 80, 80),
+];
+
+const NUM_COLOR_REGISTERS: usize = 256;
+
+/// A decoded sixel image
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SixelImage {
+    pub width: usize,
+    pub height: usize,
+    /// Row-major pixels, `None` where the image is transparent
+    pub pixels: Vec<Option<Rgb888>>,
+}
+
+/// A sixel image shown at a cell position, over the cells
+#[derive(Debug)]
+pub(crate) struct PlacedImage {
+    /// May be negative when the image has been scrolled partly off the top of the screen
+    pub row: isize,
+    pub col: usize,
+    pub image: SixelImage,
+    /// Whether the image needs to be drawn, because it is new or the cells under it were drawn
+    pub dirty: bool,
+}
+
+impl PlacedImage {
+    /// Whether the image covers any of the cells in `rows` and `cols`
+    pub fn overlaps(&self, rows: &Range<usize>, cols: &Range<usize>, cell_size: Size) -> bool {
+        let bottom = self.row + self.image.rows(cell_size) as isize;
+        let right = self.col + self.image.columns(cell_size);
+        (rows.start as isize) < bottom
+            && self.row < rows.end as isize
+            && cols.start < right
+            && self.col < cols.end
+    }
+}
+
+impl SixelImage {
+    /// Decode the `params` and `data` of a sixel DCS (`ESC P params q data ESC \`).
+    ///
+    /// Pixels beyond `max_width` by `max_height` are discarded.
+    pub fn decode(params: &[u16], data: &[u8], max_width: usize, max_height: usize) -> Self {
+        let transparent = params.get(1) == Some(&1);
+        let mut palette: Vec<Rgb888> = (0..NUM_COLOR_REGISTERS)
+            .map(|i| {
+                let (r, g, b) = DEFAULT_PALETTE.get(i).copied().unwrap_or((0, 0, 0));
+                rgb_from_percent(r.into(), g.into(), b.into())
+            })
+            .collect();
+        let mut color = palette[0];
+        let mut rows: Vec<Vec<Option<Rgb888>>> = Vec::new();
+        let (mut width, mut height) = (0, 0);
+        let (mut x, mut y) = (0, 0);
+
+        let mut i = 0;
+        while i < data.len() {
+            let byte = data[i];
+            i += 1;
+            match byte {
+                b'"' => {
+                    let (args, next) = parse_numbers(data, i);
+                    i = next;
+                    if let [_, _, w, h, ..] = args[..] {
+                        width = width.max(min(w as usize, max_width));
+                        height = height.max(min(h as usize, max_height));
+                    }
+                }
+                b'#' => {
+                    let (args, next) = parse_numbers(data, i);
+                    i = next;
+                    let Some(&register) = args.first() else {
+                        continue;
+                    };
+                    let register = register as usize % NUM_COLOR_REGISTERS;
+                    if let [_, space, a, b, c, ..] = args[..] {
+                        palette[register] = match space {
+                            1 => rgb_from_hls(a, b, c),
+                            _ => rgb_from_percent(a, b, c),
+                        };
+                    }
+                    color = palette[register];
+                }
+                b'!' => {
+                    let (args, next) = parse_numbers(data, i);
+                    i = next;
+                    if let Some(&sixel) = data.get(i)
+                        && is_sixel(sixel)
+                    {
+                        i += 1;
+                        let count = args.first().copied().unwrap_or(1).max(1) as usize;
+                        for _ in 0..count {
+                            plot(&mut rows, x, y, sixel, color, max_width, max_height);
+                            x += 1;
+                        }
+                    }
+                }
+                b'$' => x = 0,
+                b'-' => {
+                    x = 0;
+                    y += 6;
+                }
+                sixel if is_sixel(sixel) => {
+                    plot(&mut rows, x, y, sixel, color, max_width, max_height);
+                    x += 1;
+                }
+                _ => (),
+            }
+        }
+
+        height = height.max(rows.len());
+        width = width.max(rows.iter().map(Vec::len).max().unwrap_or(0));
+        let background = if transparent { None } else { Some(palette[0]) };
+        let mut pixels = vec![background; width * height];
+        for (row, values) in rows.iter().enumerate() {
+            for (col, &value) in values.iter().enumerate() {
+                if value.is_some() {
+                    pixels[row * width + col] = value;
+                }
+            }
+        }
+        SixelImage {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// The number of rows of cells of `cell_size` that the image covers
+    pub fn rows(&self, cell_size: Size) -> usize {
+        self.height.div_ceil(cell_size.height.max(1) as usize)
+    }
+
+    /// Number of columns of cells that the image covers
+    pub fn columns(&self, cell_size: Size) -> usize {
+        self.width.div_ceil(cell_size.width.max(1) as usize)
+    }
+
+    /// Draw the image with its top left corner at `origin`
+    pub fn draw<D, P>(
+        &self,
+        origin: Point,
+        to_pixel: impl Fn(Rgb888) -> P,
+        display: &mut D,
+    ) -> Result<(), D::Error>
+    where
+        P: PixelColor,
+        D: DrawTarget<Color = P>,
+    {
+        let width = self.width.max(1);
+        display.draw_iter(self.pixels.iter().enumerate().filter_map(|(i, pixel)| {
+            pixel.map(|color| {
+                let point = Point::new((i % width) as i32, (i / width) as i32);
+                Pixel(origin + point, to_pixel(color))
+            })
+        }))
+    }
+}
+
+fn is_sixel(byte: u8) -> bool {
+    (0x3f..=0x7e).contains(&byte)
+}
+
+/// Set the pixels of the `sixel` whose top is at (`x`, `y`)
+fn plot(
+    rows: &mut Vec<Vec<Option<Rgb888>>>,
+    x: usize,
+    y: usize,
+    sixel: u8,
+    color: Rgb888,
+    max_width: usize,
+    max_height: usize,
+) {
+    let bits = sixel - 0x3f;
+    if x >= max_width {
+        return;
+    }
+    for bit in 0..6 {
+        let row = y + bit;
+        if bits & (1 << bit) == 0 || row >= max_height {
+            continue;
+        }
+        if rows.len() <= row {
+            rows.resize(row + 1, Vec::new());
+        }
+        if rows[row].len() <= x {
+            rows[row].resize(x + 1, None);
+        }
+        rows[row][x] = Some(color);
+    }
+}
+
+/// Parse `;` separated numbers starting at `start`, returning them and the index of the first byte after them
+fn parse_numbers(data: &[u8], start: usize) -> (Vec<u32>, usize) {
+    let mut numbers = Vec::new();
+    let mut current: Option<u32> = None;
+    let mut i = start;
+    while let Some(&byte) = data.get(i) {
+        match byte {
+            b'0'..=b'9' => {
+                let digit = (byte - b'0') as u32;
+                current = Some(
+                    current
+                        .unwrap_or(0)
+                        .saturating_mul(10)
+                        .saturating_add(digit),
+                );
+            }
+            b';' => numbers.push(current.take().unwrap_or(0)),
+            _ => break,
+        }
+        i += 1;
+    }
+    if let Some(n) = current {
+        numbers.push(n);
+    }
+    (numbers, i)
+}
+
+fn percent_to_8bit(percent: u32) -> u8 {
+    (percent.min(100) * 255 / 100) as u8
+}
+
+fn rgb_from_percent(r: u32, g: u32, b: u32) -> Rgb888 {
+    Rgb888::new(percent_to_8bit(r), percent_to_8bit(g), percent_to_8bit(b))
+}
+
+/// Sixel HLS, where a hue of 0 is blue, lightness and saturation are percentages
+fn rgb_from_hls(h: u32, l: u32, s: u32) -> Rgb888 {
+    let (l, s) = (l.min(100) as i32, s.min(100) as i32);
+    if s == 0 {
+        return rgb_from_percent(l as u32, l as u32, l as u32);
+    }
+    // Work in hundredths of a percent to avoid floats
+    let q = if l < 50 {
+        l * (100 + s)
+    } else {
+        (l + s) * 100 - l * s
+    };
+    let p = 2 * l * 100 - q;
+    let hue = ((h % 360 + 240) % 360) as i32;
+    let channel = |hue: i32| -> u32 {
+        let hue = hue.rem_euclid(360);
+        let value = if hue < 60 {
+            p + (q - p) * hue / 60
+        } else if hue < 180 {
+            q
+        } else if hue < 240 {
+            p + (q - p) * (240 - hue) / 60
+        } else {
+            p
+        };
+        (value / 100) as u32
+    };
+    rgb_from_percent(channel(hue + 120), channel(hue), channel(hue - 120))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_display::Framebuffer;
+    use crate::{Console, Style};
+    use core::fmt::Write;
+
+    #[test]
+    fn test_decode() {
+        // A 2x7 image: red over the top six rows, then a blue bottom row with a transparent pixel
+        let image = SixelImage::decode(&[0, 1], b"#1;2;100;0;0!2~-#2;2;0;0;100@?", 100, 100);
+        let red = Some(Rgb888::new(255, 0, 0));
+        let blue = Some(Rgb888::new(0, 0, 255));
+        assert_eq!((image.width, image.height), (2, 7));
+        assert_eq!(image.pixels[0], red);
+        assert_eq!(image.pixels[11], red);
+        assert_eq!(image.pixels[12], blue);
+        assert_eq!(image.pixels[13], None);
+    }
+
+    #[test]
+    fn test_decode_clipped() {
+        let image = SixelImage::decode(&[], b"\"1;1;4;2~~~~", 3, 2);
+        assert_eq!((image.width, image.height), (3, 2));
+    }
+
+    #[test]
+    fn test_hls() {
+        assert_eq!(rgb_from_hls(0, 50, 100), Rgb888::new(0, 0, 255));
+        assert_eq!(rgb_from_hls(120, 50, 100), Rgb888::new(255, 0, 0));
+        assert_eq!(rgb_from_hls(240, 50, 100), Rgb888::new(0, 255, 0));
+    }
+
+    #[test]
+    fn test_draw_sixel() {
+        let mut console = Console::new(10, 3, Style::default());
+        let (width, height) = console.image_dimensions();
+        let mut display = Framebuffer::new(Size::new(width, height), Rgb888::new(1, 2, 3));
+        console
+            .write_str("ab\x1bPq#1;2;100;0;0!2~-#2;2;0;0;100@?\x1b\\c")
+            .unwrap();
+        console.draw(&mut display).unwrap();
+
+        // The image is drawn at the third cell of the first row
+        let (x, y) = (2 * 9, 0);
+        assert_eq!(display.pixel(x, y), Rgb888::new(255, 0, 0));
+        assert_eq!(display.pixel(x + 1, y + 5), Rgb888::new(255, 0, 0));
+        assert_eq!(display.pixel(x, y + 6), Rgb888::new(0, 0, 255));
+        // Pixels that aren't set take the background color
+        assert_eq!(display.pixel(x + 1, y + 6), Rgb888::new(0, 0, 0));
+        // The cursor moves past the image
+        assert_eq!(console.get_cursor_position(), (1, 1));
+    }
+
+    #[test]
+    fn test_sixel_redrawn() {
+        let mut console = Console::new(10, 3, Style::default());
+        let (width, height) = console.image_dimensions();
+        let mut display = Framebuffer::new(Size::new(width, height), Rgb888::new(1, 2, 3));
+        console
+            .write_str("\r\n\x1bPq#1;2;100;0;0!2~\x1b\\")
+            .unwrap();
+        console.draw(&mut display).unwrap();
+        assert_eq!(display.pixel(0, 18), Rgb888::new(255, 0, 0));
+
+        // The image moves up with its row, and isn't erased by the cells drawn under it
+        console.write_str("\n").unwrap();
+        console.draw(&mut display).unwrap();
+        assert_eq!(display.pixel(0, 0), Rgb888::new(255, 0, 0));
+        assert_eq!(display.pixel(0, 18), Rgb888::new(0, 0, 0));
+        console.force_full_redraw();
+        console.draw(&mut display).unwrap();
+        assert_eq!(display.pixel(0, 0), Rgb888::new(255, 0, 0));
+
+        // Text written over the image replaces it
+        console.write_str("\x1b[Hx").unwrap();
+        console.force_full_redraw();
+        console.draw(&mut display).unwrap();
+        assert_ne!(display.pixel(0, 0), Rgb888::new(255, 0, 0));
+    }
+}
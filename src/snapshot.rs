@@ -0,0 +1,209 @@
+use alloc::vec::Vec;
+
+use crate::Console;
+use crate::cell::Cell;
+use crate::color::Color;
+use crate::style::{DrawCell, Style};
+
+use embedded_graphics::pixelcolor::RgbColor;
+
+/// A 64-bit FNV-1a hash, for [`Console::contents_hash`]
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Self {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    fn write_color(&mut self, color: Color) {
+        match color {
+            Color::Named(named) => self.write(&[0, named as u8]),
+            Color::RGB(rgb) => self.write(&[1, rgb.r(), rgb.g(), rgb.b()]),
+            Color::Indexed(index) => self.write(&[2, index]),
+        }
+    }
+}
+
+/// A copy of the cells of a [`Console`] at one point in time, taken with [`Console::snapshot`].
+///
+/// The changes between two snapshots can be applied to another console with [`Console::apply_diff_from_snapshot`], e.g. to mirror a remote terminal.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ConsoleSnapshot {
+    width: usize,
+    height: usize,
+    // Row-major, with `to_flush` cleared so that snapshots compare by content
+    cells: Vec<Cell>,
+}
+
+impl ConsoleSnapshot {
+    /// Number of columns
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Number of rows
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The cell at `(row, col)`, or `None` if it is out of bounds
+    pub fn get(&self, row: usize, col: usize) -> Option<Cell> {
+        if row >= self.height || col >= self.width {
+            return None;
+        }
+        Some(self.cells[row * self.width + col])
+    }
+
+    /// The `(row, col, cell)` of every cell of `self` that differs from `old`
+    fn diff<'s>(
+        &'s self,
+        old: &'s ConsoleSnapshot,
+    ) -> impl Iterator<Item = (usize, usize, Cell)> + 's {
+        self.cells.iter().enumerate().filter_map(move |(i, &cell)| {
+            let (row, col) = (i / self.width, i % self.width);
+            (old.get(row, col) != Some(cell)).then_some((row, col, cell))
+        })
+    }
+}
+
+impl<'a, C, F> Console<'a, C, F>
+where
+    Style<'a, C, F>: DrawCell<C>,
+{
+    /// Take a [`ConsoleSnapshot`] of the cells of the console
+    pub fn snapshot(&self) -> ConsoleSnapshot {
+        ConsoleSnapshot {
+            width: self.columns(),
+            height: self.rows(),
+            cells: self
+                .rows_iter()
+                .flatten()
+                .map(|&cell| Cell {
+                    to_flush: 0,
+                    ..cell
+                })
+                .collect(),
+        }
+    }
+
+    /// A hash of the characters and attributes of every cell, and of the dimensions and [line attributes](crate::LineAttr), e.g. to find out cheaply whether anything has changed since the last frame.
+    ///
+    /// This is a 64-bit FNV-1a hash, which is fast but not collision resistant. Whether cells still need to be drawn doesn't change the hash, and neither does the cursor.
+    pub fn contents_hash(&self) -> u64 {
+        let mut hash = Fnv1a::new();
+        hash.write(&(self.columns() as u64).to_le_bytes());
+        hash.write(&(self.rows() as u64).to_le_bytes());
+        for (row, cells) in self.rows_iter().enumerate() {
+            hash.write(&[self.line_attr(row) as u8]);
+            for cell in cells {
+                hash.write(&(cell.c as u32).to_le_bytes());
+                hash.write_color(cell.fg);
+                hash.write_color(cell.bg);
+                match cell.underline_color {
+                    Some(color) => hash.write_color(color),
+                    None => hash.write(&[3]),
+                }
+                hash.write(&cell.flags.bits().to_le_bytes());
+            }
+        }
+        hash.0
+    }
+
+    /// Write each `(row, col, cell)` of `diffs` to the console, to be drawn on the next call to [`Console::draw`].
+    ///
+    /// Positions that are out of bounds are skipped. The cursor and the current attributes are unchanged.
+    pub fn apply_diff_cells(&mut self, diffs: &[(usize, usize, Cell)]) {
+        for &(row, col, cell) in diffs {
+            self.write_dirty_cell(row, col, cell);
+        }
+    }
+
+    /// Write the cells that differ between the snapshots `old` and `new`, as with [`Console::apply_diff_cells`].
+    ///
+    /// Cells of `new` that are out of bounds of `old` are always written.
+    pub fn apply_diff_from_snapshot(&mut self, old: &ConsoleSnapshot, new: &ConsoleSnapshot) {
+        for (row, col, cell) in new.diff(old) {
+            self.write_dirty_cell(row, col, cell);
+        }
+    }
+
+    fn write_dirty_cell(&mut self, row: usize, col: usize, cell: Cell) {
+        let cell = Cell {
+            to_flush: cell.to_flush.max(1),
+            ..cell
+        };
+        self.inner.buf.write(row, col, cell);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cell::Flags;
+    use crate::color::{Color, NamedColor};
+    use crate::test_display::Framebuffer;
+    use core::fmt::Write;
+    use embedded_graphics::pixelcolor::Rgb888;
+    use embedded_graphics::prelude::*;
+
+    #[test]
+    fn test_apply_diff_cells() {
+        let mut console = Console::new(4, 2, Style::default());
+        let (width, height) = console.image_dimensions();
+        let mut display = Framebuffer::new(Size::new(width, height), Rgb888::BLACK);
+        console.write_str("ab").unwrap();
+        console.draw(&mut display).unwrap();
+
+        let red = Color::Named(NamedColor::Red);
+        let mut cell = Cell::new('x', red, red, Flags::empty());
+        cell.to_flush = 0;
+        console.apply_diff_cells(&[(1, 3, cell), (2, 0, cell), (0, 9, cell)]);
+
+        let rows = console.rows_iter().collect::<Vec<_>>();
+        assert_eq!((rows[1][3].c(), rows[1][3].fg()), ('x', red));
+        assert!(rows[1][3].is_dirty());
+        assert!(!rows[1][2].is_dirty());
+        assert_eq!(rows[0][0].c(), 'a');
+        assert_eq!(console.get_cursor_position(), (0, 2));
+    }
+
+    #[test]
+    fn test_apply_diff_from_snapshot() {
+        let mut source = Console::new(4, 2, Style::default());
+        source.write_str("abcd").unwrap();
+        let old = source.snapshot();
+        source.write_str("\x1b[1;2Hx\r\ny").unwrap();
+        let new = source.snapshot();
+        assert_eq!(new.diff(&old).count(), 2);
+
+        let mut mirror = Console::new(4, 2, Style::default());
+        mirror.write_str("abcd").unwrap();
+        mirror.apply_diff_from_snapshot(&old, &new);
+        assert_eq!(mirror.snapshot(), new);
+    }
+
+    #[test]
+    fn test_contents_hash() {
+        let mut console = Console::new(4, 2, Style::default());
+        let empty = console.contents_hash();
+        assert_eq!(Console::new(4, 2, Style::default()).contents_hash(), empty);
+        assert_ne!(Console::new(2, 4, Style::default()).contents_hash(), empty);
+
+        console.write_str("ab").unwrap();
+        let written = console.contents_hash();
+        assert_ne!(written, empty);
+        let mut display = Framebuffer::new(Size::new(36, 36), Rgb888::BLACK);
+        console.draw(&mut display).unwrap();
+        assert_eq!(console.contents_hash(), written);
+
+        // Attributes are part of the contents
+        console.write_str("\r\x1b[1mab").unwrap();
+        assert_ne!(console.contents_hash(), written);
+    }
+}
@@ -1,4 +1,5 @@
 use crate::cell::{Cell, Flags};
+use crate::cell_buffer::LineAttr;
 use crate::color::{Color, NamedColor};
 
 use embedded_graphics::mono_font::{
@@ -8,6 +9,7 @@ use embedded_graphics::mono_font::{
 use embedded_graphics::prelude::*;
 use embedded_graphics::{
     pixelcolor::{Rgb666, Rgb888},
+    primitives::Rectangle,
     text::{Baseline, Text, TextStyle},
 };
 
@@ -40,7 +42,18 @@ impl ColorInterpolate for Rgb888 {
 }
 
 /// Interpolate between two 8-bit values by the amount specified in the value. 0 is fully background color, 255 is fully foreground color.
+///
+/// This rounds to the nearest value, unless the `truncating-interpolation` feature is enabled, in which case it uses [`interpolate_8bit_values_truncated`].
+#[inline]
 pub fn interpolate_8bit_values(a: u8, b: u8, value: u8) -> u8 {
+    #[cfg(not(feature = "truncating-interpolation"))]
+    return interpolate_8bit_values_rounded(a, b, value);
+    #[cfg(feature = "truncating-interpolation")]
+    return interpolate_8bit_values_truncated(a, b, value);
+}
+
+/// Like [`interpolate_8bit_values`], rounding to the nearest value.
+pub fn interpolate_8bit_values_rounded(a: u8, b: u8, value: u8) -> u8 {
     let a = a as u16;
     let b = b as u16;
     let value = value as u16;
@@ -54,6 +67,23 @@ pub fn interpolate_8bit_values(a: u8, b: u8, value: u8) -> u8 {
     result as u8
 }
 
+/// Like [`interpolate_8bit_values`], rounding towards `a`.
+///
+/// This saves an addition per channel, for a result that is at most one step further from `b` than [`interpolate_8bit_values_rounded`]: one less when `a < b`, and one more when `a > b`.
+pub fn interpolate_8bit_values_truncated(a: u8, b: u8, value: u8) -> u8 {
+    let a = a as u16;
+    let b = b as u16;
+    let value = value as u16;
+
+    let result = if a > b {
+        a - (a - b) * value / 255
+    } else {
+        a + (b - a) * value / 255
+    };
+
+    result as u8
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -114,6 +144,162 @@ mod tests {
             "50% between bg:50 and fg:150"
         );
     }
+
+    #[test]
+    fn test_interpolation_strategies() {
+        // 3 * 128 / 255 is 1.5
+        assert_eq!(interpolate_8bit_values_rounded(0, 3, 128), 2);
+        assert_eq!(interpolate_8bit_values_truncated(0, 3, 128), 1);
+        assert_eq!(interpolate_8bit_values_rounded(3, 0, 128), 1);
+        assert_eq!(interpolate_8bit_values_truncated(3, 0, 128), 2);
+
+        for (a, b) in [(0, 255), (255, 0), (17, 200), (200, 17)] {
+            for value in 0..=255 {
+                let rounded = interpolate_8bit_values_rounded(a, b, value);
+                let truncated = interpolate_8bit_values_truncated(a, b, value);
+                if a < b {
+                    assert!(truncated <= rounded && rounded - truncated <= 1);
+                } else {
+                    assert!(truncated >= rounded && truncated - rounded <= 1);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_resolved_colors() {
+        use core::sync::atomic::{AtomicUsize, Ordering};
+        static CONVERSIONS: AtomicUsize = AtomicUsize::new(0);
+
+        let style = Style {
+            color_to_pixel: |color| {
+                CONVERSIONS.fetch_add(1, Ordering::Relaxed);
+                color_to_rgb(color)
+            },
+            ..Style::default()
+        };
+        let resolved = style.resolve_colors();
+        let cached = Style {
+            resolved_colors: Some(&resolved),
+            ..style
+        };
+        let colors = NamedColor::ALL
+            .map(Color::Named)
+            .into_iter()
+            .chain((0..=255).map(Color::Indexed))
+            .collect::<alloc::vec::Vec<_>>();
+        for &color in &colors {
+            assert_eq!(cached.color_to_pixel(color), style.color_to_pixel(color));
+        }
+
+        CONVERSIONS.store(0, Ordering::Relaxed);
+        for &color in &colors {
+            cached.color_to_pixel(color);
+        }
+        assert_eq!(CONVERSIONS.load(Ordering::Relaxed), 0);
+        let rgb = Color::RGB(Rgb888::new(1, 2, 3));
+        assert_eq!(cached.color_to_pixel(rgb), Rgb888::new(1, 2, 3));
+        assert_eq!(CONVERSIONS.load(Ordering::Relaxed), 1);
+
+        // The colors don't need to outlive the font
+        use crate::test_display::Framebuffer;
+        use core::fmt::Write;
+        let mut console = crate::Console::new(1, 1, cached);
+        console.set_overlay_mode(true);
+        console.write_str("\x1b[41m ").unwrap();
+        let mut display = Framebuffer::new(Size::new(9, 18), Rgb888::BLUE);
+        CONVERSIONS.store(0, Ordering::Relaxed);
+        console.draw(&mut display).unwrap();
+        assert_eq!(
+            display.pixel(0, 0),
+            color_to_rgb(Color::Named(NamedColor::Red))
+        );
+        assert_eq!(CONVERSIONS.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn test_color_to_rgb_with() {
+        let mut palette = [Rgb888::new(0, 0, 0); 256];
+        palette[1] = Rgb888::new(255, 0, 0);
+        palette[200] = Rgb888::new(0, 128, 128);
+        assert_eq!(
+            color_to_rgb_with(Color::Indexed(200), &palette),
+            palette[200]
+        );
+        assert_eq!(
+            color_to_rgb_with(Color::Named(NamedColor::Red), &palette),
+            palette[1]
+        );
+        let rgb = Rgb888::new(1, 2, 3);
+        assert_eq!(color_to_rgb_with(Color::RGB(rgb), &palette), rgb);
+        assert_eq!(
+            color_to_rgb(Color::Indexed(200)),
+            Rgb888::new(0xff, 0x00, 0xd7)
+        );
+    }
+
+    #[test]
+    fn test_nearest_named_color() {
+        let rgb = Color::RGB(Rgb888::new(200, 40, 40));
+        assert_eq!(nearest_named_color(rgb), NamedColor::Red);
+        assert_eq!(
+            nearest_named_color(Color::Indexed(196)),
+            NamedColor::BrightRed
+        );
+        assert_eq!(nearest_named_color(Color::Indexed(4)), NamedColor::Blue);
+        assert_eq!(nearest_named_color(Color::Indexed(232)), NamedColor::Black);
+
+        let style = Style {
+            snap_to_named_colors: true,
+            ..Style::default()
+        };
+        assert_eq!(style.color_to_pixel(rgb), Rgb888::new(194, 54, 33));
+        assert_eq!(
+            Style::default().color_to_pixel(rgb),
+            Rgb888::new(200, 40, 40)
+        );
+    }
+
+    #[test]
+    fn test_cell_origin() {
+        let style = Style {
+            offset: (3, 5),
+            ..Style::default()
+        };
+        for row in 0..4 {
+            for col in 0..4 {
+                assert_eq!(
+                    style.cell_origin(row, col),
+                    Point::new(col as i32 * 9 + 3, row as i32 * 18 + 5)
+                );
+            }
+        }
+        assert_eq!(Style::default().cell_origin(0, 0), Point::zero());
+    }
+
+    #[test]
+    fn test_draw_rotated() {
+        use crate::Console;
+        use crate::test_display::Framebuffer;
+        use core::fmt::Write;
+
+        let style = Style {
+            rotation: Rotation::Deg90,
+            ..Style::default()
+        };
+        let mut console = Console::new(3, 1, style);
+        console.write_str("\x1b[41m \x1b[m").unwrap();
+        assert_eq!(console.image_dimensions(), (18, 27));
+
+        let mut display = Framebuffer::new(Size::new(18, 27), Rgb888::new(1, 2, 3));
+        console.draw(&mut display).unwrap();
+        // The first 9x18 cell becomes the top 18x9 of the rotated display
+        let red = Rgb888::new(194, 54, 33);
+        assert_eq!(display.pixel(0, 0), red);
+        assert_eq!(display.pixel(17, 8), red);
+        assert_eq!(display.pixel(0, 9), Rgb888::new(0, 0, 0));
+        assert_eq!(display.pixel(17, 26), Rgb888::new(0, 0, 0));
+    }
 }
 
 //-----------------------------------------------------------
@@ -132,6 +318,9 @@ pub trait DrawCell<C> {
     where
         D: DrawTarget<Color = P>,
         P: PixelColor + From<C> + ColorInterpolate;
+
+    /// The size of a cell, in pixels
+    fn cell_size(&self) -> Size;
 }
 
 //-----------------------------------------------------------
@@ -152,6 +341,12 @@ pub struct Style<'a, C, F> {
     pub dim_color: fn(C) -> C,
     /// Pixel amount to offset all cells by
     pub offset: (u32, u32),
+    /// Rotation of the console on the display. The console is rotated about its top left corner, and then offset.
+    pub rotation: Rotation,
+    /// Whether RGB and indexed colors are replaced by the [nearest named color](nearest_named_color) before they are converted, for displays that can only show the 16 named colors
+    pub snap_to_named_colors: bool,
+    /// Named and indexed colors that have already been converted by `color_to_pixel`, to be looked up instead of calling it for every cell that is drawn
+    pub resolved_colors: Option<&'a ResolvedColors<C>>,
 }
 
 impl<'a, C, F> Style<'a, C, F> {
@@ -168,12 +363,46 @@ impl<'a, C, F> Style<'a, C, F> {
             color_to_pixel,
             dim_color,
             offset: (0, 0),
+            rotation: Rotation::Deg0,
+            snap_to_named_colors: false,
+            resolved_colors: None,
         }
     }
 
-    /// Call the `color_to_pixel` function.
-    pub fn color_to_pixel(&self, color: Color) -> C {
-        (self.color_to_pixel)(color)
+    /// Call the `color_to_pixel` function, with the nearest named color if `snap_to_named_colors` is set.
+    ///
+    /// Named and indexed colors are looked up in `resolved_colors` instead, if it is set.
+    pub fn color_to_pixel(&self, color: Color) -> C
+    where
+        C: Copy,
+    {
+        match (self.snapped(color), self.resolved_colors) {
+            (Color::Named(named), Some(resolved)) => resolved.named[named as usize],
+            (Color::Indexed(index), Some(resolved)) => resolved.indexed[index as usize],
+            (color, _) => (self.color_to_pixel)(color),
+        }
+    }
+
+    /// The nearest named color to `color` if `snap_to_named_colors` is set, or else `color`
+    pub(crate) fn snapped(&self, color: Color) -> Color {
+        match color {
+            Color::RGB(_) | Color::Indexed(_) if self.snap_to_named_colors => {
+                Color::Named(nearest_named_color(color))
+            }
+            color => color,
+        }
+    }
+
+    /// Convert every named and indexed color with `color_to_pixel`, so that they can be looked up while drawing by setting `resolved_colors`.
+    ///
+    /// This trades memory for drawing speed, e.g. 816 bytes for [`Rgb888`]. RGB colors are still converted as they are drawn.
+    pub fn resolve_colors(&self) -> ResolvedColors<C> {
+        ResolvedColors {
+            named: NamedColor::ALL.map(|named| (self.color_to_pixel)(Color::Named(named))),
+            indexed: core::array::from_fn(|index| {
+                (self.color_to_pixel)(Color::Indexed(index as u8))
+            }),
+        }
     }
 
     /// Call the `dim_color` function.
@@ -182,11 +411,171 @@ impl<'a, C, F> Style<'a, C, F> {
     }
 }
 
+impl<'a, C, F> Style<'a, C, F>
+where
+    Self: DrawCell<C>,
+{
+    /// The position of the top left corner of the cell at `row`, `col`, including the offset, before the console is rotated
+    pub fn cell_origin(&self, row: usize, col: usize) -> Point {
+        let size = self.cell_size();
+        Point::new(
+            col as i32 * size.width as i32 + self.offset.0 as i32,
+            row as i32 * size.height as i32 + self.offset.1 as i32,
+        )
+    }
+}
+
+/// The named and indexed colors converted by a [`Style`]'s `color_to_pixel` function, as made by [`Style::resolve_colors`]
+#[derive(Debug, Clone)]
+pub struct ResolvedColors<C> {
+    named: [C; 16],
+    indexed: [C; 256],
+}
+
+//-----------------------------------------------------------
+// MARK: Rotation
+//-----------------------------------------------------------
+
+/// A clockwise rotation of the [`Console`][crate::Console] on the display.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum Rotation {
+    /// Not rotated
+    #[default]
+    Deg0,
+    /// Rotated by 90 degrees, so that the top of the console is on the right of the display
+    Deg90,
+    /// Rotated by 180 degrees
+    Deg180,
+    /// Rotated by 270 degrees, so that the top of the console is on the left of the display
+    Deg270,
+}
+
+impl Rotation {
+    /// The size of an area of `size` after it is rotated
+    pub fn rotate_size(self, size: Size) -> Size {
+        match self {
+            Rotation::Deg0 | Rotation::Deg180 => size,
+            Rotation::Deg90 | Rotation::Deg270 => Size::new(size.height, size.width),
+        }
+    }
+}
+
+/// A [`DrawTarget`] that rotates everything drawn to it into an area of `size` at `origin`
+pub(crate) struct Rotated<'d, D> {
+    pub target: &'d mut D,
+    pub rotation: Rotation,
+    pub origin: Point,
+    /// The size of the area before rotation
+    pub size: Size,
+}
+
+impl<D: DrawTarget> Dimensions for Rotated<'_, D> {
+    fn bounding_box(&self) -> Rectangle {
+        Rectangle::new(self.origin, self.size)
+    }
+}
+
+impl<D: DrawTarget> DrawTarget for Rotated<'_, D> {
+    type Color = D::Color;
+    type Error = D::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let (width, height) = (self.size.width as i32, self.size.height as i32);
+        let (rotation, origin) = (self.rotation, self.origin);
+        self.target
+            .draw_iter(pixels.into_iter().map(|Pixel(point, color)| {
+                let Point { x, y } = point - origin;
+                let rotated = match rotation {
+                    Rotation::Deg0 => Point::new(x, y),
+                    Rotation::Deg90 => Point::new(height - 1 - y, x),
+                    Rotation::Deg180 => Point::new(width - 1 - x, height - 1 - y),
+                    Rotation::Deg270 => Point::new(y, width - 1 - x),
+                };
+                Pixel(rotated + origin, color)
+            }))
+    }
+}
+
+/// A [`DrawTarget`] that scales a cell drawn at row 0, column 0 into place on a row with a [`LineAttr`] other than [`LineAttr::Normal`]
+pub(crate) struct LineScaled<'d, D> {
+    target: &'d mut D,
+    // Where a cell at row 0, column 0 is drawn
+    source: Point,
+    // Where the scaled cell is drawn
+    origin: Point,
+    cell_size: Size,
+    line_attr: LineAttr,
+}
+
+impl<'d, D> LineScaled<'d, D> {
+    /// Scale a cell of `cell_size` drawn at `source` to be drawn at `origin`
+    pub fn new(
+        target: &'d mut D,
+        source: Point,
+        origin: Point,
+        cell_size: Size,
+        line_attr: LineAttr,
+    ) -> Self {
+        LineScaled {
+            target,
+            source,
+            origin,
+            cell_size,
+            line_attr,
+        }
+    }
+}
+
+impl<D: DrawTarget> Dimensions for LineScaled<'_, D> {
+    fn bounding_box(&self) -> Rectangle {
+        Rectangle::new(self.source, self.cell_size)
+    }
+}
+
+impl<D: DrawTarget> DrawTarget for LineScaled<'_, D> {
+    type Color = D::Color;
+    type Error = D::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let (width, height) = (self.cell_size.width as i32, self.cell_size.height as i32);
+        // Double height cells are drawn twice as tall, and moved up a row to show their bottom half
+        let (y_scale, y_shift) = match self.line_attr {
+            LineAttr::Normal | LineAttr::DoubleWidth => (1, 0),
+            LineAttr::DoubleHeightTop => (2, 0),
+            LineAttr::DoubleHeightBottom => (2, height),
+        };
+        let (source, origin) = (self.source, self.origin);
+        self.target.draw_iter(
+            pixels
+                .into_iter()
+                .filter_map(move |Pixel(point, color)| {
+                    let Point { x, y } = point - source;
+                    ((0..width).contains(&x) && (0..height).contains(&y))
+                        .then_some((Point::new(x * 2, y * y_scale - y_shift), color))
+                })
+                .flat_map(move |(scaled, color)| {
+                    (0..2 * y_scale).filter_map(move |i| {
+                        let point = scaled + Point::new(i % 2, i / 2);
+                        (0..height)
+                            .contains(&point.y)
+                            .then_some(Pixel(point + origin, color))
+                    })
+                }),
+        )
+    }
+}
+
 //-----------------------------------------------------------
 // MARK: MonoFont DrawCell implementation
 //-----------------------------------------------------------
 
-impl<C> DrawCell<C> for Style<'static, C, MonoFont<'static>> {
+impl<'a, 'f, C: Copy> DrawCell<C> for Style<'a, C, MonoFont<'f>> {
     fn draw_cell<D, P: PixelColor + From<C>>(
         &self,
         cell: &Cell,
@@ -223,20 +612,24 @@ impl<C> DrawCell<C> for Style<'static, C, MonoFont<'static>> {
             style = style.strikethrough();
         }
         if cell.flags.contains(Flags::UNDERLINE) {
-            style = style.underline();
+            style = match cell.underline_color {
+                Some(color) => style.underline_with_color(P::from(self.color_to_pixel(color))),
+                None => style.underline(),
+            };
         }
         let text = Text::with_text_style(
             s,
-            Point::new(
-                col as i32 * self.font.character_size.width as i32 + self.offset.0 as i32,
-                row as i32 * self.font.character_size.height as i32 + self.offset.1 as i32,
-            ),
+            self.cell_origin(row, col),
             style.build(),
             TextStyle::with_baseline(Baseline::Top),
         );
         text.draw(display)?;
         Ok(())
     }
+
+    fn cell_size(&self) -> Size {
+        self.font.character_size
+    }
 }
 
 //-----------------------------------------------------------
@@ -250,6 +643,9 @@ impl Default for Style<'static, Rgb888, MonoFont<'static>> {
             color_to_pixel: |color| color_to_rgb(color),
             dim_color: |color| dim_rgb(color),
             offset: (0, 0),
+            rotation: Rotation::Deg0,
+            snap_to_named_colors: false,
+            resolved_colors: None,
         }
     }
 }
@@ -264,13 +660,43 @@ pub fn dim_rgb(color: Rgb888) -> Rgb888 {
 ///
 /// You can create your own styles with your own color mapping.
 pub fn color_to_rgb(color: Color) -> Rgb888 {
+    color_to_rgb_with(color, &COLOR_MAP)
+}
+
+/// Convert a [`Color`] to [`Rgb888`], looking up named and indexed colors in `palette` instead of the default colors.
+///
+/// Named colors are the first 16 entries of `palette`, in the order of [`NamedColor::ALL`].
+pub fn color_to_rgb_with(color: Color, palette: &[Rgb888; 256]) -> Rgb888 {
     match color {
         Color::RGB(rgb) => rgb,
-        Color::Named(name) => COLOR_MAP[name as usize],
-        Color::Indexed(idx) => COLOR_MAP[idx as usize],
+        Color::Named(name) => palette[name as usize],
+        Color::Indexed(idx) => palette[idx as usize],
     }
 }
 
+/// The named color that is closest to `color`, by the Euclidean distance between their RGB values as given by [`color_to_rgb`].
+pub fn nearest_named_color(color: Color) -> NamedColor {
+    if let Color::Named(name) = color {
+        return name;
+    }
+    let rgb = color_to_rgb(color);
+    let distance = |name: &NamedColor| {
+        let named = COLOR_MAP[*name as usize];
+        [
+            (rgb.r(), named.r()),
+            (rgb.g(), named.g()),
+            (rgb.b(), named.b()),
+        ]
+        .iter()
+        .map(|&(a, b)| (a.abs_diff(b) as u32).pow(2))
+        .sum::<u32>()
+    };
+    NamedColor::ALL
+        .into_iter()
+        .min_by_key(distance)
+        .unwrap_or(NamedColor::Black)
+}
+
 lazy_static::lazy_static! {
     /// Array of indexed colors.
     ///
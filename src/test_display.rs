@@ -0,0 +1,106 @@
+//! Draw targets for tests
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::convert::Infallible;
+
+use embedded_graphics::pixelcolor::Rgb888;
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::Rectangle;
+
+use crate::cell::Cell;
+
+/// An in-memory display that records every pixel drawn to it
+pub(crate) struct Framebuffer {
+    pixels: Vec<Rgb888>,
+    size: Size,
+}
+
+impl Framebuffer {
+    /// A display of `size` filled with `color`
+    pub(crate) fn new(size: Size, color: Rgb888) -> Self {
+        Self {
+            pixels: vec![color; (size.width * size.height) as usize],
+            size,
+        }
+    }
+
+    pub(crate) fn pixel(&self, x: u32, y: u32) -> Rgb888 {
+        self.pixels[(y * self.size.width + x) as usize]
+    }
+}
+
+impl OriginDimensions for Framebuffer {
+    fn size(&self) -> Size {
+        self.size
+    }
+}
+
+impl DrawTarget for Framebuffer {
+    type Color = Rgb888;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            if self.bounding_box().contains(point) {
+                let i = point.y as u32 * self.size.width + point.x as u32;
+                self.pixels[i as usize] = color;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A display that discards everything drawn to it
+pub(crate) struct NullDisplay;
+
+impl Dimensions for NullDisplay {
+    fn bounding_box(&self) -> Rectangle {
+        Rectangle::new(Point::zero(), Size::new(800, 600))
+    }
+}
+
+impl DrawTarget for NullDisplay {
+    type Color = Rgb888;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, _pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        Ok(())
+    }
+}
+
+/// A display that counts the pixels drawn to it
+#[derive(Default)]
+pub(crate) struct CountingDisplay {
+    pub(crate) pixels: usize,
+}
+
+impl Dimensions for CountingDisplay {
+    fn bounding_box(&self) -> Rectangle {
+        Rectangle::new(Point::zero(), Size::new(800, 600))
+    }
+}
+
+impl DrawTarget for CountingDisplay {
+    type Color = Rgb888;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        self.pixels += pixels.into_iter().count();
+        Ok(())
+    }
+}
+
+/// The characters of a row of cells
+pub(crate) fn row_text(cells: &[Cell]) -> String {
+    cells.iter().map(Cell::c).collect()
+}
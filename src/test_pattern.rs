@@ -0,0 +1,205 @@
+use core::fmt::Write;
+
+use crate::Console;
+use crate::style::{DrawCell, Style};
+
+/// SGR attributes, and their names
+const ATTRIBUTES: [(u8, &str); 8] = [
+    (1, "bold"),
+    (2, "dim"),
+    (3, "italic"),
+    (4, "underline"),
+    (5, "blink"),
+    (7, "inverse"),
+    (8, "hidden"),
+    (9, "strike"),
+];
+
+/// SGR parameters shown by [`Console::write_ansi_sgr_test_chart`], and their names
+const SGR_CHART: [(&str, &str); 11] = [
+    ("0", "plain"),
+    ("1", "bold"),
+    ("3", "italic"),
+    ("4", "underline"),
+    ("9", "strikethrough"),
+    ("7", "inverse"),
+    ("2", "dim"),
+    ("8", "hidden"),
+    ("4:2", "double underline"),
+    ("53", "overline"),
+    ("5", "blink"),
+];
+
+/// Labels, each followed by the control sequences that it describes, which write `*` where the cursor is moved to
+const STEPS: [(&str, &str); 9] = [
+    ("CUP 5;20: ", "\x1b[5;20H*\r\n"),
+    ("CUF/CUB/CUU/CUD: ", "\x1b[3C*\x1b[B\x1b[A\x1b[3D*\r\n"),
+    ("DECSC/DECRC: ", "\x1b7\x1b[H*\x1b8*\r\n"),
+    ("EL 0: ", "cleared\x1b[7D\x1b[K*\r\n"),
+    ("EL 1: ", "\x1b[1K*\r\n"),
+    ("HT: ", "\tA\tB\tC\r\n"),
+    ("CHT/CBT: ", "\x1b[3IA\x1b[2ZB\r\n"),
+    ("DSR: ", "\x1b[5n\x1b[6n*\r\n"),
+    ("ED 0: ", "\x1b[J*\r\n"),
+];
+
+impl<'a, C, F> Console<'a, C, F>
+where
+    Style<'a, C, F>: DrawCell<C>,
+{
+    /// Clear the screen and write a pattern that exercises the control sequences that the console supports, each labelled with their name.
+    ///
+    /// The pattern shows the 16 named colors as foreground and background, the SGR attributes, cursor movement, line and screen clearing, tab stops, device status reports and scrolling. It is written from the top left corner, and needs at least 80 columns and 24 rows to be seen in full. Reports are queued as usual, to be read with [`Console::pop_report`].
+    pub fn write_vt100_test_pattern(&mut self) {
+        let _ = self.write_str("\x1b[0m\x1b[2J\x1b[H*VT100 test pattern\r\n");
+
+        self.write_named_color_rows();
+        let _ = self.write_str("\r\nSGR: ");
+        for (attribute, name) in ATTRIBUTES {
+            let _ = write!(self, "\x1b[{}m{}\x1b[0m ", attribute, name);
+        }
+        let _ = self.write_str("\r\n");
+
+        for (label, sequence) in STEPS {
+            let _ = self.write_str(label);
+            let _ = self.write_str(sequence);
+        }
+
+        // Scroll the title off the top of the screen
+        let _ = self.write_str("\x1b[999;1HScrolled\r\nIND: *");
+    }
+
+    /// Clear the screen and write a chart of the SGR attributes, one per row labelled with its parameter and name, followed by the 16 named colors as foreground and background.
+    ///
+    /// Double underline is written as `4:2`, since `21` cancels bold. Attributes that the console doesn't support, such as double underline and overline, are shown as plain text. The chart is written from the top left corner, and needs at least 48 columns and 14 rows to be seen in full.
+    pub fn write_ansi_sgr_test_chart(&mut self) {
+        let _ = self.write_str("\x1b[0m\x1b[2J\x1b[HSGR test chart\r\n");
+        for (parameter, name) in SGR_CHART {
+            let _ = write!(
+                self,
+                "{:<4}{:<17}\x1b[{}mThe quick brown fox\x1b[0m\r\n",
+                parameter, name, parameter
+            );
+        }
+        self.write_named_color_rows();
+    }
+
+    /// Write the 16 named colors as foreground on one row, and as background on the next, each labelled with its number, leaving the cursor at the end of the second row
+    fn write_named_color_rows(&mut self) {
+        let _ = self.write_str("FG: ");
+        for color in 0..8 {
+            let _ = write!(
+                self,
+                "\x1b[3{}m{} \x1b[9{}m{} ",
+                color,
+                color,
+                color,
+                color + 8
+            );
+        }
+        let _ = self.write_str("\x1b[0m\r\nBG: ");
+        for color in 0..8 {
+            let _ = write!(
+                self,
+                "\x1b[4{}m{} \x1b[10{}m{} ",
+                color,
+                color,
+                color,
+                color + 8
+            );
+        }
+        let _ = self.write_str("\x1b[0m");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cell::Flags;
+    use crate::color::{Color, NamedColor};
+    use crate::test_display::row_text;
+    use alloc::string::String;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_vt100_test_pattern() {
+        let mut console = Console::new(80, 24, Style::default());
+        console.write_str("garbage").unwrap();
+        console.write_vt100_test_pattern();
+
+        let rows = console
+            .rows_iter()
+            .map(|row| String::from(row_text(row).trim_end()))
+            .collect::<Vec<_>>();
+        // The title was scrolled off
+        assert_eq!(rows[0], "FG: 0 8 1 9 2 10 3 11 4 12 5 13 6 14 7 15");
+        assert_eq!(
+            rows[3..12],
+            [
+                "CUP 5;20:          *",
+                "CUF/CUB/CUU/CUD:  * *",
+                "DECSC/DECRC: *",
+                "EL 0: *",
+                "      *",
+                "HT:     A       B       C",
+                "CHT/CBT:                B       A",
+                "DSR: *",
+                "ED 0: *",
+            ]
+        );
+        assert_eq!(rows[12..22].concat(), "");
+        assert_eq!(rows[22..], ["Scrolled", "IND: *"]);
+
+        let second = console.rows_iter().nth(1).unwrap();
+        assert_eq!(second[4].bg(), Color::Named(NamedColor::Black));
+        assert_eq!(second[6].bg(), Color::Named(NamedColor::BrightBlack));
+        let sgr = console.rows_iter().nth(2).unwrap();
+        assert_eq!(sgr[5].flags(), Flags::BOLD);
+
+        let reports = (0..)
+            .map_while(|_| console.pop_report())
+            .collect::<Vec<_>>();
+        assert_eq!(reports, b"\x1b[0n\x1b[12;6R");
+    }
+
+    #[test]
+    fn test_ansi_sgr_test_chart() {
+        let mut console = Console::new(48, 14, Style::default());
+        console.write_str("\x1b[1mgarbage").unwrap();
+        console.write_ansi_sgr_test_chart();
+
+        let rows = console
+            .rows_iter()
+            .map(|row| String::from(row_text(row).trim_end()))
+            .collect::<Vec<_>>();
+        assert_eq!(rows[0], "SGR test chart");
+        assert_eq!(rows[1], "0   plain            The quick brown fox");
+        assert_eq!(rows[9], "4:2 double underline The quick brown fox");
+        assert_eq!(rows[12], "FG: 0 8 1 9 2 10 3 11 4 12 5 13 6 14 7 15");
+
+        let flags = console
+            .rows_iter()
+            .skip(1)
+            .take(SGR_CHART.len())
+            .map(|row| (row[0].flags(), row[21].flags()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            flags,
+            [
+                (Flags::empty(), Flags::empty()),
+                (Flags::empty(), Flags::BOLD),
+                (Flags::empty(), Flags::ITALIC),
+                (Flags::empty(), Flags::UNDERLINE),
+                (Flags::empty(), Flags::STRIKEOUT),
+                (Flags::empty(), Flags::INVERSE),
+                (Flags::empty(), Flags::DIM),
+                (Flags::empty(), Flags::HIDDEN),
+                (Flags::empty(), Flags::empty()),
+                (Flags::empty(), Flags::empty()),
+                (Flags::empty(), Flags::empty()),
+            ]
+        );
+        let bg = console.rows_iter().nth(13).unwrap();
+        assert_eq!(bg[6].bg(), Color::Named(NamedColor::BrightBlack));
+    }
+}
@@ -1,5 +1,8 @@
 use embedded_graphics::{
-    mono_font::mapping::{GlyphMapping, StrGlyphMapping},
+    mono_font::{
+        DecorationDimensions,
+        mapping::{GlyphMapping, StrGlyphMapping},
+    },
     prelude::*,
     primitives::Rectangle,
     text::{
@@ -7,23 +10,32 @@ use embedded_graphics::{
         renderer::{CharacterStyle, TextMetrics, TextRenderer},
     },
 };
+#[cfg(feature = "fontdue")]
 use fontdue::Font;
 
 // Used by environments without ceil/round functions
 #[allow(unused_imports)]
 use micromath::F32Ext;
 
+use alloc::borrow::Cow;
+use alloc::string::String;
 use alloc::vec::Vec;
 
 use crate::cell::{Cell, Flags};
 use crate::style::{ColorInterpolate, DrawCell, Style};
 
-/// An alternative to [`embedded_graphics::mono_font::MonoFont`] that uses [`fontdue`] to render text.
+/// An alternative to [`embedded_graphics::mono_font::MonoFont`] that draws glyphs with 8-bit coverage values, so that their edges are blended with the background.
+///
+/// With the `fontdue` feature, it is rasterized from a font file with [`fontdue`].
 pub struct Mono8BitFont {
     rasterized: Vec<u8>,
     character_size: Size,
-    glyph_mapping: StrGlyphMapping<'static>,
+    // The glyphs in the order they are rasterized, as for `StrGlyphMapping`, and the index of the one drawn for other characters
+    glyphs: Cow<'static, str>,
+    replacement: usize,
     baseline: u32,
+    underline: DecorationDimensions,
+    strikethrough: DecorationDimensions,
     glyph_bytes: usize,
 }
 
@@ -36,11 +48,149 @@ impl Mono8BitFont {
         self.character_size
     }
 
+    /// The number of bytes held by the rasterized glyphs, e.g. for budgeting RAM when choosing glyphs and scales. Every glyph is rasterized when the font is created, so this doesn't change.
+    pub fn memory_usage(&self) -> usize {
+        self.rasterized.len()
+    }
+
+    /// The mapping from characters to the index of their glyph
+    fn glyph_mapping(&self) -> StrGlyphMapping<'_> {
+        StrGlyphMapping::new(&self.glyphs, self.replacement)
+    }
+
+    /// The rasterized coverage values of the glyph used for `c`
+    fn glyph(&self, c: char) -> &[u8] {
+        let glyph = self.glyph_mapping().index(c);
+        &self.rasterized[glyph * self.glyph_bytes..(glyph + 1) * self.glyph_bytes]
+    }
+
+    /// The rasterized coverage values of the glyph for `c`, in rows (0 is background, 255 is foreground), and the size of the glyph, e.g. for drawing custom effects.
+    ///
+    /// Returns `None` if `c` is not one of the glyphs of the font.
+    pub fn glyph_coverage(&self, c: char) -> Option<(&[u8], Size)> {
+        self.glyph_mapping()
+            .contains(c)
+            .then(|| (self.glyph(c), self.character_size))
+    }
+
+    /// The rasterized glyph used for `c`, as rows of coverage values (0 is background, 255 is foreground).
+    pub fn render_char_preview(&self, c: char) -> Vec<Vec<u8>> {
+        self.glyph(c)
+            .chunks(self.character_size.width as usize)
+            .map(|row| row.to_vec())
+            .collect()
+    }
+
+    /// Log the rasterized glyph used for `c` as ASCII art, one row per line.
+    pub fn print_char_ascii_art(&self, c: char) {
+        const RAMP: &[u8] = b".:-=+*#";
+        debug!("Glyph {:?}:", c);
+        for row in self.glyph(c).chunks(self.character_size.width as usize) {
+            let line: String = row
+                .iter()
+                .map(|&value| RAMP[value as usize * (RAMP.len() - 1) / 255] as char)
+                .collect();
+            debug!("{}", line);
+        }
+    }
+
+    /// Create a font from the coverage values of each of the `glyphs` in turn, `character_size` pixels each, with the glyph at index `replacement` drawn for other characters, and the baseline `baseline` pixels below the top of the characters
+    #[cfg(feature = "tinybmp")]
+    pub(crate) fn from_rasterized(
+        rasterized: Vec<u8>,
+        character_size: Size,
+        glyphs: Cow<'static, str>,
+        replacement: usize,
+        baseline: u32,
+    ) -> Self {
+        let height = character_size.height;
+        Self {
+            rasterized,
+            character_size,
+            glyphs,
+            replacement,
+            baseline,
+            underline: DecorationDimensions::new((baseline + 1).min(height.saturating_sub(1)), 1),
+            strikethrough: DecorationDimensions::new(baseline.saturating_sub(height / 4), 1),
+            glyph_bytes: (character_size.width * height) as usize,
+        }
+    }
+
     /// Create a new [`Mono8BitFont`] from the bytes of a font file, a scale (font size), and the list of glyphs to include.
     ///
     /// The list of glyphs should be a string of characters that are present in the font file. Ranges can be represented by `"\0<character>-<character>"`, i.e. `"\0a-z"` will include all lowercase letters.
+    #[cfg(feature = "fontdue")]
     pub fn from_font_bytes(bytes: &[u8], scale: f32, glyphs: &'static str) -> Self {
-        let glyph_mapping = StrGlyphMapping::new(glyphs, '?' as usize - ' ' as usize);
+        Self::rasterize(
+            bytes,
+            scale,
+            Cow::Borrowed(glyphs),
+            '?' as usize - ' ' as usize,
+        )
+    }
+
+    /// Create a new [`Mono8BitFont`] from the bytes of a font file, a scale (font size), and inclusive ranges of the glyphs to include, e.g. `&[('\u{20}', '\u{7e}'), ('\u{2500}', '\u{257f}')]` for ASCII and box drawing characters.
+    ///
+    /// The `?` glyph is drawn for characters that aren't in a range, or the first glyph if there is no `?`. The list of glyphs that is built from the ranges is owned by the font, and freed with it.
+    #[cfg(feature = "fontdue")]
+    pub fn from_font_ranges(bytes: &[u8], scale: f32, ranges: &[(char, char)]) -> Self {
+        let mut glyphs = String::new();
+        for &(first, last) in ranges {
+            glyphs.push('\0');
+            glyphs.push(first);
+            glyphs.push(last);
+        }
+        let replacement = StrGlyphMapping::new(&glyphs, 0)
+            .chars()
+            .position(|c| c == '?')
+            .unwrap_or(0);
+        Self::rasterize(bytes, scale, Cow::Owned(glyphs), replacement)
+    }
+
+    /// Create a new [`Mono8BitFont`] from the bytes of a font file and a list of glyphs, as with [`Mono8BitFont::from_font_bytes`], at the largest scale (font size) at which a grid of `columns` by `rows` characters fits in `display_size`, e.g. 80 by 24 characters on an 800x480 display.
+    ///
+    /// The scale is found to within a hundredth of a point. If the grid doesn't fit even at a scale of 1, the font is created at that scale.
+    #[cfg(feature = "fontdue")]
+    pub fn fit(
+        bytes: &[u8],
+        columns: u32,
+        rows: u32,
+        display_size: Size,
+        glyphs: &'static str,
+    ) -> Self {
+        let font = Font::from_bytes(bytes, fontdue::FontSettings::default()).unwrap();
+        let fits = |scale: f32| {
+            let size = Self::cell_size_at(&font, scale);
+            size.width.saturating_mul(columns) <= display_size.width
+                && size.height.saturating_mul(rows) <= display_size.height
+        };
+        // Characters are never taller than the display
+        let (mut low, mut high) = (1.0, display_size.height.max(1) as f32 + 1.0);
+        while high - low > 0.01 {
+            let scale = (low + high) / 2.0;
+            if fits(scale) {
+                low = scale;
+            } else {
+                high = scale;
+            }
+        }
+        Self::from_font_bytes(bytes, low, glyphs)
+    }
+
+    /// The size of the cells of `font` at a scale (font size)
+    #[cfg(feature = "fontdue")]
+    fn cell_size_at(font: &Font, scale: f32) -> Size {
+        let line_metrics = font.horizontal_line_metrics(scale).unwrap();
+        Size::new(
+            font.metrics(' ', scale).advance_width.ceil() as u32,
+            line_metrics.new_line_size.ceil() as u32,
+        )
+    }
+
+    /// Rasterize each of the `glyphs`, as for [`StrGlyphMapping`], from the bytes of a font file at a scale (font size), drawing the glyph at index `replacement` for other characters
+    #[cfg(feature = "fontdue")]
+    fn rasterize(bytes: &[u8], scale: f32, glyphs: Cow<'static, str>, replacement: usize) -> Self {
+        let glyph_mapping = StrGlyphMapping::new(&glyphs, replacement);
         let font = Font::from_bytes(
             bytes,
             fontdue::FontSettings {
@@ -50,9 +200,8 @@ impl Mono8BitFont {
         )
         .unwrap();
         let horizontal_line_metrics = font.horizontal_line_metrics(scale).unwrap();
-        let metrics = font.metrics(' ', scale);
-        let fixed_width = metrics.advance_width.ceil() as usize;
-        let fixed_height = horizontal_line_metrics.new_line_size.ceil() as usize;
+        let size = Self::cell_size_at(&font, scale);
+        let (fixed_width, fixed_height) = (size.width as usize, size.height as usize);
         let baseline = horizontal_line_metrics.ascent.round() as i32;
         debug!(
             "Creating font with line metrics: {:?}; ",
@@ -60,6 +209,14 @@ impl Mono8BitFont {
         );
         let glyph_bytes = fixed_width * fixed_height;
 
+        // Underline just below the baseline, strikethrough through the middle of the lowercase letters
+        let underline = DecorationDimensions::new(
+            (baseline as u32 + 1).min(fixed_height.saturating_sub(1) as u32),
+            1,
+        );
+        let x_height = font.metrics('x', scale).height as i32;
+        let strikethrough = DecorationDimensions::new((baseline - x_height / 2).max(0) as u32, 1);
+
         // Rasterize all glyphs
         let mut rasterized = Vec::with_capacity(glyph_bytes * glyph_mapping.chars().count());
         for c in glyph_mapping.chars() {
@@ -109,8 +266,11 @@ impl Mono8BitFont {
         Self {
             rasterized,
             character_size: Size::new(fixed_width as u32, fixed_height as u32),
-            glyph_mapping,
+            glyphs,
+            replacement,
             baseline: baseline as u32,
+            underline,
+            strikethrough,
             glyph_bytes,
         }
     }
@@ -124,6 +284,8 @@ pub struct Mono8BitTextStyle<'a, C: PixelColor> {
     background_color: C,
     underline_color: DecorationColor<C>,
     strikethrough_color: DecorationColor<C>,
+    // Color and offset of the shadow of each glyph
+    shadow: Option<(C, Point)>,
 }
 
 impl<'a, C: PixelColor> Mono8BitTextStyle<'a, C> {
@@ -135,9 +297,18 @@ impl<'a, C: PixelColor> Mono8BitTextStyle<'a, C> {
             background_color,
             underline_color: DecorationColor::None,
             strikethrough_color: DecorationColor::None,
+            shadow: None,
         }
     }
 
+    /// Draw a shadow of each glyph in `color` under it, moved by `offset` pixels, e.g. `Point::new(1, 1)` for a shadow below and to the right.
+    ///
+    /// The shadow is blended with the background like the glyph is, and is cut off at the edges of the character.
+    pub fn with_shadow(mut self, color: C, offset: Point) -> Self {
+        self.shadow = Some((color, offset));
+        self
+    }
+
     /// Returns the vertical offset between the line position and the top edge of the bounding box.
     fn baseline_offset(&self, baseline: Baseline) -> i32 {
         match baseline {
@@ -150,27 +321,46 @@ impl<'a, C: PixelColor> Mono8BitTextStyle<'a, C> {
 
     fn draw_decorations<D>(
         &self,
-        _width: u32,
-        _position: Point,
-        _target: &mut D,
+        width: u32,
+        position: Point,
+        target: &mut D,
     ) -> Result<(), D::Error>
     where
         D: DrawTarget<Color = C>,
     {
-        // if let Some(color) = self.strikethrough_color.to_color(self.text_color) {
-        //     let rect = self.font.strikethrough.to_rectangle(position, width);
-        //     target.fill_solid(&rect, color)?;
-        // }
+        if let Some(color) = decoration_color(self.strikethrough_color, self.text_color) {
+            let rect = decoration_rectangle(&self.font.strikethrough, position, width);
+            target.fill_solid(&rect, color)?;
+        }
 
-        // if let Some(color) = self.underline_color.to_color(self.text_color) {
-        //     let rect = self.font.underline.to_rectangle(position, width);
-        //     target.fill_solid(&rect, color)?;
-        // }
+        if let Some(color) = decoration_color(self.underline_color, self.text_color) {
+            let rect = decoration_rectangle(&self.font.underline, position, width);
+            target.fill_solid(&rect, color)?;
+        }
 
         Ok(())
     }
 }
 
+fn decoration_color<C: PixelColor>(decoration: DecorationColor<C>, text_color: C) -> Option<C> {
+    match decoration {
+        DecorationColor::None => None,
+        DecorationColor::TextColor => Some(text_color),
+        DecorationColor::Custom(color) => Some(color),
+    }
+}
+
+fn decoration_rectangle(
+    decoration: &DecorationDimensions,
+    position: Point,
+    width: u32,
+) -> Rectangle {
+    Rectangle::new(
+        position + Size::new(0, decoration.offset),
+        Size::new(width, decoration.height),
+    )
+}
+
 impl<C: PixelColor + ColorInterpolate> TextRenderer for Mono8BitTextStyle<'_, C> {
     type Color = C;
 
@@ -186,27 +376,40 @@ impl<C: PixelColor + ColorInterpolate> TextRenderer for Mono8BitTextStyle<'_, C>
     {
         let mut next_position = position - Point::new(0, self.baseline_offset(baseline));
 
+        let size = self.font.character_size;
         for c in text.chars() {
-            let glyph = self.font.glyph_mapping.index(c);
-            let bitmap = &self.font.rasterized
-                [glyph * self.font.glyph_bytes..(glyph + 1) * self.font.glyph_bytes];
-            target.draw_iter(
-                bitmap
-                    .chunks(self.font.character_size.width as usize)
-                    .enumerate()
-                    .flat_map(|(row, values)| {
-                        values.iter().enumerate().map(move |(col, value)| {
-                            let pos = next_position + Point::new(col as i32, row as i32);
-                            let color = match value {
+            let bitmap = self.font.glyph(c);
+            // The coverage of the glyph at `point` within the character
+            let coverage = |point: Point| {
+                if point.x < 0 || point.y < 0 || point.x >= size.width as i32 {
+                    return 0;
+                }
+                let i = point.y as usize * size.width as usize + point.x as usize;
+                bitmap.get(i).copied().unwrap_or(0)
+            };
+            target.draw_iter(bitmap.chunks(size.width as usize).enumerate().flat_map(
+                |(row, values)| {
+                    values.iter().enumerate().map(move |(col, value)| {
+                        let point = Point::new(col as i32, row as i32);
+                        let background = match self.shadow {
+                            Some((shadow_color, offset)) => match coverage(point - offset) {
                                 0 => self.background_color,
-                                255 => self.text_color,
-                                _ => C::interpolate(self.text_color, self.background_color, *value),
-                            };
-
-                            Pixel(pos, color)
-                        })
-                    }),
-            )?;
+                                shadow => {
+                                    C::interpolate(shadow_color, self.background_color, shadow)
+                                }
+                            },
+                            None => self.background_color,
+                        };
+                        let color = match value {
+                            0 => background,
+                            255 => self.text_color,
+                            _ => C::interpolate(self.text_color, background, *value),
+                        };
+
+                        Pixel(next_position + point, color)
+                    })
+                },
+            ))?;
 
             next_position += Size::new(self.font.character_size.width, 0)
         }
@@ -214,7 +417,11 @@ impl<C: PixelColor + ColorInterpolate> TextRenderer for Mono8BitTextStyle<'_, C>
         if next_position.x > position.x {
             let width = (next_position.x - position.x) as u32;
 
-            self.draw_decorations(width, position, target)?;
+            self.draw_decorations(
+                width,
+                position - Point::new(0, self.baseline_offset(baseline)),
+                target,
+            )?;
         }
 
         Ok(next_position + Point::new(0, self.baseline_offset(baseline)))
@@ -248,8 +455,8 @@ impl<C: PixelColor + ColorInterpolate> TextRenderer for Mono8BitTextStyle<'_, C>
         let bb_width = text.chars().count() as u32 * (self.font.character_size.width);
 
         let bb_height = if self.underline_color != DecorationColor::None {
-            // self.font.underline.height + self.font.underline.offset
-            0
+            (self.font.underline.height + self.font.underline.offset)
+                .max(self.font.character_size.height)
         } else {
             self.font.character_size.height
         };
@@ -291,7 +498,7 @@ impl<C: Clone + PixelColor> CharacterStyle for Mono8BitTextStyle<'_, C> {
     }
 }
 
-impl<'a, C> DrawCell<C> for Style<'a, C, Mono8BitFont> {
+impl<'a, C: Copy> DrawCell<C> for Style<'a, C, Mono8BitFont> {
     fn draw_cell<D, P>(
         &self,
         cell: &Cell,
@@ -322,23 +529,123 @@ impl<'a, C> DrawCell<C> for Style<'a, C, Mono8BitFont> {
         } else {
             self.font
         };
-        let style = Mono8BitTextStyle::new(font, P::from(fg), P::from(bg));
+        let mut style = Mono8BitTextStyle::new(font, P::from(fg), P::from(bg));
         if cell.flags.contains(Flags::STRIKEOUT) {
-            // TODO
+            style.set_strikethrough_color(DecorationColor::TextColor);
         }
         if cell.flags.contains(Flags::UNDERLINE) {
-            // TODO
+            style.set_underline_color(match cell.underline_color {
+                Some(color) => DecorationColor::Custom(P::from(self.color_to_pixel(color))),
+                None => DecorationColor::TextColor,
+            });
         }
         let text = Text::with_text_style(
             s,
-            Point::new(
-                col as i32 * self.font.character_size.width as i32 + self.offset.0 as i32,
-                row as i32 * self.font.character_size.height as i32 + self.offset.1 as i32,
-            ),
+            self.cell_origin(row, col),
             style,
             TextStyle::with_baseline(Baseline::Top),
         );
         text.draw(display)?;
         Ok(())
     }
+
+    fn cell_size(&self) -> Size {
+        self.font.character_size
+    }
+}
+
+#[cfg(all(test, feature = "fontdue"))]
+mod tests {
+    use super::*;
+
+    const FONT_BYTES: &[u8] = include_bytes!("../examples/resources/RobotoMono-Regular.ttf");
+
+    #[test]
+    fn test_render_char_preview() {
+        let font = Mono8BitFont::from_font_bytes(FONT_BYTES, 16.0, Mono8BitFont::ASCII_GLYPHS);
+        let size = font.character_size();
+
+        let preview = font.render_char_preview('A');
+        assert_eq!(preview.len(), size.height as usize);
+        assert!(preview.iter().all(|row| row.len() == size.width as usize));
+        assert!(preview.iter().flatten().any(|&value| value > 0));
+
+        let space = font.render_char_preview(' ');
+        assert!(space.iter().flatten().all(|&value| value == 0));
+    }
+
+    #[test]
+    fn test_draw_shadow() {
+        use crate::test_display::Framebuffer;
+        use embedded_graphics::pixelcolor::Rgb888;
+
+        let font = Mono8BitFont::from_font_bytes(FONT_BYTES, 16.0, Mono8BitFont::ASCII_GLYPHS);
+        let size = font.character_size();
+        let (coverage, _) = font.glyph_coverage('l').unwrap();
+        let shadow = Rgb888::RED;
+        let style = Mono8BitTextStyle::new(&font, Rgb888::WHITE, Rgb888::BLACK)
+            .with_shadow(shadow, Point::new(2, 1));
+        let mut display = Framebuffer::new(size, Rgb888::BLUE);
+        style
+            .draw_string("l", Point::zero(), Baseline::Top, &mut display)
+            .unwrap();
+
+        let mut shadow_pixels = 0;
+        for y in 0..size.height {
+            for x in 0..size.width {
+                let value = |x: u32, y: u32| coverage[(y * size.width + x) as usize];
+                let pixel = display.pixel(x, y);
+                if pixel == shadow {
+                    shadow_pixels += 1;
+                    assert_eq!(value(x, y), 0);
+                    assert_eq!(value(x - 2, y - 1), 255);
+                } else if value(x, y) == 0 && (x < 2 || y < 1 || value(x - 2, y - 1) == 0) {
+                    assert_eq!(pixel, Rgb888::BLACK);
+                }
+            }
+        }
+        assert!(shadow_pixels > 0);
+    }
+
+    #[test]
+    fn test_glyph_coverage() {
+        let font = Mono8BitFont::from_font_bytes(FONT_BYTES, 16.0, Mono8BitFont::ASCII_GLYPHS);
+        let (coverage, size) = font.glyph_coverage('A').unwrap();
+        assert_eq!(size, font.character_size());
+        assert_eq!(coverage.len(), (size.width * size.height) as usize);
+        assert_eq!(coverage, font.render_char_preview('A').concat());
+        assert_eq!(font.glyph_coverage('é'), None);
+    }
+
+    #[test]
+    fn test_fit() {
+        let display = Size::new(800, 480);
+        let font = Mono8BitFont::fit(FONT_BYTES, 80, 24, display, Mono8BitFont::ASCII_GLYPHS);
+        let size = font.character_size();
+        assert!(size.width * 80 <= display.width && size.height * 24 <= display.height);
+        // The font that fits half the grid is too large for all of it
+        let larger = Mono8BitFont::fit(FONT_BYTES, 40, 12, display, Mono8BitFont::ASCII_GLYPHS);
+        let larger = larger.character_size();
+        assert!(larger.width > size.width);
+        assert!(larger.width * 80 > display.width || larger.height * 24 > display.height);
+    }
+
+    #[test]
+    fn test_from_font_ranges() {
+        let font = Mono8BitFont::from_font_ranges(FONT_BYTES, 16.0, &[('A', 'C'), ('x', 'z')]);
+        let ascii = Mono8BitFont::from_font_bytes(FONT_BYTES, 16.0, Mono8BitFont::ASCII_GLYPHS);
+        assert_eq!(font.glyph_coverage('B'), ascii.glyph_coverage('B'));
+        assert_eq!(font.glyph_coverage('y'), ascii.glyph_coverage('y'));
+        assert_eq!(font.glyph_coverage('D'), None);
+        // Without a `?` glyph, the first one is drawn instead
+        assert_eq!(font.render_char_preview('D'), font.render_char_preview('A'));
+    }
+
+    #[test]
+    fn test_memory_usage() {
+        let font = Mono8BitFont::from_font_ranges(FONT_BYTES, 16.0, &[('A', 'C'), ('x', 'z')]);
+        let size = font.character_size();
+        // The 6 glyphs in the ranges
+        assert_eq!(font.memory_usage(), (size.width * size.height) as usize * 6);
+    }
 }
@@ -0,0 +1,821 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+use crate::Console;
+use crate::ansi::Handler;
+use crate::cell::{Cell, Flags};
+use crate::color::{Color, NamedColor};
+use crate::format::Alignment;
+use crate::style::{DrawCell, Style};
+
+/// The bar color used when no chart palette is set
+const DEFAULT_BAR_COLOR: Color = Color::Named(NamedColor::Green);
+
+/// The largest table of common subsequence lengths that [`Console::write_diff_highlight`] builds, beyond which every character is replaced
+const MAX_DIFF_TABLE_SIZE: usize = 4096;
+
+/// The rows of each digit of [`ClockStyle::SevenSegment`]
+const SEVEN_SEGMENT_DIGITS: [[&str; 3]; 10] = [
+    ["╔═╗", "║ ║", "╚═╝"],
+    ["  ║", "  ║", "  ║"],
+    ["══╗", "╔═╝", "╚══"],
+    ["══╗", " ═╣", "══╝"],
+    ["║ ║", "╚═╣", "  ║"],
+    ["╔══", "╚═╗", "══╝"],
+    ["╔══", "╠═╗", "╚═╝"],
+    ["══╗", "  ║", "  ║"],
+    ["╔═╗", "╠═╣", "╚═╝"],
+    ["╔═╗", "╚═╣", "══╝"],
+];
+
+/// The pixels of each digit of [`ClockStyle::BlockFont`], 3 wide and 5 tall, with the most significant bit on the left
+const BLOCK_FONT_DIGITS: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b001, 0b001, 0b001],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+];
+
+/// The colors of a heatmap drawn with [`Console::write_heatmap`]
+///
+/// Values between `low` and `mid`, and between `mid` and `high`, are blended with [`Color::blend`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct HeatmapPalette {
+    /// The color of a value of 0.0
+    pub low: Color,
+    /// The color of a value of 0.5
+    pub mid: Color,
+    /// The color of a value of 1.0
+    pub high: Color,
+}
+
+impl Default for HeatmapPalette {
+    /// Blue, through yellow, to red
+    fn default() -> Self {
+        HeatmapPalette {
+            low: Color::Named(NamedColor::Blue),
+            mid: Color::Named(NamedColor::Yellow),
+            high: Color::Named(NamedColor::Red),
+        }
+    }
+}
+
+impl HeatmapPalette {
+    /// The color of a `value` from 0.0 to 1.0. Values out of range are clamped, and NaN is `low`.
+    pub fn color(&self, value: f32) -> Color {
+        let value = if value.is_nan() {
+            0.0
+        } else {
+            value.clamp(0.0, 1.0)
+        };
+        let (from, to, fraction) = if value < 0.5 {
+            (self.low, self.mid, value * 2.0)
+        } else {
+            (self.mid, self.high, value * 2.0 - 1.0)
+        };
+        match fraction {
+            0.0 => from,
+            1.0 => to,
+            _ => from.blend(to, (fraction * 255.0 + 0.5) as u8),
+        }
+    }
+}
+
+/// The index of the dot of a braille character at each of its 4 rows and 2 columns, as used by [`Console::write_braille_dots`]
+const BRAILLE_DOTS: [[usize; 2]; 4] = [[0, 3], [1, 4], [2, 5], [6, 7]];
+
+/// The characters used by [`Console::write_clock_display`]
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum ClockStyle {
+    /// Digits drawn with double line box drawing characters
+    #[default]
+    SevenSegment,
+    /// Digits drawn with block elements (`█`, `▀` and `▄`)
+    BlockFont,
+}
+
+impl ClockStyle {
+    /// The character at `row`, `col` of the 3×3 glyph of `digit`
+    fn digit_char(self, digit: u8, row: usize, col: usize) -> char {
+        let digit = digit as usize % 10;
+        match self {
+            ClockStyle::SevenSegment => SEVEN_SEGMENT_DIGITS[digit][row]
+                .chars()
+                .nth(col)
+                .unwrap_or(' '),
+            ClockStyle::BlockFont => {
+                let pixels = BLOCK_FONT_DIGITS[digit];
+                let pixel = |y: usize| pixels.get(y).is_some_and(|bits| bits & (0b100 >> col) != 0);
+                block_char(pixel(row * 2), pixel(row * 2 + 1))
+            }
+        }
+    }
+
+    /// The character at `row` of the 1 column wide colon glyph
+    fn colon_char(self, row: usize) -> char {
+        match (self, row) {
+            (ClockStyle::SevenSegment, 1) => ':',
+            (ClockStyle::BlockFont, 0 | 1) => '▄',
+            _ => ' ',
+        }
+    }
+}
+
+/// The block element with the top and bottom halves filled as given
+fn block_char(top: bool, bottom: bool) -> char {
+    match (top, bottom) {
+        (true, true) => '█',
+        (true, false) => '▀',
+        (false, true) => '▄',
+        (false, false) => ' ',
+    }
+}
+
+impl<'a, C, F> Console<'a, C, F>
+where
+    Style<'a, C, F>: DrawCell<C>,
+{
+    /// Set the colors used for the bars of charts, in turn. By default every bar is green.
+    pub fn set_chart_palette(&mut self, palette: &[Color]) {
+        self.chart_palette = palette.to_vec();
+    }
+
+    /// The color of the `i`th bar of a chart
+    fn bar_color(&self, i: usize) -> Color {
+        match self.chart_palette.len() {
+            0 => DEFAULT_BAR_COLOR,
+            n => self.chart_palette[i % n],
+        }
+    }
+
+    /// Draw a column chart in the `height` rows from `start_row`, with bars of `bar_width` columns starting at `col`.
+    ///
+    /// Each of `values` is from 0.0 to 1.0, and fills that fraction of `height` from the bottom with `█`. Bar colors come from the [chart palette](Console::set_chart_palette), and the other attributes from the current ones. The cursor is not moved, and bars that don't fit on the console are cut off.
+    pub fn write_column_chart(
+        &mut self,
+        start_row: usize,
+        col: usize,
+        height: usize,
+        values: &[f32],
+        bar_width: usize,
+    ) {
+        let blank = Cell {
+            c: ' ',
+            ..self.inner.temp
+        };
+        for (i, &value) in values.iter().enumerate() {
+            let filled = bar_length(value, height);
+            let bar = Cell {
+                c: '█',
+                fg: self.bar_color(i),
+                ..blank
+            };
+            let start_col = col + i * bar_width;
+            for row in 0..height {
+                let cell = if height - row <= filled { bar } else { blank };
+                for col in start_col..start_col + bar_width {
+                    self.inner.buf.write(start_row + row, col, cell);
+                }
+            }
+        }
+    }
+
+    /// Write a horizontal bar graph at the cursor, with one row for each `(value, label)` of `values`.
+    ///
+    /// Each row has the label, padded to the length of the longest one, a bar of `bar_char` that is `bar_width` long when `value` is `max_value`, and the value, right-aligned. Bar colors come from the [chart palette](Console::set_chart_palette) if one is set, and are the current foreground color otherwise. There is no newline after the last row.
+    pub fn write_bar_graph(
+        &mut self,
+        values: &[(f32, &str)],
+        max_value: f32,
+        bar_width: usize,
+        bar_char: char,
+    ) {
+        let numbers = values
+            .iter()
+            .map(|(value, _)| format!("{}", value))
+            .collect::<Vec<String>>();
+        let label_width = values
+            .iter()
+            .map(|(_, label)| label.chars().count())
+            .max()
+            .unwrap_or(0);
+        let number_width = numbers.iter().map(String::len).max().unwrap_or(0);
+
+        let fg = self.inner.temp.fg;
+        for (i, ((value, label), number)) in values.iter().zip(&numbers).enumerate() {
+            if i > 0 {
+                let _ = self.write_str("\r\n");
+            }
+            self.write_padded(label, label_width, Alignment::Left, ' ');
+            let _ = self.write_char(' ');
+
+            let fraction = if max_value > 0.0 {
+                value / max_value
+            } else {
+                0.0
+            };
+            let filled = bar_length(fraction, bar_width);
+            if !self.chart_palette.is_empty() {
+                self.inner.temp.fg = self.bar_color(i);
+            }
+            for _ in 0..filled {
+                self.inner.input(bar_char);
+            }
+            self.inner.temp.fg = fg;
+            for _ in filled..bar_width {
+                self.inner.input(' ');
+            }
+
+            let _ = self.write_char(' ');
+            self.write_padded(number, number_width, Alignment::Right, ' ');
+        }
+    }
+}
+
+impl<'a, C, F> Console<'a, C, F>
+where
+    Style<'a, C, F>: DrawCell<C>,
+{
+    /// Write each line of `art` centered on its own row, starting at the cursor's row, e.g. for a logo on a boot screen.
+    ///
+    /// Line `i` is written in the foreground color `row_colors[i]`, cycling through `row_colors` if there are fewer colors than lines. With no colors the current foreground is used. Empty lines are just a newline, and the cursor is left at the start of the row after the last line.
+    pub fn write_terminal_logo(&mut self, art: &[&str], row_colors: &[Color]) {
+        let fg = self.inner.temp.fg;
+        for (i, line) in art.iter().enumerate() {
+            if !line.is_empty() {
+                let padding = self.columns().saturating_sub(self.measure_text_width(line)) / 2;
+                let (row, _) = self.get_cursor_position();
+                self.inner.goto(row, padding);
+                if !row_colors.is_empty() {
+                    self.inner.temp.fg = row_colors[i % row_colors.len()];
+                }
+                let _ = self.write_str(line);
+                self.inner.temp.fg = fg;
+            }
+            let _ = self.write_str("\r\n");
+        }
+    }
+
+    /// Draw `data` as a grid of `grid_rows` by `grid_cols` cells from `start_row`, `start_col`, e.g. for a thermal camera.
+    ///
+    /// `data` is in row-major order, and each of its values from 0.0 to 1.0 is drawn as a `█` in the [color](HeatmapPalette::color) of `palette`. Cells without a value in `data` are not written. The background is the current one, the cursor is not moved, and cells that don't fit on the console are cut off.
+    pub fn write_heatmap(
+        &mut self,
+        start_row: usize,
+        start_col: usize,
+        grid_rows: usize,
+        grid_cols: usize,
+        data: &[f32],
+        palette: &HeatmapPalette,
+    ) {
+        if grid_cols == 0 {
+            return;
+        }
+        for (i, &value) in data.iter().take(grid_rows * grid_cols).enumerate() {
+            let cell = Cell {
+                c: '█',
+                fg: palette.color(value),
+                ..self.inner.temp
+            };
+            self.inner
+                .buf
+                .write(start_row + i / grid_cols, start_col + i % grid_cols, cell);
+        }
+    }
+
+    /// Fill `height` rows and `width` columns from `row`, `col` with spaces on a background of `color`, e.g. to preview a color.
+    ///
+    /// The cells have no other attributes, the cursor is not moved, and cells that don't fit on the console are cut off.
+    pub fn write_color_swatch(
+        &mut self,
+        row: usize,
+        col: usize,
+        color: Color,
+        width: usize,
+        height: usize,
+    ) {
+        let cell = Cell {
+            c: ' ',
+            bg: color,
+            underline_color: None,
+            flags: Flags::empty(),
+            ..self.inner.temp
+        };
+        for swatch_row in row..row + height {
+            for swatch_col in col..col + width {
+                self.inner.buf.write(swatch_row, swatch_col, cell);
+            }
+        }
+    }
+
+    /// Write a [swatch](Console::write_color_swatch) of `swatch_width` by `swatch_height` cells for each of `colors`, from `row`, `col`, with `cols_per_row` swatches on each row of the grid.
+    ///
+    /// Swatches are laid out left to right and then top to bottom, with no space between them. The cursor is not moved, and cells that don't fit on the console are cut off.
+    pub fn write_color_grid(
+        &mut self,
+        row: usize,
+        col: usize,
+        colors: &[Color],
+        cols_per_row: usize,
+        swatch_width: usize,
+        swatch_height: usize,
+    ) {
+        if cols_per_row == 0 {
+            return;
+        }
+        for (i, &color) in colors.iter().enumerate() {
+            self.write_color_swatch(
+                row + i / cols_per_row * swatch_height,
+                col + i % cols_per_row * swatch_width,
+                color,
+                swatch_width,
+                swatch_height,
+            );
+        }
+    }
+
+    /// Write a braille character (U+2800 to U+28FF) for each dot pattern of `data`, from `row`, `col` to the right.
+    ///
+    /// `data[i][n]` is whether dot `n + 1` of the `i`th character is raised. Dots 1, 2, 3 and 7 are the left column from top to bottom, and dots 4, 5, 6 and 8 the right column. The current attributes are used, the cursor is not moved, and characters that don't fit on the console are cut off.
+    pub fn write_braille_dots(&mut self, row: usize, col: usize, data: &[[bool; 8]]) {
+        for (i, dots) in data.iter().enumerate() {
+            let cell = Cell {
+                c: braille_char(dots),
+                ..self.inner.temp
+            };
+            self.inner.buf.write(row, col + i, cell);
+        }
+    }
+
+    /// Draw `bitmap` in braille characters, in the `height_cells` rows and `width_cells` columns from `row`, `col`. Each character shows 2 by 4 pixels.
+    ///
+    /// `bitmap` has one bit per pixel, set for raised dots, with the most significant bit of each byte on the left. It is `width_cells * 2` pixels wide and `height_cells * 4` tall, and each row of pixels starts on a new byte. Pixels past the end of `bitmap` are not raised. The current attributes are used, the cursor is not moved, and characters that don't fit on the console are cut off.
+    pub fn write_braille_bitmap(
+        &mut self,
+        row: usize,
+        col: usize,
+        width_cells: usize,
+        height_cells: usize,
+        bitmap: &[u8],
+    ) {
+        let bytes_per_row = (width_cells * 2).div_ceil(8);
+        let pixel = |x: usize, y: usize| {
+            bitmap
+                .get(y * bytes_per_row + x / 8)
+                .is_some_and(|byte| byte & (0x80 >> (x % 8)) != 0)
+        };
+        for cell_row in 0..height_cells {
+            for cell_col in 0..width_cells {
+                let mut dots = [false; 8];
+                for (y, indices) in BRAILLE_DOTS.iter().enumerate() {
+                    for (x, &index) in indices.iter().enumerate() {
+                        dots[index] = pixel(cell_col * 2 + x, cell_row * 4 + y);
+                    }
+                }
+                self.write_braille_dots(row + cell_row, col + cell_col, &[dots]);
+            }
+        }
+    }
+
+    /// Write the `visible_rows` entries of a scrolling menu from `scroll_offset`, each on its own line, with the `selected` entry in reverse video.
+    ///
+    /// Entries are padded to the width of the console, so that the selection is highlighted across the whole row, and entries that are too long are truncated as in [`Console::write_padded`]. Rows after the last entry are blank. Lines start at the cursor, which should be in the first column, and there is no newline after the last row.
+    pub fn write_menu(
+        &mut self,
+        entries: &[&str],
+        selected: usize,
+        scroll_offset: usize,
+        visible_rows: usize,
+    ) {
+        let temp = self.inner.temp;
+        for i in 0..visible_rows {
+            if i > 0 {
+                let _ = self.write_str("\r\n");
+            }
+            let index = scroll_offset + i;
+            if index == selected {
+                self.inner.temp.flags.insert(Flags::INVERSE);
+            }
+            let entry = entries.get(index).copied().unwrap_or("");
+            self.write_padded(entry, self.columns(), Alignment::Left, ' ');
+            self.inner.temp = temp;
+        }
+    }
+
+    /// Write `new_str`, highlighting how it differs from `old` character by character, e.g. to show a changed setting.
+    ///
+    /// Characters only in `old` are written struck through in `del_color`, and characters only in `new_str` in `add_color`, each before the following common characters. Characters in both are written with the current attributes. The diff keeps the longest common subsequence of the strings, but strings that differ in more than a few dozen characters are shown as deleting all of `old` and adding all of `new_str`, to bound the memory used.
+    pub fn write_diff_highlight(
+        &mut self,
+        old: &str,
+        new_str: &str,
+        del_color: Color,
+        add_color: Color,
+    ) {
+        let old = old.chars().collect::<Vec<_>>();
+        let new = new_str.chars().collect::<Vec<_>>();
+        let temp = self.inner.temp;
+        for op in diff_chars(&old, &new) {
+            let c = match op {
+                DiffOp::Keep(c) => c,
+                DiffOp::Delete(c) => {
+                    self.inner.temp.fg = del_color;
+                    self.inner.temp.flags.insert(Flags::STRIKEOUT);
+                    c
+                }
+                DiffOp::Add(c) => {
+                    self.inner.temp.fg = add_color;
+                    c
+                }
+            };
+            let _ = self.write_char(c);
+            self.inner.temp = temp;
+        }
+    }
+
+    /// Draw a 3 row tall `HH:MM:SS` clock with its top left corner at `row`, `col`.
+    ///
+    /// Digits are 3 columns wide, colons are 1, and there is a blank column between each of them, for a width of 27 columns. Values over 99 only show their last two digits. The current attributes are used, the cursor is not moved, and the parts of the clock that don't fit on the console are cut off.
+    pub fn write_clock_display(
+        &mut self,
+        row: usize,
+        col: usize,
+        hours: u8,
+        minutes: u8,
+        seconds: u8,
+        style: ClockStyle,
+    ) {
+        // Each glyph is a digit, or `None` for a colon
+        let glyphs = [
+            Some(hours / 10),
+            Some(hours),
+            None,
+            Some(minutes / 10),
+            Some(minutes),
+            None,
+            Some(seconds / 10),
+            Some(seconds),
+        ];
+        let blank = Cell {
+            c: ' ',
+            ..self.inner.temp
+        };
+        let mut col = col;
+        for (i, glyph) in glyphs.into_iter().enumerate() {
+            let width = if glyph.is_some() { 3 } else { 1 };
+            for glyph_row in 0..3 {
+                for glyph_col in 0..width {
+                    let c = match glyph {
+                        Some(digit) => style.digit_char(digit, glyph_row, glyph_col),
+                        None => style.colon_char(glyph_row),
+                    };
+                    self.inner
+                        .buf
+                        .write(row + glyph_row, col + glyph_col, Cell { c, ..blank });
+                }
+                if i < glyphs.len() - 1 {
+                    self.inner.buf.write(row + glyph_row, col + width, blank);
+                }
+            }
+            col += width + 1;
+        }
+    }
+}
+
+/// The braille character with each dot of `dots` raised, where `dots[i]` is dot `i + 1`
+fn braille_char(dots: &[bool; 8]) -> char {
+    let bits = dots
+        .iter()
+        .enumerate()
+        .fold(0, |bits, (i, &dot)| bits | (dot as u32) << i);
+    char::from_u32(0x2800 | bits).unwrap_or(' ')
+}
+
+/// An edit in the character-level diff of two strings
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum DiffOp {
+    Keep(char),
+    Delete(char),
+    Add(char),
+}
+
+/// The edits that turn `old` into `new`, keeping their longest common subsequence.
+///
+/// Only the part between their common prefix and suffix is diffed, and if that needs a table of more than [`MAX_DIFF_TABLE_SIZE`] entries, it is deleted and added in full.
+fn diff_chars(old: &[char], new: &[char]) -> Vec<DiffOp> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (a, b) = (
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    );
+
+    let mut ops = old[..prefix]
+        .iter()
+        .map(|&c| DiffOp::Keep(c))
+        .collect::<Vec<_>>();
+    let width = b.len() + 1;
+    if (a.len() + 1).saturating_mul(width) > MAX_DIFF_TABLE_SIZE {
+        ops.extend(a.iter().map(|&c| DiffOp::Delete(c)));
+        ops.extend(b.iter().map(|&c| DiffOp::Add(c)));
+    } else {
+        // `lengths[i * width + j]` is the length of the longest common subsequence of `a[i..]` and `b[j..]`
+        let mut lengths = vec![0u16; (a.len() + 1) * width];
+        for i in (0..a.len()).rev() {
+            for j in (0..b.len()).rev() {
+                lengths[i * width + j] = if a[i] == b[j] {
+                    lengths[(i + 1) * width + j + 1] + 1
+                } else {
+                    lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < a.len() && j < b.len() {
+            if a[i] == b[j] {
+                ops.push(DiffOp::Keep(a[i]));
+                (i, j) = (i + 1, j + 1);
+            } else if lengths[(i + 1) * width + j] >= lengths[i * width + j + 1] {
+                ops.push(DiffOp::Delete(a[i]));
+                i += 1;
+            } else {
+                ops.push(DiffOp::Add(b[j]));
+                j += 1;
+            }
+        }
+        ops.extend(a[i..].iter().map(|&c| DiffOp::Delete(c)));
+        ops.extend(b[j..].iter().map(|&c| DiffOp::Add(c)));
+    }
+    ops.extend(old[old.len() - suffix..].iter().map(|&c| DiffOp::Keep(c)));
+    ops
+}
+
+/// The number of cells filled by a bar for a `value` from 0.0 to 1.0, out of `length`
+fn bar_length(value: f32, length: usize) -> usize {
+    // NaN is clamped to 0
+    let value = if value > 0.0 { value.min(1.0) } else { 0.0 };
+    (value * length as f32 + 0.5) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_display::row_text;
+
+    #[test]
+    fn test_column_chart() {
+        let mut console = Console::new(6, 5, Style::default());
+        let blue = Color::Named(NamedColor::Blue);
+        console.set_chart_palette(&[DEFAULT_BAR_COLOR, blue]);
+        console.write_column_chart(1, 1, 4, &[1.0, 0.5, 0.1, f32::NAN], 1);
+
+        let rows = console.rows_iter().map(row_text).collect::<Vec<_>>();
+        assert_eq!(rows, ["      ", " █    ", " █    ", " ██   ", " ██   "]);
+        let bottom = console.rows_iter().nth(4).unwrap();
+        assert_eq!(bottom[1].fg(), DEFAULT_BAR_COLOR);
+        assert_eq!(bottom[2].fg(), blue);
+        assert_eq!(console.get_cursor_position(), (0, 0));
+    }
+
+    #[test]
+    fn test_bar_graph() {
+        let mut console = Console::new(16, 4, Style::default());
+        let red = Color::Named(NamedColor::Red);
+        console.write_str("\x1b[31m").unwrap();
+        console.write_bar_graph(
+            &[(10.0, "cpu"), (2.5, "memory"), (-1.0, "io")],
+            10.0,
+            4,
+            '#',
+        );
+
+        let rows = console.rows_iter().map(row_text).collect::<Vec<_>>();
+        assert_eq!(
+            rows,
+            [
+                "cpu    ####  10 ",
+                "memory #    2.5 ",
+                "io           -1 ",
+                "                "
+            ]
+        );
+        let row = console.rows_iter().next().unwrap();
+        assert_eq!(row[7].fg(), red);
+        assert_eq!(console.get_cursor_position(), (2, 15));
+
+        console.set_chart_palette(&[DEFAULT_BAR_COLOR]);
+        console.write_str("\x1b[H").unwrap();
+        console.write_bar_graph(&[(1.0, "a")], 1.0, 2, '=');
+        let row = console.rows_iter().next().unwrap();
+        assert_eq!(row[2].fg(), DEFAULT_BAR_COLOR);
+        assert_eq!(row[5].fg(), red);
+    }
+
+    #[test]
+    fn test_terminal_logo() {
+        let mut console = Console::new(8, 6, Style::default());
+        let (red, blue) = (
+            Color::Named(NamedColor::Red),
+            Color::Named(NamedColor::Blue),
+        );
+        console.write_str("\r\n").unwrap();
+        console.write_terminal_logo(&["ab", "", "abcde", "\x1b[1mxy\x1b[22m"], &[red, blue]);
+
+        let rows = console.rows_iter().map(row_text).collect::<Vec<_>>();
+        assert_eq!(
+            rows,
+            [
+                "        ", "   ab   ", "        ", " abcde  ", "   xy   ", "        "
+            ]
+        );
+        assert_eq!(console.rows_iter().nth(1).unwrap()[3].fg(), red);
+        assert_eq!(console.rows_iter().nth(3).unwrap()[1].fg(), red);
+        assert_eq!(console.rows_iter().nth(4).unwrap()[3].fg(), blue);
+        assert_eq!(console.get_cursor_position(), (5, 0));
+        assert_eq!(console.inner.temp.fg, Cell::default().fg());
+    }
+
+    #[test]
+    fn test_menu() {
+        let mut console = Console::new(6, 4, Style::default());
+        console.write_str("\r\n").unwrap();
+        console.write_menu(&["one", "two", "three", "sixteen"], 3, 2, 3);
+
+        let rows = console.rows_iter().map(row_text).collect::<Vec<_>>();
+        assert_eq!(rows, ["      ", "three ", "sixte…", "      "]);
+        let inverse = |row: usize| console.rows_iter().nth(row).unwrap()[0].flags();
+        assert_eq!(inverse(1), Flags::empty());
+        assert_eq!(inverse(2), Flags::INVERSE);
+        assert_eq!(inverse(3), Flags::empty());
+        assert_eq!(console.inner.temp.flags, Flags::empty());
+    }
+
+    #[test]
+    fn test_diff_chars() {
+        use DiffOp::*;
+        let diff = |old: &str, new: &str| {
+            diff_chars(
+                &old.chars().collect::<Vec<_>>(),
+                &new.chars().collect::<Vec<_>>(),
+            )
+        };
+        assert_eq!(
+            diff("baud=9600", "baud=19200"),
+            [
+                Keep('b'),
+                Keep('a'),
+                Keep('u'),
+                Keep('d'),
+                Keep('='),
+                Add('1'),
+                Keep('9'),
+                Delete('6'),
+                Add('2'),
+                Keep('0'),
+                Keep('0'),
+            ]
+        );
+        assert_eq!(diff("", "ab"), [Add('a'), Add('b')]);
+        assert_eq!(diff("ab", "ab"), [Keep('a'), Keep('b')]);
+
+        // Too large to diff, so replaced in full
+        let (old, new) = ("ab".repeat(50), "ba".repeat(50));
+        let ops = diff(&old, &new);
+        assert!(ops[..100].iter().all(|op| matches!(op, Delete(_))));
+        assert!(ops[100..].iter().all(|op| matches!(op, Add(_))));
+    }
+
+    #[test]
+    fn test_diff_highlight() {
+        let mut console = Console::new(8, 1, Style::default());
+        let (red, green) = (
+            Color::Named(NamedColor::Red),
+            Color::Named(NamedColor::Green),
+        );
+        console.write_str("\x1b[1m").unwrap();
+        console.write_diff_highlight("on", "off", red, green);
+
+        let row = console.rows_iter().next().unwrap();
+        assert_eq!(row_text(row), "onff    ");
+        assert_eq!(row[0].flags(), Flags::BOLD);
+        assert_eq!(
+            (row[1].fg(), row[1].flags()),
+            (red, Flags::BOLD | Flags::STRIKEOUT)
+        );
+        assert_eq!((row[2].fg(), row[2].flags()), (green, Flags::BOLD));
+        assert_eq!(row[3].fg(), green);
+        assert_eq!(console.inner.temp.flags, Flags::BOLD);
+    }
+
+    #[test]
+    fn test_heatmap() {
+        let palette = HeatmapPalette::default();
+        assert_eq!(palette.color(-1.0), palette.low);
+        assert_eq!(palette.color(f32::NAN), palette.low);
+        assert_eq!(palette.color(0.5), palette.mid);
+        assert_eq!(palette.color(2.0), palette.high);
+        assert_eq!(palette.color(0.25), palette.low.blend(palette.mid, 128));
+
+        let mut console = Console::new(4, 3, Style::default());
+        console.write_heatmap(1, 1, 2, 2, &[0.0, 0.5, 1.0, 0.25, 1.0], &palette);
+        let rows = console.rows_iter().map(row_text).collect::<Vec<_>>();
+        assert_eq!(rows, ["    ", " ██ ", " ██ "]);
+        let row = console.rows_iter().nth(2).unwrap();
+        assert_eq!(row[1].fg(), palette.high);
+        assert_eq!(row[2].fg(), palette.color(0.25));
+        assert_eq!(row[2].bg(), Cell::default().bg());
+    }
+
+    #[test]
+    fn test_color_grid() {
+        let red = Color::Named(NamedColor::Red);
+        let blue = Color::Named(NamedColor::Blue);
+        let green = Color::Named(NamedColor::Green);
+        let mut console = Console::new(5, 4, Style::default());
+        console.write_str("\x1b[7mab").unwrap();
+        console.write_color_grid(1, 0, &[red, blue, green], 2, 2, 2);
+        assert_eq!(console.get_cursor_position(), (0, 2));
+
+        let bgs = console
+            .rows_iter()
+            .map(|row| row.iter().map(|cell| cell.bg()).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let black = Cell::default().bg();
+        assert_eq!(bgs[0][2..], [black; 3]);
+        assert_eq!(bgs[1], [red, red, blue, blue, black]);
+        assert_eq!(bgs[2], [red, red, blue, blue, black]);
+        assert_eq!(bgs[3], [green, green, black, black, black]);
+        let row = console.rows_iter().nth(1).unwrap();
+        assert_eq!((row[0].c(), row[0].flags()), (' ', Flags::empty()));
+    }
+
+    #[test]
+    fn test_braille() {
+        let mut console = Console::new(4, 3, Style::default());
+        let mut all = [true; 8];
+        console.write_braille_dots(0, 2, &[[false; 8], all, all]);
+        all[7] = false;
+        console.write_braille_dots(
+            1,
+            0,
+            &[all, [true, false, false, false, false, false, false, true]],
+        );
+        assert_eq!(
+            console.rows_iter().map(row_text).collect::<Vec<_>>(),
+            ["  \u{2800}\u{28ff}", "\u{287f}\u{2881}  ", "    "]
+        );
+
+        // A 6 by 4 pixel bitmap, with the top row and the diagonal from the top left raised
+        let mut console = Console::new(4, 2, Style::default());
+        let bitmap = [0b1111_1100, 0b0100_0000, 0b0010_0000, 0b0001_0000];
+        console.write_braille_bitmap(1, 1, 3, 1, &bitmap);
+        assert_eq!(
+            console.rows_iter().map(row_text).collect::<Vec<_>>(),
+            ["    ", " ⠙⢍⠉"]
+        );
+    }
+
+    #[test]
+    fn test_clock_display() {
+        let mut console = Console::new(28, 4, Style::default());
+        console.write_clock_display(1, 1, 12, 34, 56, ClockStyle::SevenSegment);
+        let rows = console.rows_iter().map(row_text).collect::<Vec<_>>();
+        assert_eq!(
+            rows,
+            [
+                "                            ",
+                "   ║ ══╗   ══╗ ║ ║   ╔══ ╔══",
+                "   ║ ╔═╝ :  ═╣ ╚═╣ : ╚═╗ ╠═╗",
+                "   ║ ╚══   ══╝   ║   ══╝ ╚═╝",
+            ]
+        );
+        assert_eq!(console.get_cursor_position(), (0, 0));
+
+        console.write_clock_display(0, 0, 0, 7, 100, ClockStyle::BlockFont);
+        let rows = console
+            .rows_iter()
+            .map(|row| row_text(&row[..12]))
+            .collect::<Vec<_>>();
+        assert_eq!(rows[..3], ["█▀█ █▀█ ▄ █▀", "█ █ █ █ ▄ █ ", "▀▀▀ ▀▀▀   ▀▀"]);
+    }
+
+    #[test]
+    fn test_bar_length() {
+        assert_eq!(bar_length(0.5, 5), 3);
+        assert_eq!(bar_length(0.49, 5), 2);
+        assert_eq!(bar_length(2.0, 5), 5);
+        assert_eq!(bar_length(-1.0, 5), 0);
+    }
+}